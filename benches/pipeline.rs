@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+use simple_redis::{process_redis_conn, Database};
+
+const GET: &[u8] = b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n";
+const GET_REPLY: &[u8] = b"$5\r\nworld\r\n";
+
+async fn start_server() -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let db = Database::new();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(process_redis_conn(stream, db.clone()));
+        }
+    });
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.set_nodelay(true).unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut ok = [0u8; 5];
+    stream.read_exact(&mut ok).await.unwrap();
+    stream
+}
+
+// send all requests at once and wait for every reply, like `redis-benchmark -P <depth>`
+async fn pipelined(stream: &mut TcpStream, request: &[u8], reply: &mut [u8]) {
+    stream.write_all(request).await.unwrap();
+    stream.read_exact(reply).await.unwrap();
+}
+
+// one round trip per request
+async fn sequential(stream: &mut TcpStream, depth: usize) {
+    let mut reply = [0u8; GET_REPLY.len()];
+    for _ in 0..depth {
+        stream.write_all(GET).await.unwrap();
+        stream.read_exact(&mut reply).await.unwrap();
+    }
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut stream = rt.block_on(start_server());
+
+    let mut group = c.benchmark_group("get");
+    for depth in [1, 16, 128] {
+        let request = GET.repeat(depth);
+        let mut reply = vec![0u8; GET_REPLY.len() * depth];
+        group.throughput(Throughput::Elements(depth as u64));
+        group.bench_with_input(BenchmarkId::new("pipelined", depth), &depth, |b, _| {
+            b.iter(|| rt.block_on(pipelined(&mut stream, &request, &mut reply)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("sequential", depth),
+            &depth,
+            |b, &depth| b.iter(|| rt.block_on(sequential(&mut stream, depth))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
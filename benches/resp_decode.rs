@@ -0,0 +1,104 @@
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use simple_redis::{RespDecoder, RespFrame};
+
+// the size of one socket read
+const CHUNK: usize = 16 * 1024;
+
+fn bulk(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(format!("${}\r\n", data.len()).as_bytes());
+    buf.extend_from_slice(data);
+    buf.extend_from_slice(b"\r\n");
+}
+
+// MSET with `pairs` keys and values of `size` bytes
+fn mset(pairs: usize, size: usize) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", pairs * 2 + 1).into_bytes();
+    bulk(&mut buf, b"mset");
+    let value = vec![b'x'; size];
+    for i in 0..pairs {
+        bulk(&mut buf, format!("key:{}", i).as_bytes());
+        bulk(&mut buf, &value);
+    }
+    buf
+}
+
+fn get_pipeline(depth: usize) -> Vec<u8> {
+    b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n".repeat(depth)
+}
+
+// the whole input is already buffered
+fn decode_buffered(data: &[u8], mut decode: impl FnMut(&mut BytesMut) -> Option<RespFrame>) {
+    let mut buf = BytesMut::from(data);
+    while !buf.is_empty() {
+        decode(&mut buf).expect("complete frame");
+    }
+}
+
+// the input arrives one read at a time, decoding is retried after every read
+fn decode_chunked(data: &[u8], mut decode: impl FnMut(&mut BytesMut) -> Option<RespFrame>) {
+    let mut buf = BytesMut::with_capacity(CHUNK);
+    for chunk in data.chunks(CHUNK) {
+        buf.extend_from_slice(chunk);
+        while decode(&mut buf).is_some() {}
+    }
+    assert!(buf.is_empty());
+}
+
+// the baseline: like the decoder this one replaced, every attempt parses the buffered frame
+// from its start again, nothing is consumed until the whole frame is there
+fn restart_decode(buf: &mut BytesMut) -> Option<RespFrame> {
+    let mut attempt = buf.clone();
+    let frame = RespDecoder::new().decode(&mut attempt).unwrap()?;
+    *buf = attempt;
+    Some(frame)
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let inputs = [
+        ("mset-100x1k", mset(100, 1024)),
+        ("mset-1000x1k", mset(1000, 1024)),
+        ("mset-10x64k", mset(10, 64 * 1024)),
+        ("get-pipeline-1000", get_pipeline(1000)),
+    ];
+
+    let mut group = c.benchmark_group("resp_decode");
+    for (name, data) in inputs.iter() {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("restart/buffered", name),
+            data,
+            |b, data| b.iter(|| decode_buffered(data, restart_decode)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("streaming/buffered", name),
+            data,
+            |b, data| {
+                b.iter(|| {
+                    let mut decoder = RespDecoder::new();
+                    decode_buffered(data, |buf| decoder.decode(buf).unwrap())
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("restart/chunked", name),
+            data,
+            |b, data| b.iter(|| decode_chunked(data, restart_decode)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("streaming/chunked", name),
+            data,
+            |b, data| {
+                b.iter(|| {
+                    let mut decoder = RespDecoder::new();
+                    decode_chunked(data, |buf| decoder.decode(buf).unwrap())
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,232 @@
+use crate::cmd::{
+    commands, extract_args, lookup_command, parse_option, CommandArgs, CommandError,
+    CommandExecute, CommandSpec, CommandSubcommand, RESP_NULL,
+};
+use crate::database::Database;
+use crate::resp::{RespFrame, TArray, TError, TMap};
+
+impl CommandExecute for CommandArgs {
+    fn execute(self, _backend: &Database) -> RespFrame {
+        match self.subcommand {
+            CommandSubcommand::List => {
+                TArray::new(commands().iter().map(command_info).collect::<Vec<_>>()).into()
+            }
+            CommandSubcommand::Count => RespFrame::Integer(commands().len() as i64),
+            CommandSubcommand::Info(names) if names.is_empty() => {
+                TArray::new(commands().iter().map(command_info).collect::<Vec<_>>()).into()
+            }
+            CommandSubcommand::Info(names) => {
+                let infos = names
+                    .iter()
+                    .map(|name| {
+                        lookup_command(name)
+                            .map(command_info)
+                            .unwrap_or_else(|| RESP_NULL.clone())
+                    })
+                    .collect::<Vec<_>>();
+                TArray::new(infos).into()
+            }
+            CommandSubcommand::Docs(names) => {
+                let mut docs = TMap::new();
+                let specs: Vec<&CommandSpec> = if names.is_empty() {
+                    commands().iter().collect()
+                } else {
+                    // unknown names are left out of the reply
+                    names
+                        .iter()
+                        .filter_map(|name| lookup_command(name))
+                        .collect()
+                };
+                for spec in specs {
+                    docs.insert(spec.name.into(), command_docs(spec));
+                }
+                docs.into()
+            }
+            CommandSubcommand::GetKeys(request) => command_getkeys(request),
+        }
+    }
+}
+
+impl TryFrom<TArray> for CommandArgs {
+    type Error = CommandError;
+
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let Some(subcommand) = args.next() else {
+            return Ok(CommandArgs {
+                subcommand: CommandSubcommand::List,
+            });
+        };
+        let name = parse_option(subcommand)?;
+        let subcommand = match name.as_slice() {
+            b"COUNT" if args.len() == 0 => CommandSubcommand::Count,
+            b"INFO" => CommandSubcommand::Info(parse_names(args)?),
+            b"DOCS" => CommandSubcommand::Docs(parse_names(args)?),
+            b"GETKEYS" if args.len() > 0 => {
+                CommandSubcommand::GetKeys(TArray::new(args.collect::<Vec<RespFrame>>()))
+            }
+            b"COUNT" | b"GETKEYS" => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "wrong number of arguments for 'command|{}' command",
+                    String::from_utf8_lossy(&name).to_lowercase()
+                )))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try COMMAND HELP.",
+                    String::from_utf8_lossy(&name)
+                )))
+            }
+        };
+        Ok(CommandArgs { subcommand })
+    }
+}
+
+fn parse_names(args: impl Iterator<Item = RespFrame>) -> Result<Vec<Vec<u8>>, CommandError> {
+    args.map(|arg| match arg {
+        RespFrame::BulkString(name) => Ok(name.0.into()),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    })
+    .collect()
+}
+
+fn status_list(names: Vec<&'static str>) -> RespFrame {
+    TArray::new(names.into_iter().map(RespFrame::from).collect::<Vec<_>>()).into()
+}
+
+// name, arity, flags, first key, last key, step, acl categories, tips, key specs, subcommands
+fn command_info(spec: &CommandSpec) -> RespFrame {
+    TArray::new([
+        spec.name.as_bytes().into(),
+        RespFrame::Integer(spec.arity),
+        status_list(spec.flag_names()),
+        RespFrame::Integer(spec.first_key),
+        RespFrame::Integer(spec.last_key),
+        RespFrame::Integer(spec.step),
+        status_list(spec.acl_categories()),
+        TArray::new([]).into(),
+        TArray::new([]).into(),
+        TArray::new([]).into(),
+    ])
+    .into()
+}
+
+fn command_docs(spec: &CommandSpec) -> RespFrame {
+    let mut doc = TMap::new();
+    doc.insert("summary".into(), spec.summary.as_bytes().into());
+    doc.insert("group".into(), spec.group.as_bytes().into());
+    doc.into()
+}
+
+fn command_getkeys(request: TArray) -> RespFrame {
+    let spec = match request.first() {
+        Some(RespFrame::BulkString(name)) => lookup_command(name),
+        _ => None,
+    };
+    let Some(spec) = spec else {
+        return TError::new("ERR Invalid command specified").into();
+    };
+    if !spec.check_arity(request.len()) {
+        return TError::new("ERR Invalid number of arguments specified for command").into();
+    }
+    let positions = spec.key_positions(request.len());
+    if positions.is_empty() {
+        return TError::new("ERR The command has no key arguments").into();
+    }
+    let keys = positions
+        .into_iter()
+        .filter_map(|i| request.get(i).cloned())
+        .collect::<Vec<_>>();
+    TArray::new(keys).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::Command;
+    use crate::resp::{decode_frame, TBulkString};
+    use crate::test_util::run;
+
+    use super::*;
+
+    #[test]
+    fn test_command_count_and_list() -> Result<()> {
+        let backend = Database::new();
+        let count = run(&backend, b"*2\r\n$7\r\ncommand\r\n$5\r\ncount\r\n")?;
+        assert_eq!(count, RespFrame::Integer(commands().len() as i64));
+
+        let RespFrame::Array(list) = run(&backend, b"*1\r\n$7\r\nCOMMAND\r\n")? else {
+            panic!("COMMAND must reply with an array");
+        };
+        assert_eq!(list.len(), commands().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_info() -> Result<()> {
+        let backend = Database::new();
+        let reply = run(
+            &backend,
+            b"*4\r\n$7\r\ncommand\r\n$4\r\ninfo\r\n$3\r\nGET\r\n$3\r\nfoo\r\n",
+        )?;
+        let RespFrame::Array(infos) = reply else {
+            panic!("COMMAND INFO must reply with an array");
+        };
+        let RespFrame::Array(ref get) = infos[0] else {
+            panic!("expected the get entry");
+        };
+        assert_eq!(get[0], b"get".into());
+        assert_eq!(get[1], RespFrame::Integer(2));
+        assert_eq!(
+            get[2],
+            TArray::new([RespFrame::from("readonly"), RespFrame::from("fast")]).into()
+        );
+        assert_eq!(get[3..6], [1.into(), 1.into(), 1.into()]);
+        assert_eq!(infos[1], RESP_NULL.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_docs() -> Result<()> {
+        let backend = Database::new();
+        let reply = run(
+            &backend,
+            b"*4\r\n$7\r\ncommand\r\n$4\r\ndocs\r\n$4\r\nhset\r\n$3\r\nfoo\r\n",
+        )?;
+        let RespFrame::Map(docs) = reply else {
+            panic!("COMMAND DOCS must reply with a map");
+        };
+        assert_eq!(docs.len(), 1);
+        let Some(RespFrame::Map(hset)) = docs.get(b"hset".as_slice()) else {
+            panic!("expected the hset docs");
+        };
+        assert_eq!(hset.get(b"group".as_slice()), Some(&b"hash".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_getkeys() -> Result<()> {
+        let backend = Database::new();
+        let reply = run(
+            &backend,
+            b"*5\r\n$7\r\ncommand\r\n$7\r\ngetkeys\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n",
+        )?;
+        assert_eq!(reply, TArray::new([TBulkString::from("k").into()]).into());
+
+        let reply = run(
+            &backend,
+            b"*4\r\n$7\r\ncommand\r\n$7\r\ngetkeys\r\n$4\r\necho\r\n$1\r\nv\r\n",
+        )?;
+        assert_eq!(
+            reply,
+            TError::new("ERR The command has no key arguments").into()
+        );
+
+        let mut buf = BytesMut::from(&b"*2\r\n$7\r\ncommand\r\n$3\r\nfoo\r\n"[..]);
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        assert!(Command::try_from(frame).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use crate::cmd::{
+    extract_args, is_command, parse_integer, parse_key, parse_option, CommandError, CommandExecute,
+    DbSizeArgs, FlushArgs, MoveArgs, SelectArgs, SwapDbArgs, RESP_OK, RESP_ONE, RESP_ZERO,
+};
+use crate::database::Database;
+use crate::network::Session;
+use crate::resp::{RespFrame, TArray};
+
+fn out_of_range() -> RespFrame {
+    CommandError::InvalidArgument("DB index is out of range".to_string()).into()
+}
+
+impl CommandExecute for SelectArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // outside of a connection (e.g. an aof replay) the switch applies to a throwaway session
+        self.select(backend, &mut Session::new(backend))
+    }
+}
+
+impl SelectArgs {
+    // switch the db the following commands of the connection apply to
+    pub(crate) fn select(self, backend: &Database, session: &mut Session) -> RespFrame {
+        match usize::try_from(self.index) {
+            Ok(index) if index < backend.databases() => {
+                session.db = index;
+                RESP_OK.clone()
+            }
+            _ => out_of_range(),
+        }
+    }
+}
+
+impl CommandExecute for MoveArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let Some(target) = usize::try_from(self.db)
+            .ok()
+            .and_then(|index| backend.select(index))
+        else {
+            return out_of_range();
+        };
+        if target.index == backend.index {
+            return CommandError::InvalidArgument(
+                "source and destination objects are the same".to_string(),
+            )
+            .into();
+        }
+        if backend.move_key(&self.key, &target) {
+            RESP_ONE.clone()
+        } else {
+            RESP_ZERO.clone()
+        }
+    }
+}
+
+impl CommandExecute for SwapDbArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let (Ok(first), Ok(second)) = (usize::try_from(self.first), usize::try_from(self.second))
+        else {
+            return out_of_range();
+        };
+        if backend.swapdb(first, second) {
+            RESP_OK.clone()
+        } else {
+            out_of_range()
+        }
+    }
+}
+
+impl CommandExecute for FlushArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if self.all {
+            backend.flushall(self.lazy);
+        } else {
+            backend.flushdb(self.lazy);
+        }
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecute for DbSizeArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        RespFrame::Integer(backend.dbsize() as i64)
+    }
+}
+
+impl TryFrom<TArray> for SelectArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SelectArgs {
+            index: parse_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for MoveArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(MoveArgs {
+            key: parse_key(args.next())?,
+            db: parse_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for SwapDbArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let first = parse_integer(args.next())
+            .map_err(|_| CommandError::InvalidArgument("invalid first DB index".to_string()))?;
+        let second = parse_integer(args.next())
+            .map_err(|_| CommandError::InvalidArgument("invalid second DB index".to_string()))?;
+        Ok(SwapDbArgs { first, second })
+    }
+}
+
+impl TryFrom<TArray> for FlushArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let all = is_command(&value, "flushall");
+        let mut args = extract_args(value, 1)?.into_iter();
+        let lazy = match args.next().map(parse_option).transpose()?.as_deref() {
+            None | Some(b"SYNC") => false,
+            Some(b"ASYNC") => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(FlushArgs { all, lazy })
+    }
+}
+
+impl TryFrom<TArray> for DbSizeArgs {
+    type Error = CommandError;
+    fn try_from(_value: TArray) -> Result<Self, Self::Error> {
+        Ok(DbSizeArgs {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::Command;
+    use crate::resp::{decode_frame, TError};
+    use crate::test_util::run;
+
+    use super::*;
+
+    fn select(request: &[u8]) -> Result<SelectArgs> {
+        Ok(decode_frame::<TArray>(&mut BytesMut::from(request))?.try_into()?)
+    }
+
+    #[test]
+    fn test_select() -> Result<()> {
+        let backend = Database::new();
+        let mut session = Session::new(&backend);
+
+        let args = select(b"*2\r\n$6\r\nselect\r\n$1\r\n3\r\n")?;
+        assert_eq!(args.select(&backend, &mut session), RESP_OK.clone());
+        assert_eq!(session.db, 3);
+
+        for index in ["16", "-1"] {
+            let request = format!("*2\r\n$6\r\nselect\r\n${}\r\n{}\r\n", index.len(), index);
+            let args = select(request.as_bytes())?;
+            assert_eq!(
+                args.select(&backend, &mut session),
+                TError::new("ERR DB index is out of range").into()
+            );
+        }
+        assert_eq!(session.db, 3);
+
+        let result = select(b"*2\r\n$6\r\nselect\r\n$1\r\nx\r\n");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move() -> Result<()> {
+        let backend = Database::new();
+        let other = backend.select(1).expect("db 1 exists");
+        backend.set_with_expire(
+            b"a".to_vec(),
+            b"1".into(),
+            crate::database::now_ms() + 60_000,
+        );
+        other.set(b"b".to_vec(), b"2".into());
+        backend.set(b"b".to_vec(), b"3".into());
+
+        let request = b"*3\r\n$4\r\nmove\r\n$1\r\na\r\n$1\r\n1\r\n";
+        assert_eq!(run(&backend, request)?, RESP_ONE.clone());
+        assert!(!backend.exists(b"a"));
+        assert_eq!(other.get(b"a")?, Some(b"1".into()));
+        assert!(other.expire_time(b"a").is_some());
+
+        // an existing destination key is left alone
+        let request = b"*3\r\n$4\r\nmove\r\n$1\r\nb\r\n$1\r\n1\r\n";
+        assert_eq!(run(&backend, request)?, RESP_ZERO.clone());
+        assert_eq!(backend.get(b"b")?, Some(b"3".into()));
+
+        let request = b"*3\r\n$4\r\nmove\r\n$1\r\nb\r\n$1\r\n0\r\n";
+        assert_eq!(
+            run(&backend, request)?,
+            TError::new("ERR source and destination objects are the same").into()
+        );
+        let request = b"*3\r\n$4\r\nmove\r\n$1\r\nb\r\n$2\r\n99\r\n";
+        assert_eq!(
+            run(&backend, request)?,
+            TError::new("ERR DB index is out of range").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_swapdb() -> Result<()> {
+        let backend = Database::new();
+        backend.set(b"a".to_vec(), b"0".into());
+        backend
+            .select(2)
+            .expect("db 2 exists")
+            .set(b"b".to_vec(), b"2".into());
+
+        let request = b"*3\r\n$6\r\nswapdb\r\n$1\r\n0\r\n$1\r\n2\r\n";
+        assert_eq!(run(&backend, request)?, RESP_OK.clone());
+        // the handle keeps the db it resolved, a new one sees the swapped content
+        let db0 = backend.select(0).expect("db 0 exists");
+        let db2 = backend.select(2).expect("db 2 exists");
+        assert_eq!(db0.get(b"b")?, Some(b"2".into()));
+        assert_eq!(db2.get(b"a")?, Some(b"0".into()));
+
+        let request = b"*3\r\n$6\r\nswapdb\r\n$1\r\n0\r\n$2\r\n16\r\n";
+        assert_eq!(
+            run(&backend, request)?,
+            TError::new("ERR DB index is out of range").into()
+        );
+        let request = b"*3\r\n$6\r\nswapdb\r\n$1\r\nx\r\n$1\r\n1\r\n";
+        let cmd: Result<Command, CommandError> =
+            decode_frame::<TArray>(&mut BytesMut::from(&request[..]))?.try_into();
+        assert_eq!(
+            cmd.map(|_| ()).map_err(|e| e.to_string()),
+            Err("ERR invalid first DB index".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flushdb_flushall_dbsize() -> Result<()> {
+        let backend = Database::new();
+        let other = backend.select(1).expect("db 1 exists");
+        for i in 0..100 {
+            backend.set(format!("k{}", i).into_bytes(), b"v".into());
+            other.sadd(b"s".to_vec(), format!("m{}", i).into_bytes())?;
+        }
+        let dbsize = b"*1\r\n$6\r\ndbsize\r\n";
+        assert_eq!(run(&backend, dbsize)?, RespFrame::Integer(100));
+        assert_eq!(run(&other, dbsize)?, RespFrame::Integer(1));
+
+        let flushdb = b"*1\r\n$7\r\nflushdb\r\n";
+        assert_eq!(run(&backend, flushdb)?, RESP_OK.clone());
+        let backend = backend.select(0).expect("db 0 exists");
+        assert_eq!(run(&backend, dbsize)?, RespFrame::Integer(0));
+        assert!(backend.select(1).is_some_and(|db| db.dbsize() == 1));
+
+        let flushall = b"*2\r\n$8\r\nflushall\r\n$5\r\nasync\r\n";
+        assert_eq!(run(&backend, flushall)?, RESP_OK.clone());
+        assert!(backend.select(1).is_some_and(|db| db.dbsize() == 0));
+
+        let flushall = b"*2\r\n$8\r\nflushall\r\n$4\r\nlazy\r\n";
+        assert!(run(&backend, flushall).is_err());
+        Ok(())
+    }
+}
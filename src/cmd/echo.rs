@@ -1,5 +1,5 @@
-use crate::cmd::{extract_args, validate_command, CommandError, CommandExecute, EchoArgs};
-use crate::resp::{RespFrame, TArray};
+use crate::cmd::{extract_args, CommandError, CommandExecute, EchoArgs, PingArgs};
+use crate::resp::{RespFrame, TArray, TSimpleString};
 use crate::Database;
 
 impl CommandExecute for EchoArgs {
@@ -8,16 +8,38 @@ impl CommandExecute for EchoArgs {
     }
 }
 
+impl CommandExecute for PingArgs {
+    fn execute(self, _backend: &Database) -> RespFrame {
+        match self.message {
+            Some(message) => RespFrame::BulkString(message.into()),
+            None => TSimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl TryFrom<TArray> for PingArgs {
+    type Error = CommandError;
+
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (None, _) => Ok(PingArgs { message: None }),
+            (Some(RespFrame::BulkString(message)), None) => Ok(PingArgs {
+                message: Some(message.0.into()),
+            }),
+            (Some(_), None) => Err(CommandError::InvalidArgument("Invalid args".to_string())),
+            _ => Err(CommandError::WrongArity("ping".to_string())),
+        }
+    }
+}
+
 impl TryFrom<TArray> for EchoArgs {
     type Error = CommandError;
 
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["echo"], 1)?;
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(EchoArgs {
-                val: String::from_utf8(key.0)?,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(EchoArgs { val: key.0.into() }),
             _ => Err(CommandError::InvalidArgument("Invalid args".to_string())),
         }
     }
@@ -28,7 +50,7 @@ mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::resp::RespDecode;
+    use crate::resp::decode_frame;
 
     use super::*;
 
@@ -37,10 +59,23 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$4\r\necho\r\n$12\r\nHello World!\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
         let result: EchoArgs = frame.try_into()?;
-        assert_eq!(result.val, "Hello World!");
+        assert_eq!(result.val, b"Hello World!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ping() -> Result<()> {
+        let backend = Database::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nping\r\n*2\r\n$4\r\nping\r\n$2\r\nhi\r\n");
 
+        let cmd: PingArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), TSimpleString::new("PONG").into());
+        let cmd: PingArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), b"hi".into());
         Ok(())
     }
 }
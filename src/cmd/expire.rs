@@ -0,0 +1,253 @@
+use crate::cmd::{
+    extract_args, parse_integer, parse_option, CommandError, CommandExecute, Expiration,
+    ExpireArgs, ExpireCondition, PersistArgs, TtlArgs, RESP_ONE, RESP_ZERO,
+};
+use crate::database::{now_ms, Database};
+use crate::resp::{RespFrame, TArray};
+
+impl CommandExecute for ExpireArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if !backend.exists(&self.key) {
+            return RESP_ZERO.clone();
+        }
+        let deadline = self.expiration.deadline();
+        let current = backend.expire_time(&self.key);
+        if !ExpireCondition::allows(self.condition, current, deadline) {
+            return RESP_ZERO.clone();
+        }
+        if deadline <= now_ms() {
+            backend.remove(&self.key);
+        } else {
+            backend.set_expire(&self.key, deadline);
+        }
+        RESP_ONE.clone()
+    }
+}
+
+impl ExpireCondition {
+    // whether `deadline` may replace the `current` one, no ttl counts as an infinite ttl for GT/LT
+    pub(super) fn allows(condition: Option<Self>, current: Option<i64>, deadline: i64) -> bool {
+        match (condition, current) {
+            (None, _) => true,
+            (Some(ExpireCondition::Nx), current) => current.is_none(),
+            (Some(ExpireCondition::Xx), current) => current.is_some(),
+            (Some(ExpireCondition::Gt), Some(current)) => deadline > current,
+            (Some(ExpireCondition::Gt), None) => false,
+            (Some(ExpireCondition::Lt), Some(current)) => deadline < current,
+            (Some(ExpireCondition::Lt), None) => true,
+        }
+    }
+
+    // NX, XX, GT or LT
+    pub(super) fn parse(option: &[u8]) -> Option<Self> {
+        match option {
+            b"NX" => Some(ExpireCondition::Nx),
+            b"XX" => Some(ExpireCondition::Xx),
+            b"GT" => Some(ExpireCondition::Gt),
+            b"LT" => Some(ExpireCondition::Lt),
+            _ => None,
+        }
+    }
+}
+
+impl CommandExecute for TtlArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if !backend.exists(&self.key) {
+            return RespFrame::Integer(-2);
+        }
+        match backend.expire_time(&self.key) {
+            Some(deadline) => {
+                let remaining = (deadline - now_ms()).max(0);
+                if self.millis {
+                    RespFrame::Integer(remaining)
+                } else {
+                    // round to the nearest second like redis does
+                    RespFrame::Integer((remaining + 500) / 1000)
+                }
+            }
+            None => RespFrame::Integer(-1),
+        }
+    }
+}
+
+impl CommandExecute for PersistArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if backend.persist(&self.key) {
+            RESP_ONE.clone()
+        } else {
+            RESP_ZERO.clone()
+        }
+    }
+}
+
+impl TryFrom<TArray> for ExpireArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (name, expiration): (&'static str, fn(i64) -> Expiration) = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => match cmd.to_ascii_lowercase().as_slice() {
+                b"expire" => ("expire", Expiration::Ex),
+                b"pexpire" => ("pexpire", Expiration::Px),
+                b"expireat" => ("expireat", Expiration::ExAt),
+                b"pexpireat" => ("pexpireat", Expiration::PxAt),
+                _ => {
+                    return Err(CommandError::InvalidCommand(
+                        "Invalid command: expected expire".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(CommandError::InvalidCommand(
+                    "Command must have a BulkString as the first argument".to_string(),
+                ))
+            }
+        };
+        if value.len() > 4 {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let expiration = expiration(parse_integer(args.next())?);
+        let condition = match args.next().map(parse_option).transpose()? {
+            None => None,
+            Some(option) => Some(ExpireCondition::parse(&option).ok_or_else(|| {
+                CommandError::InvalidArgument(format!("Unsupported option for {}", name))
+            })?),
+        };
+
+        Ok(ExpireArgs {
+            key,
+            expiration,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<TArray> for TtlArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let millis = matches!(
+            value.first(),
+            Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(b"pttl")
+        );
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => Ok(TtlArgs {
+                key: key.0.into(),
+                millis,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<TArray> for PersistArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => Ok(PersistArgs { key: key.0.into() }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::resp::decode_frame;
+
+    use super::*;
+
+    #[test]
+    fn test_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$7\r\nPEXPIRE\r\n$5\r\nhello\r\n$3\r\n100\r\n$2\r\ngt\r\n");
+
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: ExpireArgs = frame.try_into()?;
+        assert_eq!(result.key, b"hello");
+        assert_eq!(result.expiration, Expiration::Px(100));
+        assert_eq!(result.condition, Some(ExpireCondition::Gt));
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$5\r\nhello\r\n$3\r\nabc\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: Result<ExpireArgs, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\npttl\r\n$5\r\nhello\r\n");
+
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: TtlArgs = frame.try_into()?;
+        assert_eq!(result.key, b"hello");
+        assert!(result.millis);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_ttl_persist_commands() -> Result<()> {
+        let backend = Database::new();
+        let ttl = |millis| {
+            TtlArgs {
+                key: b"hello".to_vec(),
+                millis,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(ttl(false), RespFrame::Integer(-2));
+
+        backend.set(b"hello".to_vec(), b"world".into());
+        assert_eq!(ttl(false), RespFrame::Integer(-1));
+
+        let cmd = ExpireArgs {
+            key: b"hello".to_vec(),
+            expiration: Expiration::Ex(100),
+            condition: Some(ExpireCondition::Xx),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_ZERO.clone());
+
+        let cmd = ExpireArgs {
+            key: b"hello".to_vec(),
+            expiration: Expiration::Ex(100),
+            condition: None,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_ONE.clone());
+        assert_eq!(ttl(false), RespFrame::Integer(100));
+
+        let cmd = ExpireArgs {
+            key: b"hello".to_vec(),
+            expiration: Expiration::Ex(50),
+            condition: Some(ExpireCondition::Gt),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_ZERO.clone());
+
+        let cmd = PersistArgs {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_ONE.clone());
+        assert_eq!(ttl(true), RespFrame::Integer(-1));
+
+        let cmd = ExpireArgs {
+            key: b"hello".to_vec(),
+            expiration: Expiration::PxAt(now_ms() - 1000),
+            condition: None,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_ONE.clone());
+        assert_eq!(backend.get(b"hello")?, None);
+        assert_eq!(ttl(true), RespFrame::Integer(-2));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use crate::cmd::{
+    extract_args, parse_integer, parse_option, CommandError, CommandExecute, HelloArgs,
+};
+use crate::database::Database;
+use crate::network::Session;
+use crate::resp::{RespFrame, RespProtocol, TArray, TError, TMap};
+
+// the redis release whose replies we follow, clients use it for feature detection
+const REDIS_VERSION: &str = "7.4.0";
+
+impl CommandExecute for HelloArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // outside of a connection (e.g. an aof replay) the handshake applies to a throwaway session
+        self.negotiate(backend, &mut Session::new(backend))
+    }
+}
+
+impl HelloArgs {
+    // authenticate, switch the protocol and name the connection, then describe the server
+    pub(crate) fn negotiate(self, backend: &Database, session: &mut Session) -> RespFrame {
+        let protocol = match self.protover {
+            None => session.protocol,
+            Some(2) => RespProtocol::Resp2,
+            Some(3) => RespProtocol::Resp3,
+            Some(_) => return TError::new("NOPROTO unsupported protocol version").into(),
+        };
+        if let Some((username, password)) = self.auth {
+            if !check_password(backend, &username, &password) {
+                return TError::new(
+                    "WRONGPASS invalid username-password pair or user is disabled.",
+                )
+                .into();
+            }
+            session.authenticated = true;
+        }
+        if !session.authenticated {
+            return TError::new(
+                "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time",
+            )
+            .into();
+        }
+        if let Some(name) = self.setname {
+            session.name = Some(name);
+        }
+        session.protocol = protocol;
+
+        let mut info = TMap::new();
+        info.insert("server".into(), b"redis".into());
+        info.insert("version".into(), REDIS_VERSION.as_bytes().into());
+        info.insert("proto".into(), protocol.version().into());
+        info.insert("id".into(), (session.id as i64).into());
+        info.insert("mode".into(), b"standalone".into());
+        info.insert("role".into(), b"master".into());
+        info.insert("modules".into(), TArray::new([]).into());
+        info.into()
+    }
+}
+
+// only the default user exists, it needs no password unless requirepass is set
+fn check_password(backend: &Database, username: &[u8], password: &[u8]) -> bool {
+    username == b"default"
+        && backend
+            .config
+            .requirepass
+            .as_ref()
+            .is_none_or(|expected| expected.as_bytes() == password)
+}
+
+impl TryFrom<TArray> for HelloArgs {
+    type Error = CommandError;
+
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut hello = HelloArgs {
+            protover: None,
+            auth: None,
+            setname: None,
+        };
+        let Some(protover) = args.next() else {
+            return Ok(hello);
+        };
+        hello.protover = Some(parse_integer(Some(protover)).map_err(|_| {
+            CommandError::InvalidArgument(
+                "Protocol version is not an integer or out of range".to_string(),
+            )
+        })?);
+
+        let syntax_error = |option: &[u8]| {
+            CommandError::InvalidArgument(format!(
+                "Syntax error in HELLO option '{}'",
+                String::from_utf8_lossy(option)
+            ))
+        };
+        while let Some(arg) = args.next() {
+            let option = parse_option(arg)?;
+            match option.as_slice() {
+                b"AUTH" => match (args.next(), args.next()) {
+                    (Some(RespFrame::BulkString(user)), Some(RespFrame::BulkString(pass))) => {
+                        hello.auth = Some((user.0.into(), pass.0.into()))
+                    }
+                    _ => return Err(syntax_error(&option)),
+                },
+                b"SETNAME" => match args.next() {
+                    Some(RespFrame::BulkString(name)) => {
+                        // same rule as CLIENT SETNAME: printable ascii without spaces
+                        if name.iter().any(|b| !(b'!'..=b'~').contains(b)) {
+                            return Err(CommandError::InvalidArgument(
+                                "Client names cannot contain spaces, newlines or special characters."
+                                    .to_string(),
+                            ));
+                        }
+                        hello.setname = Some(name.0.into());
+                    }
+                    _ => return Err(syntax_error(&option)),
+                },
+                _ => return Err(syntax_error(&option)),
+            }
+        }
+        Ok(hello)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::config::Config;
+    use crate::resp::decode_frame;
+
+    use super::*;
+
+    fn hello(request: &[u8]) -> Result<HelloArgs, CommandError> {
+        let mut buf = BytesMut::from(request);
+        decode_frame::<TArray>(&mut buf)?.try_into()
+    }
+
+    #[test]
+    fn test_hello_from_resp_array() -> Result<()> {
+        let args = hello(b"*6\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$2\r\npw\r\n$7\r\nSETNAME\r\n")
+            .unwrap_err();
+        assert!(args
+            .to_string()
+            .contains("Syntax error in HELLO option 'SETNAME'"));
+
+        let args =
+            hello(b"*5\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nSETNAME\r\n$3\r\ncli\r\n$3\r\nfoo\r\n")
+                .unwrap_err();
+        assert!(args
+            .to_string()
+            .contains("Syntax error in HELLO option 'FOO'"));
+
+        assert!(hello(b"*2\r\n$5\r\nhello\r\n$3\r\nabc\r\n").is_err());
+        assert!(hello(b"*4\r\n$5\r\nhello\r\n$1\r\n2\r\n$7\r\nsetname\r\n$3\r\na b\r\n").is_err());
+
+        let args = hello(b"*4\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nsetname\r\n$3\r\ncli\r\n")?;
+        assert_eq!(args.protover, Some(3));
+        assert_eq!(args.setname, Some(b"cli".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_negotiate() -> Result<()> {
+        let backend = Database::new();
+        let mut session = Session::new(&backend);
+
+        let args = hello(b"*4\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nsetname\r\n$3\r\ncli\r\n")?;
+        let RespFrame::Map(info) = args.negotiate(&backend, &mut session) else {
+            panic!("HELLO must reply with a map");
+        };
+        assert_eq!(info.get(b"proto".as_slice()), Some(&RespFrame::Integer(3)));
+        assert_eq!(session.protocol, RespProtocol::Resp3);
+        assert_eq!(session.name, Some(b"cli".to_vec()));
+
+        let args = hello(b"*2\r\n$5\r\nhello\r\n$1\r\n4\r\n")?;
+        assert_eq!(
+            args.negotiate(&backend, &mut session),
+            TError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(session.protocol, RespProtocol::Resp3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_auth() -> Result<()> {
+        let backend = Database::with_config(Config {
+            requirepass: Some("secret".to_string()),
+            ..Default::default()
+        });
+        let mut session = Session::new(&backend);
+        assert!(!session.authenticated);
+
+        let args = hello(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n")?;
+        let reply = args.negotiate(&backend, &mut session);
+        assert!(matches!(reply, RespFrame::Error(e) if e.starts_with("NOAUTH")));
+
+        let args = hello(
+            b"*5\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$5\r\nwrong\r\n",
+        )?;
+        let reply = args.negotiate(&backend, &mut session);
+        assert!(matches!(reply, RespFrame::Error(e) if e.starts_with("WRONGPASS")));
+        assert_eq!(session.protocol, RespProtocol::Resp2);
+
+        let args = hello(
+            b"*5\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n",
+        )?;
+        assert!(matches!(
+            args.negotiate(&backend, &mut session),
+            RespFrame::Map(_)
+        ));
+        assert!(session.authenticated);
+        assert_eq!(session.protocol, RespProtocol::Resp3);
+        Ok(())
+    }
+}
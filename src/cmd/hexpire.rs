@@ -0,0 +1,281 @@
+use crate::cmd::hmap::parse_field;
+use crate::cmd::{
+    extract_args, is_command, parse_integer, parse_key, parse_option, CommandError, CommandExecute,
+    Expiration, ExpireCondition, HExpireArgs, HPersistArgs, HTtlArgs,
+};
+use crate::database::{now_ms, Database};
+use crate::resp::{RespFrame, TArray};
+
+impl CommandExecute for HExpireArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let deadline = self.expiration.deadline();
+        let allowed = |current| ExpireCondition::allows(self.condition, current, deadline);
+        match backend.hexpire(&self.key, &self.fields, deadline, allowed) {
+            Ok(codes) => reply(codes),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HTtlArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let deadlines = match backend.hexpire_time(&self.key, &self.fields) {
+            Ok(deadlines) => deadlines,
+            Err(e) => return e.into(),
+        };
+        let now = now_ms();
+        let ttls = deadlines
+            .into_iter()
+            .map(|deadline| match deadline {
+                // -2 and -1 tell a missing field and a field without ttl
+                code if code < 0 => code,
+                deadline if self.millis => (deadline - now).max(0),
+                // round to the nearest second like TTL does
+                deadline => ((deadline - now).max(0) + 500) / 1000,
+            })
+            .collect();
+        reply(ttls)
+    }
+}
+
+impl CommandExecute for HPersistArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hpersist(&self.key, &self.fields) {
+            Ok(codes) => reply(codes),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// one integer per requested field, in the order they were given
+fn reply(codes: Vec<i64>) -> RespFrame {
+    TArray::new(
+        codes
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+// FIELDS numfields field [field ...], the count has to match the fields that follow
+fn parse_fields(mut args: impl Iterator<Item = RespFrame>) -> Result<Vec<Vec<u8>>, CommandError> {
+    if args.next().map(parse_option).transpose()?.as_deref() != Some(b"FIELDS") {
+        return Err(CommandError::InvalidArgument(
+            "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+        ));
+    }
+    let count = parse_integer(args.next())?;
+    if count <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        ));
+    }
+    let fields = args
+        .map(|arg| parse_field(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if fields.len() as i64 != count {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    Ok(fields)
+}
+
+impl TryFrom<TArray> for HExpireArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (name, expiration): (&'static str, fn(i64) -> Expiration) = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => match cmd.to_ascii_lowercase().as_slice() {
+                b"hexpire" => ("hexpire", Expiration::Ex),
+                b"hpexpire" => ("hpexpire", Expiration::Px),
+                b"hexpireat" => ("hexpireat", Expiration::ExAt),
+                b"hpexpireat" => ("hpexpireat", Expiration::PxAt),
+                _ => {
+                    return Err(CommandError::InvalidCommand(
+                        "Invalid command: expected hexpire".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(CommandError::InvalidCommand(
+                    "Command must have a BulkString as the first argument".to_string(),
+                ))
+            }
+        };
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = parse_key(args.next())?;
+        let time = parse_integer(args.next())?;
+        if time < 0 {
+            return Err(CommandError::InvalidArgument(
+                "invalid expire time, must be >= 0".to_string(),
+            ));
+        }
+        let fields_next = matches!(
+            args.peek(),
+            Some(RespFrame::BulkString(arg)) if arg.eq_ignore_ascii_case(b"FIELDS")
+        );
+        let condition = match args.next_if(|_| !fields_next).map(parse_option) {
+            None => None,
+            Some(option) => Some(ExpireCondition::parse(&option?).ok_or_else(|| {
+                CommandError::InvalidArgument(format!("Unsupported option for {}", name))
+            })?),
+        };
+
+        Ok(HExpireArgs {
+            key,
+            expiration: expiration(time),
+            condition,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HTtlArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let millis = is_command(&value, "hpttl");
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        Ok(HTtlArgs {
+            key,
+            millis,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HPersistArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        Ok(HPersistArgs {
+            key,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::Command;
+    use crate::resp::{decode_frame, TBulkString, TError};
+    use crate::test_util::run;
+
+    use super::*;
+
+    fn codes(codes: &[i64]) -> RespFrame {
+        reply(codes.to_vec())
+    }
+
+    #[test]
+    fn test_hexpire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$8\r\nHPEXPIRE\r\n$1\r\nh\r\n$3\r\n100\r\n$2\r\ngt\r\n$6\r\nfields\r\n$1\r\n1\r\n$1\r\na\r\n",
+        );
+        let result: HExpireArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(result.key, b"h");
+        assert_eq!(result.expiration, Expiration::Px(100));
+        assert_eq!(result.condition, Some(ExpireCondition::Gt));
+        assert_eq!(result.fields, vec![b"a".to_vec()]);
+
+        // the field count has to match
+        buf.extend_from_slice(
+            b"*6\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n10\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n",
+        );
+        let result: Result<HExpireArgs, CommandError> =
+            decode_frame::<TArray>(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        // a field named like a condition is still a field
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nhttl\r\n$1\r\nh\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$2\r\nnx\r\n$1\r\na\r\n",
+        );
+        let result: HTtlArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(result.fields, vec![b"nx".to_vec(), b"a".to_vec()]);
+        assert!(!result.millis);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_expire_commands() -> Result<()> {
+        let backend = Database::new();
+        let hexpire = b"*8\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$3\r\n100\r\n$6\r\nFIELDS\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, hexpire)?, codes(&[-2, -2, -2]));
+
+        backend.hset(b"h".to_vec(), b"a".to_vec(), b"1".into())?;
+        backend.hset(b"h".to_vec(), b"b".to_vec(), b"2".into())?;
+        backend.hset(b"h".to_vec(), b"c".to_vec(), b"3".into())?;
+        assert_eq!(run(&backend, hexpire)?, codes(&[1, 1, -2]));
+
+        let httl = b"*7\r\n$4\r\nhttl\r\n$1\r\nh\r\n$6\r\nFIELDS\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nc\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, httl)?, codes(&[100, -1, -2]));
+
+        // GT only raises the ttl, a field without ttl counts as infinite
+        let gt = b"*8\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n50\r\n$2\r\nGT\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nc\r\n";
+        assert_eq!(run(&backend, gt)?, codes(&[0, 0]));
+        let nx = b"*8\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n50\r\n$2\r\nNX\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nc\r\n";
+        assert_eq!(run(&backend, nx)?, codes(&[0, 1]));
+
+        let hpersist =
+            b"*6\r\n$8\r\nhpersist\r\n$1\r\nh\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, hpersist)?, codes(&[1, -2]));
+        assert_eq!(run(&backend, hpersist)?, codes(&[-1, -2]));
+        let hpttl = b"*5\r\n$5\r\nhpttl\r\n$1\r\nh\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\nc\r\n";
+        let RespFrame::Array(ttl) = run(&backend, hpttl)? else {
+            panic!("expected an array");
+        };
+        assert!(matches!(ttl[0], RespFrame::Integer(ms) if ms > 49_000 && ms <= 50_000));
+
+        // a deadline in the past deletes the field, the last one takes the key with it
+        let past = b"*7\r\n$10\r\nhpexpireat\r\n$1\r\nh\r\n$1\r\n1\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, past)?, codes(&[2, 2]));
+        assert_eq!(backend.hlen(b"h")?, 1);
+        let past = b"*6\r\n$9\r\nhexpireat\r\n$1\r\nh\r\n$1\r\n1\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\nc\r\n";
+        assert_eq!(run(&backend, past)?, codes(&[2]));
+        assert!(!backend.exists(b"h"));
+
+        backend.set(b"s".to_vec(), b"v".into());
+        let httl = b"*5\r\n$4\r\nhttl\r\n$1\r\ns\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n";
+        assert_eq!(
+            run(&backend, httl)?,
+            TError::new("WRONGTYPE Operation against a key holding the wrong kind of value").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_propagates_absolute_deadline() -> Result<()> {
+        let request = b"*7\r\n$7\r\nhexpire\r\n$1\r\nh\r\n$2\r\n10\r\n$2\r\nxx\r\n$6\r\nfields\r\n$1\r\n1\r\n$1\r\na\r\n";
+        let frame = decode_frame::<TArray>(&mut BytesMut::from(&request[..]))?;
+        let mut cmd: Command = frame.clone().try_into()?;
+        let before = now_ms();
+        let Some(propagated) = cmd.propagate(frame) else {
+            panic!("hexpire must be propagated");
+        };
+        let Command::HExpire(args) = cmd else {
+            panic!("expected hexpire");
+        };
+        let Expiration::PxAt(deadline) = args.expiration else {
+            panic!("the deadline must be pinned");
+        };
+        assert!(deadline >= before + 10_000);
+        let expected = TArray::new([
+            b"hpexpireat".into(),
+            b"h".into(),
+            TBulkString::from(deadline.to_string()).into(),
+            b"xx".into(),
+            b"fields".into(),
+            b"1".into(),
+            b"a".into(),
+        ]);
+        assert_eq!(propagated, expected);
+        Ok(())
+    }
+}
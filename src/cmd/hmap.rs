@@ -1,46 +1,159 @@
 use crate::cmd::{
-    extract_args, validate_command, CommandError, CommandExecute, HGetAllArgs, HGetArgs, HSetArgs,
-    RESP_NULL, RESP_OK,
+    extract_args, is_command, parse_float, parse_integer, parse_key, parse_option,
+    parse_random_count, CommandError, CommandExecute, HDelArgs, HExistsArgs, HGetAllArgs, HGetArgs,
+    HIncrByArgs, HIncrByFloatArgs, HKeysArgs, HLenArgs, HRandFieldArgs, HSetArgs, HSetNxArgs,
+    HStrlenArgs, RESP_NULL, RESP_ONE, RESP_ZERO,
 };
-use crate::database::Database;
-use crate::resp::{RespFrame, TArray, TBulkString};
+use crate::database::{string_bytes, Database};
+use crate::resp::{RespFrame, TArray, TBulkString, TMap};
 
 impl CommandExecute for HGetArgs {
     fn execute(self, backend: &Database) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RESP_NULL.clone(),
+            Ok(Some(value)) => value,
+            Ok(None) => RESP_NULL.clone(),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecute for HSetArgs {
     fn execute(self, backend: &Database) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
-        RESP_OK.clone()
+        match backend.hset_multi(self.key, self.fields) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
     }
 }
 
-impl CommandExecute for HGetAllArgs {
+impl CommandExecute for HSetNxArgs {
     fn execute(self, backend: &Database) -> RespFrame {
-        let hmap = backend.hgetall(&self.key);
-        match hmap {
-            Some(hmap) => {
-                let mut data = Vec::with_capacity(hmap.len());
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
-                }
-                if self.sort {
-                    data.sort_by(|a, b| a.0.cmp(&b.0));
+        match backend.hsetnx(self.key, self.field, self.value) {
+            Ok(true) => RESP_ONE.clone(),
+            Ok(false) => RESP_ZERO.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HDelArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hdel(self.key, &self.fields) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HExistsArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hget(&self.key, &self.field) {
+            Ok(Some(_)) => RESP_ONE.clone(),
+            Ok(None) => RESP_ZERO.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HLenArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HKeysArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let hash = match backend.hgetall(&self.key) {
+            Ok(hash) => hash.unwrap_or_default(),
+            Err(e) => return e.into(),
+        };
+        let ret = hash
+            .into_iter()
+            .map(|(field, value)| match self.values {
+                true => value,
+                false => TBulkString::new(field).into(),
+            })
+            .collect::<Vec<RespFrame>>();
+        TArray::new(ret).into()
+    }
+}
+
+impl CommandExecute for HStrlenArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hget(&self.key, &self.field) {
+            Ok(value) => {
+                let len = value.map(|v| string_bytes(v).len()).unwrap_or_default();
+                RespFrame::Integer(len as i64)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HIncrByArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hincr_by(self.key, self.field, self.delta) {
+            Ok(value) => RespFrame::Integer(value),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HIncrByFloatArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.hincr_by_float(self.key, self.field, self.delta) {
+            Ok(value) => TBulkString::from(value.to_string()).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for HRandFieldArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let fields = match backend.hrandfield(&self.key, self.count.unwrap_or(1)) {
+            Ok(fields) => fields,
+            Err(e) => return e.into(),
+        };
+        let Some(_) = self.count else {
+            return match fields.into_iter().next() {
+                Some((field, _)) => TBulkString::new(field).into(),
+                None => RESP_NULL.clone(),
+            };
+        };
+        let ret = fields
+            .into_iter()
+            .flat_map(|(field, value)| {
+                let field = TBulkString::new(field).into();
+                match self.withvalues {
+                    true => vec![field, value],
+                    false => vec![field],
                 }
-                let ret = data
-                    .into_iter()
-                    .flat_map(|(k, v)| vec![TBulkString::from(k).into(), v])
-                    .collect::<Vec<RespFrame>>();
-                TArray::new(ret).into()
+            })
+            .collect::<Vec<RespFrame>>();
+        TArray::new(ret).into()
+    }
+}
+
+pub(super) fn parse_field(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(field)) => Ok(field.0.into()),
+        _ => Err(CommandError::InvalidArgument("Invalid field".to_string())),
+    }
+}
+
+impl CommandExecute for HGetAllArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // a map on resp3, flattened to field value pairs for resp2 clients
+        match backend.hgetall(&self.key) {
+            Ok(hash) => {
+                let mut map = TMap::new();
+                map.extend(hash.unwrap_or_default());
+                map.into()
             }
-            None => TArray::new([]).into(),
+            Err(e) => e.into(),
         }
     }
 }
@@ -49,13 +162,12 @@ impl TryFrom<TArray> for HGetArgs {
     type Error = CommandError;
 
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hget"], 2)?;
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => {
                 Ok(HGetArgs {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
+                    key: key.0.into(),
+                    field: field.0.into(),
                 })
             }
             _ => Err(CommandError::InvalidArgument(
@@ -68,17 +180,29 @@ impl TryFrom<TArray> for HGetArgs {
 impl TryFrom<TArray> for HSetArgs {
     type Error = CommandError;
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], 3)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        if args.len() == 0 || args.len() % 2 != 0 {
+            return Err(CommandError::WrongArity("hset".to_string()));
+        }
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        while let (field @ Some(_), Some(value)) = (args.next(), args.next()) {
+            fields.push((parse_field(field)?, value));
+        }
+        Ok(HSetArgs { key, fields })
+    }
+}
 
+impl TryFrom<TArray> for HSetNxArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
         let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSetArgs {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
-                    value,
-                })
-            }
+        match (
+            parse_key(args.next())?,
+            parse_field(args.next())?,
+            args.next(),
+        ) {
+            (key, field, Some(value)) => Ok(HSetNxArgs { key, field, value }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key, field or value".to_string(),
             )),
@@ -86,17 +210,113 @@ impl TryFrom<TArray> for HSetArgs {
     }
 }
 
-impl TryFrom<TArray> for HGetAllArgs {
+impl TryFrom<TArray> for HDelArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let fields = args
+            .map(|arg| parse_field(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(HDelArgs { key, fields })
+    }
+}
+
+impl TryFrom<TArray> for HExistsArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HExistsArgs {
+            key: parse_key(args.next())?,
+            field: parse_field(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HLenArgs {
     type Error = CommandError;
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hgetall"], 1)?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HLenArgs {
+            key: parse_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HKeysArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let values = is_command(&value, "hvals");
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HKeysArgs {
+            key: parse_key(args.next())?,
+            values,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HStrlenArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HStrlenArgs {
+            key: parse_key(args.next())?,
+            field: parse_field(args.next())?,
+        })
+    }
+}
 
+impl TryFrom<TArray> for HIncrByArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HIncrByArgs {
+            key: parse_key(args.next())?,
+            field: parse_field(args.next())?,
+            delta: parse_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HIncrByFloatArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let field = parse_field(args.next())?;
+        let delta = parse_float(args.next())?;
+        Ok(HIncrByFloatArgs { key, field, delta })
+    }
+}
+
+impl TryFrom<TArray> for HRandFieldArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let count = args
+            .next()
+            .map(|arg| parse_random_count(Some(arg)))
+            .transpose()?;
+        let withvalues = match args.next().map(parse_option).transpose()?.as_deref() {
+            None => false,
+            Some(b"WITHVALUES") if args.len() == 0 => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(HRandFieldArgs {
+            key,
+            count,
+            withvalues,
+        })
+    }
+}
+
+impl TryFrom<TArray> for HGetAllArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(HGetAllArgs {
-                key: String::from_utf8(key.0)?,
-                sort: false,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(HGetAllArgs { key: key.0.into() }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
@@ -107,7 +327,8 @@ mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::resp::RespDecode;
+    use crate::resp::{decode_frame, RespEncode, RespProtocol, TError};
+    use crate::test_util::run;
 
     use super::*;
 
@@ -116,11 +337,11 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*3\r\n$4\r\nhget\r\n$3\r\nmap\r\n$5\r\nhello\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let result: HGetArgs = frame.try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.field, b"hello");
 
         Ok(())
     }
@@ -130,10 +351,10 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$7\r\nhgetall\r\n$3\r\nmap\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let result: HGetAllArgs = frame.try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
 
         Ok(())
     }
@@ -143,12 +364,22 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$3\r\nmap\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let result: HSetArgs = frame.try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
-        assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(result.key, b"map");
+        assert_eq!(
+            result.fields,
+            vec![(b"hello".to_vec(), RespFrame::BulkString(b"world".into()))]
+        );
+
+        // fields and values come in pairs
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nhset\r\n$3\r\nmap\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n",
+        );
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: Result<HSetArgs, CommandError> = frame.try_into();
+        assert!(result.is_err());
 
         Ok(())
     }
@@ -157,40 +388,181 @@ mod tests {
     fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = Database::new();
         let cmd = HSetArgs {
-            key: "map".to_string(),
-            field: "hello".to_string(),
-            value: RespFrame::BulkString(b"world".into()),
+            key: b"map".to_vec(),
+            fields: vec![(b"hello".to_vec(), RespFrame::BulkString(b"world".into()))],
         };
         let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_OK.clone());
+        assert_eq!(result, RESP_ONE.clone());
 
+        // only the new fields are counted
         let cmd = HSetArgs {
-            key: "map".to_string(),
-            field: "hello1".to_string(),
-            value: RespFrame::BulkString(b"world1".into()),
+            key: b"map".to_vec(),
+            fields: vec![
+                (b"hello".to_vec(), RespFrame::BulkString(b"world".into())),
+                (b"hello1".to_vec(), RespFrame::BulkString(b"world1".into())),
+            ],
         };
-        cmd.execute(&backend);
+        assert_eq!(cmd.execute(&backend), RESP_ONE.clone());
 
         let cmd = HGetArgs {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAllArgs {
-            key: "map".to_string(),
-            sort: true,
+            key: b"map".to_vec(),
         };
         let result = cmd.execute(&backend);
 
-        let expected = TArray::new([
-            TBulkString::from("hello").into(),
-            TBulkString::from("world").into(),
-            TBulkString::from("hello1").into(),
-            TBulkString::from("world1").into(),
-        ]);
+        let mut expected = TMap::new();
+        expected.insert(b"hello".to_vec(), b"world".into());
+        expected.insert(b"hello1".to_vec(), b"world1".into());
         assert_eq!(result, expected.into());
+
+        // resp2 clients get the fields and values as one flat array
+        let mut buf = BytesMut::new();
+        result.encode_for(RespProtocol::Resp2, &mut buf);
+        assert_eq!(
+            buf.as_ref(),
+            b"*4\r\n$5\r\nhello\r\n$5\r\nworld\r\n$6\r\nhello1\r\n$6\r\nworld1\r\n"
+        );
+
+        // a missing hash is an empty map, so an empty array and not a null one on resp2
+        let cmd = HGetAllArgs {
+            key: b"missing".to_vec(),
+        };
+        let mut buf = BytesMut::new();
+        cmd.execute(&backend)
+            .encode_for(RespProtocol::Resp2, &mut buf);
+        assert_eq!(buf.as_ref(), b"*0\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_hset_binary_field() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$1\r\n\xff\r\n$2\r\n\x00\x80\r\n$1\r\nv\r\n");
+
+        let backend = Database::new();
+        let cmd: HSetArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.fields[0].0, b"\x00\x80");
+        cmd.execute(&backend);
+
+        let cmd = HGetAllArgs {
+            key: b"\xff".to_vec(),
+        };
+        // a field that is not text can't be a simple string key
+        assert_eq!(
+            cmd.execute(&backend).to_vec(),
+            b"%1\r\n$2\r\n\x00\x80\r\n$1\r\nv\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_commands() -> Result<()> {
+        let backend = Database::new();
+        let hset =
+            b"*6\r\n$4\r\nhset\r\n$1\r\nh\r\n$1\r\na\r\n$2\r\n10\r\n$1\r\nb\r\n$5\r\nhello\r\n";
+        assert_eq!(run(&backend, hset)?, RespFrame::Integer(2));
+
+        let hsetnx = b"*4\r\n$6\r\nhsetnx\r\n$1\r\nh\r\n$1\r\na\r\n$1\r\n0\r\n";
+        assert_eq!(run(&backend, hsetnx)?, RESP_ZERO.clone());
+        let hexists = b"*3\r\n$7\r\nhexists\r\n$1\r\nh\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, hexists)?, RESP_ONE.clone());
+        let hlen = b"*2\r\n$4\r\nhlen\r\n$1\r\nh\r\n";
+        assert_eq!(run(&backend, hlen)?, RespFrame::Integer(2));
+        let hstrlen = b"*3\r\n$7\r\nhstrlen\r\n$1\r\nh\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, hstrlen)?, RespFrame::Integer(5));
+
+        let sorted = |frame: RespFrame| {
+            let RespFrame::Array(array) = frame else {
+                panic!("expected an array");
+            };
+            let mut items = array
+                .0
+                .iter()
+                .map(|f| f.clone().to_vec())
+                .collect::<Vec<_>>();
+            items.sort();
+            items
+        };
+        let hkeys = b"*2\r\n$5\r\nhkeys\r\n$1\r\nh\r\n";
+        assert_eq!(
+            sorted(run(&backend, hkeys)?),
+            vec![b"$1\r\na\r\n".to_vec(), b"$1\r\nb\r\n".to_vec()]
+        );
+        let hvals = b"*2\r\n$5\r\nhvals\r\n$1\r\nh\r\n";
+        assert_eq!(
+            sorted(run(&backend, hvals)?),
+            vec![b"$2\r\n10\r\n".to_vec(), b"$5\r\nhello\r\n".to_vec()]
+        );
+
+        let hincrby = b"*4\r\n$7\r\nhincrby\r\n$1\r\nh\r\n$1\r\na\r\n$2\r\n-3\r\n";
+        assert_eq!(run(&backend, hincrby)?, RespFrame::Integer(7));
+        let hincrby = b"*4\r\n$7\r\nhincrby\r\n$1\r\nh\r\n$1\r\nb\r\n$1\r\n1\r\n";
+        assert_eq!(
+            run(&backend, hincrby)?,
+            TError::new("ERR hash value is not an integer").into()
+        );
+        let hincrbyfloat = b"*4\r\n$12\r\nhincrbyfloat\r\n$1\r\nh\r\n$1\r\na\r\n$3\r\n0.5\r\n";
+        assert_eq!(
+            run(&backend, hincrbyfloat)?,
+            RespFrame::BulkString(b"7.5".into())
+        );
+
+        let hdel = b"*5\r\n$4\r\nhdel\r\n$1\r\nh\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, hdel)?, RespFrame::Integer(2));
+        // the empty hash is gone
+        assert_eq!(backend.key_type(b"h"), None);
+        assert_eq!(run(&backend, hlen)?, RespFrame::Integer(0));
+        assert_eq!(run(&backend, hkeys)?.to_vec(), b"*0\r\n");
+
+        backend.set(b"s".to_vec(), b"v".into());
+        let hlen = b"*2\r\n$4\r\nhlen\r\n$1\r\ns\r\n";
+        assert!(matches!(run(&backend, hlen)?, RespFrame::Error(e) if e.starts_with("WRONGTYPE")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield() -> Result<()> {
+        let backend = Database::new();
+        let hrandfield = b"*2\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n";
+        assert_eq!(run(&backend, hrandfield)?, RESP_NULL.clone());
+
+        backend.hset(b"h".to_vec(), b"a".to_vec(), b"1".into())?;
+        backend.hset(b"h".to_vec(), b"b".to_vec(), b"2".into())?;
+        let reply = run(&backend, hrandfield)?;
+        assert!(reply == b"a".into() || reply == b"b".into());
+
+        let len = |frame: RespFrame| match frame {
+            RespFrame::Array(array) => array.len(),
+            other => panic!("expected an array, got {:?}", other),
+        };
+        let distinct = b"*3\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$1\r\n5\r\n";
+        assert_eq!(len(run(&backend, distinct)?), 2);
+        let repeated = b"*3\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$2\r\n-5\r\n";
+        assert_eq!(len(run(&backend, repeated)?), 5);
+        let withvalues = b"*4\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$1\r\n1\r\n$10\r\nwithvalues\r\n";
+        let RespFrame::Array(pair) = run(&backend, withvalues)? else {
+            panic!("expected an array");
+        };
+        assert_eq!(
+            backend.hget(b"h", &pair[0].clone().to_vec()[4..5])?,
+            Some(pair[1].clone())
+        );
+
+        let invalid = b"*3\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$10\r\nwithvalues\r\n";
+        assert!(run(&backend, invalid).is_err());
+
+        // a count that would repeat fields without bound is refused before anything is allocated
+        let huge =
+            b"*4\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$11\r\n-1000000000\r\n$10\r\nwithvalues\r\n";
+        assert!(run(&backend, huge).is_err());
+        let min = b"*3\r\n$10\r\nhrandfield\r\n$1\r\nh\r\n$20\r\n-9223372036854775808\r\n";
+        assert!(run(&backend, min).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,224 @@
+use crate::cmd::{
+    extract_args, is_command, parse_key, parse_option, CommandError, CommandExecute, CopyArgs,
+    DelArgs, ExistsArgs, RenameArgs, TypeArgs, RESP_OK, RESP_ONE, RESP_ZERO,
+};
+use crate::database::Database;
+use crate::resp::{RespFrame, TArray, TSimpleString};
+
+impl CommandExecute for DelArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let removed = self
+            .keys
+            .iter()
+            .filter(|key| {
+                if self.lazy {
+                    backend.unlink(key)
+                } else {
+                    backend.remove(key)
+                }
+            })
+            .count();
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecute for ExistsArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // a key given twice is counted twice, like in redis
+        let existing = self.keys.iter().filter(|key| backend.exists(key)).count();
+        RespFrame::Integer(existing as i64)
+    }
+}
+
+impl CommandExecute for TypeArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let name = backend.key_type(&self.key).unwrap_or("none");
+        TSimpleString::new(name).into()
+    }
+}
+
+impl CommandExecute for RenameArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.rename(&self.key, self.newkey, self.nx) {
+            None => CommandError::InvalidArgument("no such key".to_string()).into(),
+            Some(_) if !self.nx => RESP_OK.clone(),
+            Some(true) => RESP_ONE.clone(),
+            Some(false) => RESP_ZERO.clone(),
+        }
+    }
+}
+
+impl CommandExecute for CopyArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if self.source == self.destination {
+            return CommandError::InvalidArgument(
+                "source and destination objects are the same".to_string(),
+            )
+            .into();
+        }
+        if backend.copy(&self.source, self.destination, self.replace) {
+            RESP_ONE.clone()
+        } else {
+            RESP_ZERO.clone()
+        }
+    }
+}
+
+impl TryFrom<TArray> for DelArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let lazy = is_command(&value, "unlink");
+        let keys = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| parse_key(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(DelArgs { keys, lazy })
+    }
+}
+
+impl TryFrom<TArray> for ExistsArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let keys = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| parse_key(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(ExistsArgs { keys })
+    }
+}
+
+impl TryFrom<TArray> for TypeArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TypeArgs {
+            key: parse_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for RenameArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let nx = is_command(&value, "renamenx");
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(RenameArgs {
+            key: parse_key(args.next())?,
+            newkey: parse_key(args.next())?,
+            nx,
+        })
+    }
+}
+
+impl TryFrom<TArray> for CopyArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = parse_key(args.next())?;
+        let destination = parse_key(args.next())?;
+        let mut replace = false;
+        for arg in args {
+            match parse_option(arg)?.as_slice() {
+                b"REPLACE" => replace = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(CopyArgs {
+            source,
+            destination,
+            replace,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::TError;
+    use crate::test_util::run;
+
+    use super::*;
+
+    #[test]
+    fn test_del_exists_type() -> Result<()> {
+        let backend = Database::new();
+        backend.set(b"a".to_vec(), b"1".into());
+        backend.hset(b"h".to_vec(), b"f".to_vec(), b"v".into())?;
+        backend.sadd(b"s".to_vec(), b"m".to_vec())?;
+
+        let exists = b"*5\r\n$6\r\nexists\r\n$1\r\na\r\n$1\r\na\r\n$1\r\nh\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, exists)?, RespFrame::Integer(3));
+        let touch = b"*3\r\n$5\r\ntouch\r\n$1\r\ns\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, touch)?, RespFrame::Integer(1));
+
+        for (key, name) in [("a", "string"), ("h", "hash"), ("s", "set"), ("x", "none")] {
+            let request = format!("*2\r\n$4\r\ntype\r\n$1\r\n{}\r\n", key);
+            let frame = run(&backend, request.as_bytes())?;
+            assert_eq!(frame, TSimpleString::new(name).into());
+        }
+
+        let del = b"*4\r\n$3\r\ndel\r\n$1\r\na\r\n$1\r\nh\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, del)?, RespFrame::Integer(2));
+        let unlink = b"*3\r\n$6\r\nunlink\r\n$1\r\ns\r\n$1\r\ns\r\n";
+        assert_eq!(run(&backend, unlink)?, RespFrame::Integer(1));
+        assert_eq!(backend.dbsize(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename() -> Result<()> {
+        let backend = Database::new();
+        backend.set_with_expire(
+            b"a".to_vec(),
+            b"1".into(),
+            crate::database::now_ms() + 60_000,
+        );
+        backend.set(b"b".to_vec(), b"2".into());
+
+        let renamenx = b"*3\r\n$8\r\nrenamenx\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, renamenx)?, RESP_ZERO.clone());
+        let rename = b"*3\r\n$6\r\nrename\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, rename)?, RESP_OK.clone());
+        assert_eq!(backend.get(b"b")?, Some(b"1".into()));
+        assert!(!backend.exists(b"a"));
+        // the ttl moves together with the value
+        assert!(backend.expire_time(b"b").is_some());
+
+        assert_eq!(
+            run(&backend, rename)?,
+            TError::new("ERR no such key").into()
+        );
+        let renamenx = b"*3\r\n$8\r\nrenamenx\r\n$1\r\nb\r\n$1\r\nc\r\n";
+        assert_eq!(run(&backend, renamenx)?, RESP_ONE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy() -> Result<()> {
+        let backend = Database::new();
+        backend.hset(b"h".to_vec(), b"f".to_vec(), b"v".into())?;
+        backend.set(b"s".to_vec(), b"1".into());
+
+        let copy = b"*3\r\n$4\r\ncopy\r\n$1\r\nh\r\n$1\r\nc\r\n";
+        assert_eq!(run(&backend, copy)?, RESP_ONE.clone());
+        assert_eq!(run(&backend, copy)?, RESP_ZERO.clone());
+        assert_eq!(backend.hget(b"c", b"f")?, Some(b"v".into()));
+        // the copy is independent of its source
+        backend.hset(b"h".to_vec(), b"f".to_vec(), b"changed".into())?;
+        assert_eq!(backend.hget(b"c", b"f")?, Some(b"v".into()));
+
+        let replace = b"*4\r\n$4\r\ncopy\r\n$1\r\ns\r\n$1\r\nc\r\n$7\r\nREPLACE\r\n";
+        assert_eq!(run(&backend, replace)?, RESP_ONE.clone());
+        assert_eq!(backend.get(b"c")?, Some(b"1".into()));
+
+        let same = b"*3\r\n$4\r\ncopy\r\n$1\r\ns\r\n$1\r\ns\r\n";
+        assert_eq!(
+            run(&backend, same)?,
+            TError::new("ERR source and destination objects are the same").into()
+        );
+        let missing = b"*3\r\n$4\r\ncopy\r\n$1\r\nx\r\n$1\r\ny\r\n";
+        assert_eq!(run(&backend, missing)?, RESP_ZERO.clone());
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use crate::cmd::{
-    extract_args, validate_command, CommandError, CommandExecute, GetArgs, SetArgs, RESP_NULL,
-    RESP_OK,
+    extract_args, parse_integer, parse_option, CommandError, CommandExecute, Expiration, GetArgs,
+    SetArgs, SetCondition, RESP_NULL, RESP_OK,
 };
 use crate::database::Database;
 use crate::resp::{RespFrame, TArray};
@@ -8,29 +8,45 @@ use crate::resp::{RespFrame, TArray};
 impl CommandExecute for GetArgs {
     fn execute(self, backend: &Database) -> RespFrame {
         match backend.get(&self.key) {
-            Some(value) => value,
-            None => RESP_NULL.clone(),
+            Ok(Some(value)) => value,
+            Ok(None) => RESP_NULL.clone(),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecute for SetArgs {
     fn execute(self, backend: &Database) -> RespFrame {
-        backend.set(self.key, self.value);
-        RESP_OK.clone()
+        let exists = self
+            .condition
+            .map(|condition| condition == SetCondition::Xx);
+        let deadline = self.expiration.map(|expiration| expiration.deadline());
+        // SET ... GET fails before writing anything when the old value is not a string
+        let (applied, old) = match backend.set_with_options(
+            self.key,
+            self.value,
+            deadline,
+            self.keep_ttl,
+            exists,
+            self.get,
+        ) {
+            Ok(outcome) => outcome,
+            Err(e) => return e.into(),
+        };
+        match (self.get, applied) {
+            (true, _) => old.unwrap_or_else(|| RESP_NULL.clone()),
+            (false, true) => RESP_OK.clone(),
+            (false, false) => RESP_NULL.clone(),
+        }
     }
 }
 
 impl TryFrom<TArray> for GetArgs {
     type Error = CommandError;
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["get"], 1)?;
-
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(GetArgs {
-                key: String::from_utf8(key.0)?,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(GetArgs { key: key.0.into() }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
@@ -39,18 +55,54 @@ impl TryFrom<TArray> for GetArgs {
 impl TryFrom<TArray> for SetArgs {
     type Error = CommandError;
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["set"], 2)?;
-
         let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(SetArgs {
-                key: String::from_utf8(key.0)?,
-                value,
-            }),
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key or value".to_string(),
-            )),
+        let (key, value) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(value)) => (key.0.into(), value),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or value".to_string(),
+                ))
+            }
+        };
+
+        let mut expiration = None;
+        let mut keep_ttl = false;
+        let mut condition = None;
+        let mut get = false;
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            let option = parse_option(arg)?;
+            match option.as_slice() {
+                b"NX" if condition.is_none() => condition = Some(SetCondition::Nx),
+                b"XX" if condition.is_none() => condition = Some(SetCondition::Xx),
+                b"GET" => get = true,
+                b"KEEPTTL" if expiration.is_none() => keep_ttl = true,
+                b"EX" | b"PX" | b"EXAT" | b"PXAT" if expiration.is_none() && !keep_ttl => {
+                    let time = parse_integer(args.next())?;
+                    if time <= 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "invalid expire time in 'set' command".to_string(),
+                        ));
+                    }
+                    expiration = Some(match option.as_slice() {
+                        b"EX" => Expiration::Ex(time),
+                        b"PX" => Expiration::Px(time),
+                        b"EXAT" => Expiration::ExAt(time),
+                        _ => Expiration::PxAt(time),
+                    });
+                }
+                _ => return Err(syntax_error()),
+            }
         }
+
+        Ok(SetArgs {
+            key,
+            value,
+            expiration,
+            keep_ttl,
+            condition,
+            get,
+        })
     }
 }
 
@@ -59,7 +111,7 @@ mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::resp::RespDecode;
+    use crate::resp::{decode_frame, RespEncode};
 
     use super::*;
 
@@ -68,10 +120,10 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let result: GetArgs = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
 
         Ok(())
     }
@@ -81,11 +133,45 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let result: SetArgs = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(result.expiration, None);
+        assert_eq!(result.condition, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_options_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nex\r\n$2\r\n10\r\n$2\r\nNX\r\n",
+        );
+
+        let frame = decode_frame::<TArray>(&mut buf)?;
+
+        let result: SetArgs = frame.try_into()?;
+        assert_eq!(result.expiration, Some(Expiration::Ex(10)));
+        assert_eq!(result.condition, Some(SetCondition::Nx));
+        assert!(!result.keep_ttl);
+        assert!(!result.get);
+
+        buf.extend_from_slice(
+            b"*6\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nEX\r\n$2\r\n10\r\n$7\r\nKEEPTTL\r\n",
+        );
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: Result<SetArgs, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nPX\r\n$1\r\n0\r\n",
+        );
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: Result<SetArgs, CommandError> = frame.try_into();
+        assert!(result.is_err());
 
         Ok(())
     }
@@ -94,18 +180,119 @@ mod tests {
     fn test_set_get_command() -> Result<()> {
         let backend = Database::new();
         let cmd = SetArgs {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
+            expiration: None,
+            keep_ttl: false,
+            condition: None,
+            get: false,
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = GetArgs {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_get_empty_value() -> Result<()> {
+        let backend = Database::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$0\r\n\r\n");
+        let cmd: SetArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        cmd.execute(&backend);
+
+        let cmd = GetArgs { key: b"k".to_vec() };
+        assert_eq!(cmd.execute(&backend).to_vec(), b"$0\r\n\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_condition_and_get_command() -> Result<()> {
+        let backend = Database::new();
+        let cmd = SetArgs {
+            key: b"hello".to_vec(),
+            value: RespFrame::BulkString(b"world".into()),
+            expiration: None,
+            keep_ttl: false,
+            condition: Some(SetCondition::Xx),
+            get: false,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_NULL.clone());
+        assert_eq!(backend.get(b"hello")?, None);
+
+        let cmd = SetArgs {
+            key: b"hello".to_vec(),
+            value: RespFrame::BulkString(b"world".into()),
+            expiration: Some(Expiration::Px(60_000)),
+            keep_ttl: false,
+            condition: Some(SetCondition::Nx),
+            get: false,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(backend.expire_time(b"hello").is_some());
+
+        let cmd = SetArgs {
+            key: b"hello".to_vec(),
+            value: RespFrame::BulkString(b"world1".into()),
+            expiration: None,
+            keep_ttl: true,
+            condition: None,
+            get: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::BulkString(b"world".into())
+        );
+        assert!(backend.expire_time(b"hello").is_some());
+
+        let cmd = SetArgs {
+            key: b"hello".to_vec(),
+            value: RespFrame::BulkString(b"world2".into()),
+            expiration: None,
+            keep_ttl: false,
+            condition: None,
+            get: false,
+        };
+        cmd.execute(&backend);
+        assert_eq!(backend.expire_time(b"hello"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_nx_is_atomic() {
+        let backend = Database::new();
+        // every thread races for the same lock, exactly one of them may get it
+        let winners = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|i| {
+                    let backend = backend.clone();
+                    scope.spawn(move || {
+                        let cmd = SetArgs {
+                            key: b"lock".to_vec(),
+                            value: RespFrame::Integer(i),
+                            expiration: Some(Expiration::Px(60_000)),
+                            keep_ttl: false,
+                            condition: Some(SetCondition::Nx),
+                            get: false,
+                        };
+                        cmd.execute(&backend) == RESP_OK.clone()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .filter(|won| *won)
+                .count()
+        });
+        assert_eq!(winners, 1);
+        assert!(backend.expire_time(b"lock").is_some());
+    }
 }
@@ -1,7 +1,7 @@
 use log::info;
 
-use crate::cmd::{extract_args, validate_command, CommandError, CommandExecute, HMGetArgs};
-use crate::resp::{RespFrame, TArray, TBulkString};
+use crate::cmd::{extract_args, CommandError, CommandExecute, HMGetArgs, RESP_NULL};
+use crate::resp::{RespFrame, TArray};
 use crate::Database;
 
 impl CommandExecute for HMGetArgs {
@@ -9,8 +9,9 @@ impl CommandExecute for HMGetArgs {
         let mut data = Vec::with_capacity(self.field.len());
         for field in self.field.iter() {
             match backend.hget(&self.key, field) {
-                Some(value) => data.push(value),
-                None => data.push(RespFrame::BulkString(TBulkString::new(Vec::new()))),
+                Ok(Some(value)) => data.push(value),
+                Ok(None) => data.push(RESP_NULL.clone()),
+                Err(e) => return e.into(),
             }
         }
         TArray::new(data).into()
@@ -21,25 +22,19 @@ impl TryFrom<TArray> for HMGetArgs {
     type Error = CommandError;
 
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        let lens = value.len();
-        validate_command(&value, &["hmget"], lens - 1)?;
-        if value.len() < 2 {
-            return Err(CommandError::InvalidArgument("Invalid args".to_string()));
-        }
-
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => String::from_utf8(key.0)?,
+            Some(RespFrame::BulkString(key)) => key.0.into(),
             _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
         };
         info!("key: {:?}", key);
         let field = args
             .map(|arg| match arg {
-                RespFrame::BulkString(field) => Ok(String::from_utf8(field.0)?),
+                RespFrame::BulkString(field) => Ok(field.0.into()),
                 _ => Err(CommandError::InvalidArgument("Invalid field".to_string())),
             })
-            .collect::<Result<Vec<String>, CommandError>>()?;
+            .collect::<Result<Vec<Vec<u8>>, CommandError>>()?;
 
         Ok(HMGetArgs { key, field })
     }
@@ -50,7 +45,7 @@ mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::resp::RespDecode;
+    use crate::resp::decode_frame;
 
     use super::*;
 
@@ -59,10 +54,13 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*5\r\n$5\r\nhmget\r\n$6\r\nmyhash\r\n$6\r\nfield1\r\n$6\r\nfield2\r\n$7\r\nnofield\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
         let result: HMGetArgs = frame.try_into()?;
-        assert_eq!(result.key, "myhash");
-        assert_eq!(result.field, vec!["field1", "field2", "nofield"]);
+        assert_eq!(result.key, b"myhash");
+        assert_eq!(
+            result.field,
+            vec![b"field1".to_vec(), b"field2".to_vec(), b"nofield".to_vec()]
+        );
         Ok(())
     }
 }
@@ -2,34 +2,67 @@ use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
-use crate::database::Database;
-use crate::resp::{RespError, RespFrame, TArray, TNull, TSimpleString};
+use crate::database::{now_ms, Database, NumericError, WrongType};
+use crate::resp::{RespError, RespFrame, TArray, TBulkString, TError, TNull, TSimpleString};
 
+pub use table::{commands, lookup_command, CommandFlag, CommandSpec};
+
+mod command;
+mod db;
 mod echo;
+mod expire;
+mod hello;
+mod hexpire;
 mod hmap;
+mod keyspace;
 mod map;
 mod mget;
+mod save;
+mod scan;
 mod set;
-mod unrecognized;
+mod string;
+mod table;
 
 lazy_static! {
     static ref RESP_OK: RespFrame = TSimpleString::new("OK").into();
-    static ref RESP_UNKNOW: RespFrame = TSimpleString::new("UNKNOWN").into();
     static ref RESP_NULL: RespFrame = TNull.into();
     static ref RESP_ZERO: RespFrame = 0.into();
     static ref RESP_ONE: RespFrame = 1.into();
 }
 
+// the message starts with the redis error code (ERR, WRONGTYPE, ...) that clients dispatch on
 #[derive(Error, Debug)]
 pub enum CommandError {
-    #[error("Invalid command: {0}")]
+    #[error("ERR {0}")]
     InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
+    #[error("ERR {0}")]
     InvalidArgument(String),
-    #[error("Utf8 error: {0}")]
-    Utf8Error(#[from] std::string::FromUtf8Error),
-    #[error("{0}")]
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("ERR unknown command {0}")]
+    UnknownCommand(String),
+    #[error("ERR Protocol error: {0}")]
     RespError(#[from] RespError),
+    #[error("{0}")]
+    WrongType(#[from] WrongType),
+}
+
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        TError::new(e.to_string()).into()
+    }
+}
+
+impl From<WrongType> for RespFrame {
+    fn from(e: WrongType) -> Self {
+        CommandError::from(e).into()
+    }
+}
+
+impl From<NumericError> for RespFrame {
+    fn from(e: NumericError) -> Self {
+        TError::new(e.to_string()).into()
+    }
 }
 
 #[enum_dispatch]
@@ -46,67 +79,501 @@ pub enum Command {
     HSet(HSetArgs),
     HGetAll(HGetAllArgs),
     Echo(EchoArgs),
+    Ping(PingArgs),
     HMGet(HMGetArgs),
     SAdd(SAddArgs),
     Sismember(SismemberArgs),
-    Unrecognized(UnrecognizedArgs),
+    Expire(ExpireArgs),
+    Ttl(TtlArgs),
+    Persist(PersistArgs),
+    Save(SaveArgs),
+    BgSave(BgSaveArgs),
+    LastSave(LastSaveArgs),
+    BgRewriteAof(BgRewriteAofArgs),
+    Introspect(CommandArgs),
+    Hello(HelloArgs),
+    Del(DelArgs),
+    Exists(ExistsArgs),
+    Type(TypeArgs),
+    Rename(RenameArgs),
+    Copy(CopyArgs),
+    Keys(KeysArgs),
+    Scan(ScanArgs),
+    Select(SelectArgs),
+    Move(MoveArgs),
+    SwapDb(SwapDbArgs),
+    Flush(FlushArgs),
+    DbSize(DbSizeArgs),
+    Append(AppendArgs),
+    Strlen(StrlenArgs),
+    GetRange(GetRangeArgs),
+    SetRange(SetRangeArgs),
+    GetSet(GetSetArgs),
+    GetDel(GetDelArgs),
+    GetEx(GetExArgs),
+    MSet(MSetArgs),
+    MGet(MGetArgs),
+    Incr(IncrArgs),
+    IncrByFloat(IncrByFloatArgs),
+    HSetNx(HSetNxArgs),
+    HDel(HDelArgs),
+    HExists(HExistsArgs),
+    HLen(HLenArgs),
+    HKeys(HKeysArgs),
+    HStrlen(HStrlenArgs),
+    HIncrBy(HIncrByArgs),
+    HIncrByFloat(HIncrByFloatArgs),
+    HRandField(HRandFieldArgs),
+    HExpire(HExpireArgs),
+    HTtl(HTtlArgs),
+    HPersist(HPersistArgs),
+    SRem(SRemArgs),
+    SCard(SCardArgs),
+    SMembers(SMembersArgs),
+    SMIsMember(SMIsMemberArgs),
+    SPop(SPopArgs),
+    SRandMember(SRandMemberArgs),
+    SMove(SMoveArgs),
+    SetOp(SetOpArgs),
+    SInterCard(SInterCardArgs),
 }
 
 #[derive(Debug)]
 pub struct GetArgs {
-    key: String,
+    key: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct SetArgs {
-    key: String,
+    key: Vec<u8>,
     value: RespFrame,
+    expiration: Option<Expiration>,
+    keep_ttl: bool,
+    condition: Option<SetCondition>,
+    get: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    // relative seconds
+    Ex(i64),
+    // relative milliseconds
+    Px(i64),
+    // absolute unix seconds
+    ExAt(i64),
+    // absolute unix milliseconds
+    PxAt(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    Nx,
+    Xx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+#[derive(Debug)]
+pub struct AppendArgs {
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct StrlenArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetRangeArgs {
+    key: Vec<u8>,
+    // inclusive, negative values count from the end
+    start: i64,
+    end: i64,
+}
+
+#[derive(Debug)]
+pub struct SetRangeArgs {
+    key: Vec<u8>,
+    offset: usize,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetSetArgs {
+    key: Vec<u8>,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct GetDelArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetExArgs {
+    key: Vec<u8>,
+    expiration: Option<Expiration>,
+    persist: bool,
+}
+
+// MSET and MSETNX
+#[derive(Debug)]
+pub struct MSetArgs {
+    pairs: Vec<(Vec<u8>, RespFrame)>,
+    nx: bool,
+}
+
+#[derive(Debug)]
+pub struct MGetArgs {
+    keys: Vec<Vec<u8>>,
+}
+
+// INCR, DECR, INCRBY and DECRBY
+#[derive(Debug)]
+pub struct IncrArgs {
+    key: Vec<u8>,
+    delta: i64,
+}
+
+#[derive(Debug)]
+pub struct IncrByFloatArgs {
+    key: Vec<u8>,
+    delta: f64,
 }
 
 #[derive(Debug)]
 pub struct HGetArgs {
-    key: String,
-    field: String,
+    key: Vec<u8>,
+    field: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct HSetArgs {
-    key: String,
-    field: String,
+    key: Vec<u8>,
+    fields: Vec<(Vec<u8>, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct HSetNxArgs {
+    key: Vec<u8>,
+    field: Vec<u8>,
     value: RespFrame,
 }
 
+#[derive(Debug)]
+pub struct HDelArgs {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HExistsArgs {
+    key: Vec<u8>,
+    field: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HLenArgs {
+    key: Vec<u8>,
+}
+
+// HKEYS and HVALS
+#[derive(Debug)]
+pub struct HKeysArgs {
+    key: Vec<u8>,
+    values: bool,
+}
+
+#[derive(Debug)]
+pub struct HStrlenArgs {
+    key: Vec<u8>,
+    field: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HIncrByArgs {
+    key: Vec<u8>,
+    field: Vec<u8>,
+    delta: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloatArgs {
+    key: Vec<u8>,
+    field: Vec<u8>,
+    delta: f64,
+}
+
+#[derive(Debug)]
+pub struct HRandFieldArgs {
+    key: Vec<u8>,
+    // None replies with a single field instead of an array
+    count: Option<i64>,
+    withvalues: bool,
+}
+
+// HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT
+#[derive(Debug)]
+pub struct HExpireArgs {
+    key: Vec<u8>,
+    expiration: Expiration,
+    condition: Option<ExpireCondition>,
+    fields: Vec<Vec<u8>>,
+}
+
+// HTTL and HPTTL
+#[derive(Debug)]
+pub struct HTtlArgs {
+    key: Vec<u8>,
+    millis: bool,
+    fields: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HPersistArgs {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+}
+
 #[derive(Debug)]
 pub struct HMGetArgs {
-    key: String,
-    field: Vec<String>,
+    key: Vec<u8>,
+    field: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
 pub struct HGetAllArgs {
-    key: String,
-    sort: bool,
+    key: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct EchoArgs {
-    val: String,
+    val: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PingArgs {
+    message: Option<Vec<u8>>,
 }
 
 #[derive(Debug)]
 pub struct SAddArgs {
-    key: String,
-    val: String,
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SRemArgs {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SCardArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SMembersArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SMIsMemberArgs {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SPopArgs {
+    key: Vec<u8>,
+    // None replies with a single member instead of an array
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMemberArgs {
+    key: Vec<u8>,
+    // None replies with a single member instead of an array
+    count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Union,
+    Inter,
+    Diff,
+}
+
+// SUNION, SINTER, SDIFF and their STORE variants
+#[derive(Debug)]
+pub struct SetOpArgs {
+    operation: SetOperation,
+    // the STORE variants keep the result under this key and reply with its size
+    destination: Option<Vec<u8>>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SInterCardArgs {
+    keys: Vec<Vec<u8>>,
+    // 0 means no limit
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct SMoveArgs {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    member: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct SismemberArgs {
-    key: String,
-    val: String,
+    key: Vec<u8>,
+    val: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ExpireArgs {
+    key: Vec<u8>,
+    expiration: Expiration,
+    condition: Option<ExpireCondition>,
+}
+
+#[derive(Debug)]
+pub struct TtlArgs {
+    key: Vec<u8>,
+    millis: bool,
 }
 
 #[derive(Debug)]
-pub struct UnrecognizedArgs {}
+pub struct PersistArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct DelArgs {
+    keys: Vec<Vec<u8>>,
+    // UNLINK: large values are freed in the background
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct ExistsArgs {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct TypeArgs {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct RenameArgs {
+    key: Vec<u8>,
+    newkey: Vec<u8>,
+    nx: bool,
+}
+
+#[derive(Debug)]
+pub struct CopyArgs {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct KeysArgs {
+    pattern: Vec<u8>,
+}
+
+// SCAN, HSCAN and SSCAN
+#[derive(Debug)]
+pub struct ScanArgs {
+    target: ScanTarget,
+    cursor: u64,
+    pattern: Option<Vec<u8>>,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub enum ScanTarget {
+    Keyspace { type_name: Option<String> },
+    Hash { key: Vec<u8>, novalues: bool },
+    Set { key: Vec<u8> },
+}
+
+#[derive(Debug)]
+pub struct SelectArgs {
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct MoveArgs {
+    key: Vec<u8>,
+    db: i64,
+}
+
+#[derive(Debug)]
+pub struct SwapDbArgs {
+    first: i64,
+    second: i64,
+}
+
+// FLUSHDB and FLUSHALL
+#[derive(Debug)]
+pub struct FlushArgs {
+    all: bool,
+    // ASYNC: the old content is freed in the background
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct DbSizeArgs {}
+
+#[derive(Debug)]
+pub struct SaveArgs {}
+
+#[derive(Debug)]
+pub struct BgSaveArgs {}
+
+#[derive(Debug)]
+pub struct LastSaveArgs {}
+
+#[derive(Debug)]
+pub struct BgRewriteAofArgs {}
+
+#[derive(Debug)]
+pub struct HelloArgs {
+    protover: Option<i64>,
+    // username and password
+    auth: Option<(Vec<u8>, Vec<u8>)>,
+    setname: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct CommandArgs {
+    subcommand: CommandSubcommand,
+}
+
+#[derive(Debug)]
+pub enum CommandSubcommand {
+    List,
+    Count,
+    // empty names mean every command
+    Info(Vec<Vec<u8>>),
+    Docs(Vec<Vec<u8>>),
+    // the full request to extract keys from
+    GetKeys(TArray),
+}
 
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
@@ -124,17 +591,9 @@ impl TryFrom<TArray> for Command {
     type Error = CommandError;
     fn try_from(v: TArray) -> Result<Self, Self::Error> {
         match v.first() {
-            Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
-                b"get" => Ok(GetArgs::try_from(v)?.into()),
-                b"set" => Ok(SetArgs::try_from(v)?.into()),
-                b"hget" => Ok(HGetArgs::try_from(v)?.into()),
-                b"hset" => Ok(HSetArgs::try_from(v)?.into()),
-                b"hgetall" => Ok(HGetAllArgs::try_from(v)?.into()),
-                b"echo" => Ok(EchoArgs::try_from(v)?.into()),
-                b"hmget" => Ok(HMGetArgs::try_from(v)?.into()),
-                b"sadd" => Ok(SAddArgs::try_from(v)?.into()),
-                b"sismember" => Ok(SismemberArgs::try_from(v)?.into()),
-                _ => Ok(UnrecognizedArgs {}.into()),
+            Some(RespFrame::BulkString(ref cmd)) => match lookup_command(cmd) {
+                Some(spec) => spec.parse(v),
+                None => Err(unknown_command(&v)),
             },
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
@@ -143,51 +602,210 @@ impl TryFrom<TArray> for Command {
     }
 }
 
-fn validate_command(
-    value: &TArray,
-    names: &[&'static str],
-    n_args: usize,
-) -> Result<(), CommandError> {
-    if value.len() != n_args + names.len() {
-        return Err(CommandError::InvalidArgument(format!(
-            "{} command must have exactly {} argument",
-            names.join(" "),
-            n_args
-        )));
-    }
-    for (i, name) in names.iter().enumerate() {
-        match value[i] {
-            RespFrame::BulkString(ref cmd) => {
-                if cmd.as_ref().to_ascii_lowercase() != name.as_bytes() {
-                    return Err(CommandError::InvalidCommand(format!(
-                        "Invalid command: expected {}, got {}",
-                        name,
-                        String::from_utf8_lossy(cmd.as_ref())
-                    )));
+impl Command {
+    // the frame to append to the aof, None for commands that do not modify the dataset;
+    // relative expirations are pinned to absolute deadlines so a replay restores the same ttl
+    pub fn propagate(&mut self, frame: TArray) -> Option<TArray> {
+        match self {
+            Command::Set(args) => {
+                let Some(expiration) = args.expiration else {
+                    return Some(frame);
+                };
+                let deadline = expiration.deadline();
+                args.expiration = Some(Expiration::PxAt(deadline));
+                let mut frames = vec![
+                    b"set".into(),
+                    TBulkString::new(args.key.clone()).into(),
+                    args.value.clone(),
+                    b"pxat".into(),
+                    TBulkString::from(deadline.to_string()).into(),
+                ];
+                match args.condition {
+                    Some(SetCondition::Nx) => frames.push(b"nx".into()),
+                    Some(SetCondition::Xx) => frames.push(b"xx".into()),
+                    None => {}
                 }
+                Some(TArray::new(frames))
             }
-            _ => {
-                return Err(CommandError::InvalidCommand(
-                    "Command must have a BulkString as the first argument".to_string(),
-                ))
+            Command::GetEx(args) => {
+                let Some(expiration) = args.expiration else {
+                    return Some(frame);
+                };
+                let deadline = expiration.deadline();
+                args.expiration = Some(Expiration::PxAt(deadline));
+                Some(TArray::new([
+                    b"getex".into(),
+                    TBulkString::new(args.key.clone()).into(),
+                    b"pxat".into(),
+                    TBulkString::from(deadline.to_string()).into(),
+                ]))
             }
+            Command::Expire(args) => {
+                let deadline = args.expiration.deadline();
+                args.expiration = Expiration::PxAt(deadline);
+                let mut frames = vec![
+                    b"pexpireat".into(),
+                    TBulkString::new(args.key.clone()).into(),
+                    TBulkString::from(deadline.to_string()).into(),
+                ];
+                match args.condition {
+                    Some(ExpireCondition::Nx) => frames.push(b"nx".into()),
+                    Some(ExpireCondition::Xx) => frames.push(b"xx".into()),
+                    Some(ExpireCondition::Gt) => frames.push(b"gt".into()),
+                    Some(ExpireCondition::Lt) => frames.push(b"lt".into()),
+                    None => {}
+                }
+                Some(TArray::new(frames))
+            }
+            Command::HExpire(args) => {
+                let deadline = args.expiration.deadline();
+                args.expiration = Expiration::PxAt(deadline);
+                let mut frames = vec![
+                    b"hpexpireat".into(),
+                    TBulkString::new(args.key.clone()).into(),
+                    TBulkString::from(deadline.to_string()).into(),
+                ];
+                match args.condition {
+                    Some(ExpireCondition::Nx) => frames.push(b"nx".into()),
+                    Some(ExpireCondition::Xx) => frames.push(b"xx".into()),
+                    Some(ExpireCondition::Gt) => frames.push(b"gt".into()),
+                    Some(ExpireCondition::Lt) => frames.push(b"lt".into()),
+                    None => {}
+                }
+                frames.push(b"fields".into());
+                frames.push(TBulkString::from(args.fields.len().to_string()).into());
+                frames.extend(
+                    args.fields
+                        .iter()
+                        .map(|field| TBulkString::new(field.clone()).into()),
+                );
+                Some(TArray::new(frames))
+            }
+            _ => match frame.first() {
+                Some(RespFrame::BulkString(name)) => lookup_command(name)
+                    .filter(|spec| spec.has_flag(CommandFlag::Write))
+                    .map(|_| frame),
+                _ => None,
+            },
         }
     }
-    Ok(())
+}
+
+// what `propagate` returned, settled against the reply: SPOP picks its members at random, so it
+// is propagated as the SREM of the members it popped, and not at all when it popped nothing
+pub fn propagate_reply(frame: TArray, reply: &RespFrame) -> Option<TArray> {
+    if !is_command(&frame, "spop") {
+        return Some(frame);
+    }
+    let popped = match reply {
+        RespFrame::BulkString(member) => vec![member.clone().into()],
+        RespFrame::Array(members) if !members.is_empty() => members.0.clone(),
+        _ => return None,
+    };
+    let key = frame.get(1)?.clone();
+    let mut frames = vec![b"srem".into(), key];
+    frames.extend(popped);
+    Some(TArray::new(frames))
+}
+
+// same wording as redis: ERR unknown command 'foo', with args beginning with: 'a' 'b'
+fn unknown_command(value: &TArray) -> CommandError {
+    let quote = |frame: &RespFrame| match frame {
+        RespFrame::BulkString(s) => format!("'{}'", String::from_utf8_lossy(s)),
+        other => format!("'{:?}'", other),
+    };
+    let name = value.first().map(quote).unwrap_or_default();
+    let args = value.iter().skip(1).map(quote).collect::<Vec<_>>();
+    CommandError::UnknownCommand(format!(
+        "{}, with args beginning with: {}",
+        name,
+        args.join(" ")
+    ))
 }
 
 fn extract_args(value: TArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
 
+fn parse_key(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(key)) => Ok(key.0.into()),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+// true when the request was sent under the given name, for args shared by several commands
+fn is_command(value: &TArray, name: &str) -> bool {
+    matches!(value.first(), Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(name.as_bytes()))
+}
+
+fn parse_integer(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    let invalid =
+        || CommandError::InvalidArgument("value is not an integer or out of range".into());
+    match arg {
+        Some(RespFrame::BulkString(s)) => std::str::from_utf8(&s)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or_else(invalid),
+        Some(RespFrame::Integer(i)) => Ok(i),
+        _ => Err(invalid()),
+    }
+}
+
+// the count of SRANDMEMBER and HRANDFIELD, in redis' range. A negative count repeats items, so
+// it may not ask for more of them than a reply can hold or one request could exhaust the memory
+fn parse_random_count(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    const MAX_REPEATED: i64 = 1024 * 1024;
+    match parse_integer(arg)? {
+        count if (-MAX_REPEATED..=i64::MAX / 2).contains(&count) => Ok(count),
+        _ => Err(CommandError::InvalidArgument(
+            "value is out of range".to_string(),
+        )),
+    }
+}
+
+// finite floats only, like the increments of INCRBYFLOAT
+fn parse_float(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    let invalid = || CommandError::InvalidArgument("value is not a valid float".into());
+    match arg {
+        Some(RespFrame::BulkString(s)) => std::str::from_utf8(&s)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|f| f.is_finite())
+            .ok_or_else(invalid),
+        Some(RespFrame::Integer(i)) => Ok(i as f64),
+        _ => Err(invalid()),
+    }
+}
+
+// upper-cased option keyword such as EX, NX or KEEPTTL
+fn parse_option(arg: RespFrame) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        RespFrame::BulkString(s) => Ok(s.to_ascii_uppercase()),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+impl Expiration {
+    // absolute deadline in unix milliseconds
+    pub fn deadline(&self) -> i64 {
+        match *self {
+            Expiration::Ex(secs) => now_ms().saturating_add(secs.saturating_mul(1000)),
+            Expiration::Px(ms) => now_ms().saturating_add(ms),
+            Expiration::ExAt(secs) => secs.saturating_mul(1000),
+            Expiration::PxAt(ms) => ms,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
     use crate::database::Database;
-    use crate::resp::RespDecode;
     use crate::resp::TNull;
+    use crate::resp::{decode_frame, RespEncode};
 
     use super::*;
 
@@ -196,7 +814,7 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
 
         let cmd: Command = frame.try_into()?;
 
@@ -207,4 +825,141 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_command_case_insensitive() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
+        buf.extend_from_slice(b"*2\r\n$3\r\nGeT\r\n$5\r\nhello\r\n");
+
+        let backend = Database::new();
+        let cmd: Command = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: Command = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), b"world".into());
+
+        // keys are binary safe
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$2\r\n\xff\xfe\r\n$1\r\nv\r\n");
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$2\r\n\xff\xfe\r\n");
+        let cmd: Command = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: Command = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), b"v".into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_propagate() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nex\r\n$2\r\n10\r\n",
+        );
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let mut cmd: Command = frame.clone().try_into()?;
+        let propagated = cmd.propagate(frame).expect("set must be propagated");
+        assert_eq!(propagated[3], b"pxat".into());
+        let Command::Set(ref args) = cmd else {
+            panic!("expected a set command");
+        };
+        let deadline = match args.expiration {
+            Some(Expiration::PxAt(deadline)) => deadline,
+            other => panic!("unexpected expiration: {:?}", other),
+        };
+        assert_eq!(
+            propagated[4],
+            TBulkString::from(deadline.to_string()).into()
+        );
+
+        buf.extend_from_slice(b"*4\r\n$5\r\ngetex\r\n$5\r\nhello\r\n$2\r\nPX\r\n$3\r\n100\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let mut cmd: Command = frame.clone().try_into()?;
+        let propagated = cmd.propagate(frame).expect("getex must be propagated");
+        assert_eq!(propagated[2], b"pxat".into());
+
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let mut cmd: Command = frame.clone().try_into()?;
+        assert_eq!(cmd.propagate(frame), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_propagates_srem() -> Result<()> {
+        let backend = Database::new();
+        backend.sadd(b"s".to_vec(), b"a".to_vec())?;
+        let request = b"*3\r\n$4\r\nspop\r\n$1\r\ns\r\n$1\r\n5\r\n";
+        let frame = decode_frame::<TArray>(&mut BytesMut::from(&request[..]))?;
+        let mut cmd: Command = frame.clone().try_into()?;
+        let propagated = cmd.propagate(frame).expect("spop must be propagated");
+        let reply = cmd.execute(&backend);
+        let expected = TArray::new([b"srem".into(), b"s".into(), b"a".into()]);
+        assert_eq!(propagate_reply(propagated.clone(), &reply), Some(expected));
+
+        // nothing was popped, nothing to replay
+        let reply = TArray::new([]).into();
+        assert_eq!(propagate_reply(propagated, &reply), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_error_frame() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$3\r\nget\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let err = Command::try_from(frame).unwrap_err();
+        assert_eq!(
+            RespFrame::from(err),
+            TError::new("ERR wrong number of arguments for 'get' command").into()
+        );
+
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n:1\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let err = Command::try_from(frame).unwrap_err();
+        assert!(RespFrame::from(err).to_vec().starts_with(b"-ERR "));
+
+        buf.extend_from_slice(b"*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let err = Command::try_from(frame).unwrap_err();
+        assert_eq!(
+            RespFrame::from(err),
+            TError::new("ERR unknown command 'foo', with args beginning with: 'bar'").into()
+        );
+
+        let err = Command::try_from(RespFrame::Integer(1)).unwrap_err();
+        assert_eq!(
+            RespFrame::from(err),
+            TError::new("ERR Command must be an Array").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_wrong_type() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$1\r\nk\r\n$1\r\nf\r\n$1\r\nv\r\n");
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n");
+        buf.extend_from_slice(b"*3\r\n$4\r\nsadd\r\n$1\r\nk\r\n$1\r\nm\r\n");
+        buf.extend_from_slice(b"*4\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n$3\r\nget\r\n");
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n");
+        buf.extend_from_slice(b"*3\r\n$4\r\nhget\r\n$1\r\nk\r\n$1\r\nf\r\n");
+
+        let backend = Database::new();
+        let mut run = || -> Result<RespFrame> {
+            let cmd: Command = decode_frame::<TArray>(&mut buf)?.try_into()?;
+            Ok(cmd.execute(&backend))
+        };
+        let wrong_type: RespFrame =
+            TError::new("WRONGTYPE Operation against a key holding the wrong kind of value").into();
+        assert_eq!(run()?, RespFrame::Integer(1));
+        assert_eq!(run()?, wrong_type);
+        assert_eq!(run()?, wrong_type);
+        // SET ... GET leaves the hash alone, a plain SET replaces it
+        assert_eq!(run()?, wrong_type);
+        assert_eq!(run()?, RESP_OK.clone());
+        assert_eq!(run()?, wrong_type);
+        assert_eq!(backend.key_type(b"k"), Some("string"));
+        Ok(())
+    }
 }
@@ -0,0 +1,125 @@
+use crate::cmd::{
+    BgRewriteAofArgs, BgSaveArgs, CommandError, CommandExecute, LastSaveArgs, SaveArgs, RESP_OK,
+};
+use crate::database::Database;
+use crate::resp::{RespFrame, TArray, TError, TSimpleString};
+
+impl CommandExecute for SaveArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.save() {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => TError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecute for BgSaveArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if backend.bgsave() {
+            TSimpleString::new("Background saving started").into()
+        } else {
+            TError::new("ERR Background save already in progress").into()
+        }
+    }
+}
+
+impl CommandExecute for LastSaveArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        RespFrame::Integer(backend.last_save())
+    }
+}
+
+impl CommandExecute for BgRewriteAofArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.bgrewriteaof() {
+            Ok(true) => TSimpleString::new("Background append only file rewriting started").into(),
+            Ok(false) => {
+                TError::new("ERR Background append only file rewriting already in progress").into()
+            }
+            Err(e) => TError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<TArray> for SaveArgs {
+    type Error = CommandError;
+    fn try_from(_value: TArray) -> Result<Self, Self::Error> {
+        Ok(SaveArgs {})
+    }
+}
+
+impl TryFrom<TArray> for BgSaveArgs {
+    type Error = CommandError;
+    fn try_from(_value: TArray) -> Result<Self, Self::Error> {
+        Ok(BgSaveArgs {})
+    }
+}
+
+impl TryFrom<TArray> for LastSaveArgs {
+    type Error = CommandError;
+    fn try_from(_value: TArray) -> Result<Self, Self::Error> {
+        Ok(LastSaveArgs {})
+    }
+}
+
+impl TryFrom<TArray> for BgRewriteAofArgs {
+    type Error = CommandError;
+    fn try_from(_value: TArray) -> Result<Self, Self::Error> {
+        Ok(BgRewriteAofArgs {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::Command;
+    use crate::resp::decode_frame;
+    use crate::test_util::temp_config;
+
+    use super::*;
+
+    #[test]
+    fn test_save_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nsave\r\n");
+
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let _: SaveArgs = frame.try_into()?;
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nbgsave\r\n$8\r\nschedule\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        let result: Result<Command, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_lastsave_commands() -> Result<()> {
+        let config = temp_config("save-cmd");
+        let backend = Database::with_config(config.clone());
+        backend.set(b"hello".to_vec(), b"world".into());
+
+        let result = SaveArgs {}.execute(&backend);
+        assert_eq!(result, RESP_OK.clone());
+        assert!(config.rdb_path().exists());
+
+        let result = LastSaveArgs {}.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(backend.last_save()));
+
+        std::fs::remove_file(config.rdb_path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bgrewriteaof_disabled() {
+        let backend = Database::new();
+        let result = BgRewriteAofArgs {}.execute(&backend);
+        assert_eq!(
+            result,
+            TError::new("ERR Append only file is disabled").into()
+        );
+    }
+}
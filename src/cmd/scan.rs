@@ -0,0 +1,238 @@
+use crate::cmd::{
+    extract_args, is_command, parse_integer, parse_key, parse_option, CommandError, CommandExecute,
+    KeysArgs, ScanArgs, ScanTarget,
+};
+use crate::database::Database;
+use crate::resp::{RespFrame, TArray, TBulkString};
+
+// the default COUNT of redis
+const SCAN_COUNT: usize = 10;
+
+// the types TYPE can filter on, as reported by the TYPE command
+const TYPE_NAMES: &[&str] = &["string", "hash", "set"];
+
+impl CommandExecute for KeysArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| TBulkString::new(key).into())
+            .collect::<Vec<RespFrame>>();
+        TArray::new(keys).into()
+    }
+}
+
+impl CommandExecute for ScanArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let pattern = self.pattern.as_deref();
+        let (cursor, items) = match self.target {
+            ScanTarget::Keyspace { type_name } => {
+                let (cursor, keys) =
+                    backend.scan(self.cursor, self.count, pattern, type_name.as_deref());
+                (cursor, keys.into_iter().map(bulk).collect())
+            }
+            ScanTarget::Hash { key, novalues } => {
+                match backend.hscan(&key, self.cursor, self.count, pattern) {
+                    Ok((cursor, entries)) => {
+                        let mut items = Vec::with_capacity(entries.len() * 2);
+                        for (field, value) in entries {
+                            items.push(bulk(field));
+                            if !novalues {
+                                items.push(value);
+                            }
+                        }
+                        (cursor, items)
+                    }
+                    Err(e) => return e.into(),
+                }
+            }
+            ScanTarget::Set { key } => {
+                match backend.sscan(&key, self.cursor, self.count, pattern) {
+                    Ok((cursor, members)) => (cursor, members.into_iter().map(bulk).collect()),
+                    Err(e) => return e.into(),
+                }
+            }
+        };
+        TArray::new([
+            bulk(cursor.to_string().into_bytes()),
+            TArray::new(items).into(),
+        ])
+        .into()
+    }
+}
+
+fn bulk(data: Vec<u8>) -> RespFrame {
+    TBulkString::new(data).into()
+}
+
+impl TryFrom<TArray> for KeysArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(KeysArgs {
+            pattern: parse_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for ScanArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (hscan, sscan) = (is_command(&value, "hscan"), is_command(&value, "sscan"));
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = if hscan || sscan {
+            Some(parse_key(args.next())?)
+        } else {
+            None
+        };
+        let cursor = match args.next() {
+            Some(RespFrame::BulkString(s)) => std::str::from_utf8(&s)
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or_else(|| CommandError::InvalidArgument("invalid cursor".to_string()))?,
+            _ => return Err(CommandError::InvalidArgument("invalid cursor".to_string())),
+        };
+
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        let mut pattern = None;
+        let mut count = SCAN_COUNT;
+        let mut type_name = None;
+        let mut novalues = false;
+        while let Some(arg) = args.next() {
+            match parse_option(arg)?.as_slice() {
+                b"MATCH" => pattern = Some(parse_key(args.next()).map_err(|_| syntax_error())?),
+                b"COUNT" => {
+                    count = usize::try_from(parse_integer(args.next())?)
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(syntax_error)?;
+                }
+                b"TYPE" if key.is_none() => {
+                    let name = parse_key(args.next()).map_err(|_| syntax_error())?;
+                    let name = String::from_utf8_lossy(&name).to_ascii_lowercase();
+                    if !TYPE_NAMES.contains(&name.as_str()) {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "unknown type name '{}'",
+                            name
+                        )));
+                    }
+                    type_name = Some(name);
+                }
+                b"NOVALUES" if hscan => novalues = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let target = match key {
+            Some(key) if hscan => ScanTarget::Hash { key, novalues },
+            Some(key) => ScanTarget::Set { key },
+            None => ScanTarget::Keyspace { type_name },
+        };
+        Ok(ScanArgs {
+            target,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::TError;
+    use crate::test_util::run_args;
+
+    use super::*;
+
+    // the cursor and the elements of a scan reply
+    fn reply(frame: RespFrame) -> (String, Vec<RespFrame>) {
+        let RespFrame::Array(reply) = frame else {
+            panic!("unexpected reply: {:?}", frame);
+        };
+        match (&reply[0], &reply[1]) {
+            (RespFrame::BulkString(cursor), RespFrame::Array(items)) => {
+                (String::from_utf8_lossy(cursor).to_string(), items.0.clone())
+            }
+            _ => panic!("unexpected reply: {:?}", reply),
+        }
+    }
+
+    #[test]
+    fn test_keys() -> Result<()> {
+        let backend = Database::new();
+        for key in ["hello", "hallo", "hxllo", "world"] {
+            backend.set(key.as_bytes().to_vec(), b"v".into());
+        }
+        let RespFrame::Array(keys) = run_args(&backend, &["keys", "h[ae]llo"]) else {
+            panic!("keys must reply with an array");
+        };
+        let mut keys = keys.0;
+        keys.sort_by_key(|key| format!("{:?}", key));
+        assert_eq!(keys, vec![b"hallo".into(), b"hello".into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan() -> Result<()> {
+        let backend = Database::new();
+        for i in 0..25 {
+            backend.set(format!("key:{}", i).into_bytes(), b"v".into());
+        }
+        backend.sadd(b"set".to_vec(), b"m".to_vec())?;
+
+        let mut seen = Vec::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let (next, items) = reply(run_args(&backend, &["scan", &cursor, "match", "key:*"]));
+            seen.extend(items);
+            cursor = next;
+            if cursor == "0" {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 25);
+
+        let (cursor, items) = reply(run_args(
+            &backend,
+            &["scan", "0", "count", "100", "type", "set"],
+        ));
+        assert_eq!((cursor.as_str(), items), ("0", vec![b"set".into()]));
+
+        let frame = run_args(&backend, &["scan", "x"]);
+        assert_eq!(frame, TError::new("ERR invalid cursor").into());
+        let frame = run_args(&backend, &["scan", "0", "count", "0"]);
+        assert_eq!(frame, TError::new("ERR syntax error").into());
+        let frame = run_args(&backend, &["scan", "0", "type", "nope"]);
+        assert_eq!(frame, TError::new("ERR unknown type name 'nope'").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_sscan() -> Result<()> {
+        let backend = Database::new();
+        backend.hset(b"h".to_vec(), b"f1".to_vec(), b"v1".into())?;
+        backend.hset(b"h".to_vec(), b"g1".to_vec(), b"v2".into())?;
+        backend.sadd(b"s".to_vec(), b"m1".to_vec())?;
+        backend.sadd(b"s".to_vec(), b"n1".to_vec())?;
+
+        let (cursor, items) = reply(run_args(&backend, &["hscan", "h", "0", "match", "f*"]));
+        assert_eq!(
+            (cursor.as_str(), items),
+            ("0", vec![b"f1".into(), b"v1".into()])
+        );
+        let (_, items) = reply(run_args(&backend, &["hscan", "h", "0", "novalues"]));
+        assert_eq!(items.len(), 2);
+        let (_, items) = reply(run_args(&backend, &["sscan", "s", "0", "match", "n?"]));
+        assert_eq!(items, vec![b"n1".into()]);
+        let (cursor, items) = reply(run_args(&backend, &["sscan", "missing", "0"]));
+        assert_eq!((cursor.as_str(), items.len()), ("0", 0));
+
+        let frame = run_args(&backend, &["sscan", "h", "0"]);
+        assert!(matches!(frame, RespFrame::Error(e) if e.starts_with("WRONGTYPE")));
+        let frame = run_args(&backend, &["sscan", "s", "0", "novalues"]);
+        assert_eq!(frame, TError::new("ERR syntax error").into());
+        Ok(())
+    }
+}
@@ -1,61 +1,221 @@
 use crate::cmd::{
-    extract_args, validate_command, CommandError, CommandExecute, SAddArgs, SismemberArgs,
-    RESP_NULL, RESP_ONE, RESP_ZERO,
+    extract_args, is_command, parse_integer, parse_key, parse_option, parse_random_count,
+    CommandError, CommandExecute, SAddArgs, SCardArgs, SInterCardArgs, SMIsMemberArgs,
+    SMembersArgs, SMoveArgs, SPopArgs, SRandMemberArgs, SRemArgs, SetOpArgs, SetOperation,
+    SismemberArgs, RESP_NULL, RESP_ONE, RESP_ZERO,
 };
-use crate::resp::{RespFrame, TArray};
+use crate::resp::{RespFrame, TArray, TBulkString};
 use crate::Database;
 
 impl CommandExecute for SAddArgs {
     fn execute(self, backend: &Database) -> RespFrame {
-        backend.sadd(self.key, self.val);
-        RESP_ONE.clone()
+        match backend.sadd_multi(self.key, self.members) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
     }
 }
 
-impl TryFrom<TArray> for SAddArgs {
-    type Error = CommandError;
-    fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sadd"], 2)?;
+impl CommandExecute for SRemArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.srem(self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
 
-        let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(val))) => Ok(SAddArgs {
-                key: String::from_utf8(key.0)?,
-                val: String::from_utf8(val.0)?,
-            }),
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key, value".to_string(),
-            )),
+impl CommandExecute for SCardArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.scard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
         }
     }
 }
 
-impl CommandExecute for SismemberArgs {
+impl CommandExecute for SMembersArgs {
     fn execute(self, backend: &Database) -> RespFrame {
         match backend.sall(&self.key) {
-            Some(value) => {
-                if value.contains(&self.val) {
-                    RESP_ONE.clone()
-                } else {
-                    RESP_ZERO.clone()
-                }
-            }
-            None => RESP_NULL.clone(),
+            Ok(set) => members_reply(set.unwrap_or_default()),
+            Err(e) => e.into(),
         }
     }
 }
 
-impl TryFrom<TArray> for SismemberArgs {
+impl CommandExecute for SismemberArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.smismember(&self.key, &[self.val]) {
+            Ok(found) if found[0] => RESP_ONE.clone(),
+            Ok(_) => RESP_ZERO.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for SMIsMemberArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.smismember(&self.key, &self.members) {
+            Ok(found) => TArray::new(
+                found
+                    .into_iter()
+                    .map(|found| RespFrame::Integer(found as i64))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for SPopArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.spop(self.key, self.count.unwrap_or(1)) {
+            Ok(popped) if self.count.is_some() => members_reply(popped),
+            Ok(popped) => single_reply(popped),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for SRandMemberArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.srandmember(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) if self.count.is_some() => members_reply(members),
+            Ok(members) => single_reply(members),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for SMoveArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.smove(&self.source, self.destination, self.member) {
+            Ok(true) => RESP_ONE.clone(),
+            Ok(false) => RESP_ZERO.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for SetOpArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let result = match self.operation {
+            SetOperation::Union => backend.sunion(&self.keys),
+            SetOperation::Inter => backend.sinter(&self.keys),
+            SetOperation::Diff => backend.sdiff(&self.keys),
+        };
+        let set = match result {
+            Ok(set) => set,
+            Err(e) => return e.into(),
+        };
+        match self.destination {
+            Some(destination) => RespFrame::Integer(backend.sstore(destination, set) as i64),
+            None => members_reply(set),
+        }
+    }
+}
+
+impl CommandExecute for SInterCardArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.sintercard(&self.keys, self.limit) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn members_reply(members: impl IntoIterator<Item = Vec<u8>>) -> RespFrame {
+    let members = members
+        .into_iter()
+        .map(|member| TBulkString::new(member).into())
+        .collect::<Vec<RespFrame>>();
+    TArray::new(members).into()
+}
+
+// the reply without a count: the member, or null for a missing key
+fn single_reply(members: Vec<Vec<u8>>) -> RespFrame {
+    match members.into_iter().next() {
+        Some(member) => TBulkString::new(member).into(),
+        None => RESP_NULL.clone(),
+    }
+}
+
+fn parse_member(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(member)) => Ok(member.0.into()),
+        _ => Err(CommandError::InvalidArgument("Invalid member".to_string())),
+    }
+}
+
+// the key followed by at least one member
+fn parse_key_members(value: TArray, name: &str) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = parse_key(args.next())?;
+    let members = args
+        .map(|arg| parse_member(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if members.is_empty() {
+        return Err(CommandError::WrongArity(name.to_string()));
+    }
+    Ok((key, members))
+}
+
+// the optional count of SPOP and SRANDMEMBER, nothing may follow it
+fn parse_count(
+    mut args: impl Iterator<Item = RespFrame>,
+    parse: fn(Option<RespFrame>) -> Result<i64, CommandError>,
+) -> Result<Option<i64>, CommandError> {
+    let count = args.next().map(|arg| parse(Some(arg))).transpose()?;
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok(count)
+}
+
+impl TryFrom<TArray> for SAddArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "sadd")?;
+        Ok(SAddArgs { key, members })
+    }
+}
+
+impl TryFrom<TArray> for SRemArgs {
     type Error = CommandError;
     fn try_from(value: TArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sismember"], 2)?;
+        let (key, members) = parse_key_members(value, "srem")?;
+        Ok(SRemArgs { key, members })
+    }
+}
 
+impl TryFrom<TArray> for SCardArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        Ok(SCardArgs { key })
+    }
+}
+
+impl TryFrom<TArray> for SMembersArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        Ok(SMembersArgs { key })
+    }
+}
+
+impl TryFrom<TArray> for SismemberArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(val))) => {
                 Ok(SismemberArgs {
-                    key: String::from_utf8(key.0)?,
-                    val: String::from_utf8(val.0)?,
+                    key: key.0.into(),
+                    val: val.0.into(),
                 })
             }
             _ => Err(CommandError::InvalidArgument(
@@ -65,12 +225,142 @@ impl TryFrom<TArray> for SismemberArgs {
     }
 }
 
+impl TryFrom<TArray> for SMIsMemberArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value, "smismember")?;
+        Ok(SMIsMemberArgs { key, members })
+    }
+}
+
+impl TryFrom<TArray> for SPopArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let count = match parse_count(args, parse_integer)? {
+            Some(count) if count < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|count| count as usize),
+        };
+        Ok(SPopArgs { key, count })
+    }
+}
+
+impl TryFrom<TArray> for SRandMemberArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let count = parse_count(args, parse_random_count)?;
+        Ok(SRandMemberArgs { key, count })
+    }
+}
+
+impl TryFrom<TArray> for SetOpArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        const OPERATIONS: [(&str, SetOperation, bool); 6] = [
+            ("sunion", SetOperation::Union, false),
+            ("sunionstore", SetOperation::Union, true),
+            ("sinter", SetOperation::Inter, false),
+            ("sinterstore", SetOperation::Inter, true),
+            ("sdiff", SetOperation::Diff, false),
+            ("sdiffstore", SetOperation::Diff, true),
+        ];
+        let Some(&(name, operation, store)) = OPERATIONS
+            .iter()
+            .find(|(name, ..)| is_command(&value, name))
+        else {
+            return Err(CommandError::InvalidCommand(
+                "Invalid command: expected a set operation".to_string(),
+            ));
+        };
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = match store {
+            true => Some(parse_key(args.next())?),
+            false => None,
+        };
+        let keys = args
+            .map(|arg| parse_key(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        Ok(SetOpArgs {
+            operation,
+            destination,
+            keys,
+        })
+    }
+}
+
+impl TryFrom<TArray> for SInterCardArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let numkeys = parse_integer(args.next())?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let args = args.collect::<Vec<_>>();
+        if numkeys as usize > args.len() {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let mut args = args.into_iter();
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(|arg| parse_key(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let limit = match args.next().map(parse_option).transpose()?.as_deref() {
+            None => 0,
+            Some(b"LIMIT") => match parse_integer(args.next())? {
+                limit if limit < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "LIMIT can't be negative".to_string(),
+                    ))
+                }
+                limit => limit as usize,
+            },
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(SInterCardArgs { keys, limit })
+    }
+}
+
+impl TryFrom<TArray> for SMoveArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = parse_key(args.next())?;
+        let destination = parse_key(args.next())?;
+        let member = parse_member(args.next())?;
+        Ok(SMoveArgs {
+            source,
+            destination,
+            member,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::resp::RespDecode;
+    use crate::resp::{decode_frame, RespEncode};
+    use crate::test_util::run;
 
     use super::*;
 
@@ -79,10 +369,160 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*3\r\n$4\r\nsadd\r\n$6\r\nmyhash\r\n$6\r\nfield1\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
         let result: SAddArgs = frame.try_into()?;
-        assert_eq!(result.key, "myhash");
-        assert_eq!(result.val, "field1");
+        assert_eq!(result.key, b"myhash");
+        assert_eq!(result.members, vec![b"field1".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_member() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nsadd\r\n$2\r\n\xc3\x28\r\n$3\r\n\x00\xff\n\r\n");
+        buf.extend_from_slice(b"*3\r\n$9\r\nsismember\r\n$2\r\n\xc3\x28\r\n$3\r\n\x00\xff\n\r\n");
+
+        let backend = Database::new();
+        let cmd: SAddArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.key, b"\xc3\x28");
+        cmd.execute(&backend);
+        let cmd: SismemberArgs = decode_frame::<TArray>(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_ONE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_commands() -> Result<()> {
+        let backend = Database::new();
+        let sismember = b"*3\r\n$9\r\nsismember\r\n$1\r\ns\r\n$1\r\na\r\n";
+        assert_eq!(run(&backend, sismember)?, RESP_ZERO.clone());
+
+        let sadd = b"*5\r\n$4\r\nsadd\r\n$1\r\ns\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\na\r\n";
+        assert_eq!(run(&backend, sadd)?, RespFrame::Integer(2));
+        assert_eq!(run(&backend, sadd)?, RESP_ZERO.clone());
+        assert_eq!(run(&backend, sismember)?, RESP_ONE.clone());
+
+        let smismember = b"*4\r\n$10\r\nsmismember\r\n$1\r\ns\r\n$1\r\nb\r\n$1\r\nx\r\n";
+        assert_eq!(
+            run(&backend, smismember)?,
+            TArray::new([RESP_ONE.clone(), RESP_ZERO.clone()]).into()
+        );
+        let scard = b"*2\r\n$5\r\nscard\r\n$1\r\ns\r\n";
+        assert_eq!(run(&backend, scard)?, RespFrame::Integer(2));
+        let smembers = b"*2\r\n$8\r\nsmembers\r\n$1\r\ns\r\n";
+        let RespFrame::Array(mut members) = run(&backend, smembers)? else {
+            panic!("expected an array");
+        };
+        members.0.sort_by_key(|m| m.clone().to_vec());
+        assert_eq!(members, TArray::new([b"a".into(), b"b".into()]));
+
+        let smove = b"*4\r\n$5\r\nsmove\r\n$1\r\ns\r\n$1\r\nd\r\n$1\r\na\r\n";
+        assert_eq!(run(&backend, smove)?, RESP_ONE.clone());
+        assert_eq!(run(&backend, smove)?, RESP_ZERO.clone());
+
+        let srem = b"*4\r\n$4\r\nsrem\r\n$1\r\ns\r\n$1\r\nb\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, srem)?, RESP_ONE.clone());
+        assert!(!backend.exists(b"s"));
+        assert_eq!(run(&backend, scard)?, RESP_ZERO.clone());
+        assert_eq!(run(&backend, smembers)?.to_vec(), b"*0\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember_commands() -> Result<()> {
+        let backend = Database::new();
+        let spop = b"*2\r\n$4\r\nspop\r\n$1\r\ns\r\n";
+        assert_eq!(run(&backend, spop)?, RESP_NULL.clone());
+        let srandmember = b"*3\r\n$11\r\nsrandmember\r\n$1\r\ns\r\n$2\r\n-3\r\n";
+        assert_eq!(run(&backend, srandmember)?.to_vec(), b"*0\r\n");
+
+        backend.sadd(b"s".to_vec(), b"a".to_vec())?;
+        backend.sadd(b"s".to_vec(), b"b".to_vec())?;
+        let len = |frame: RespFrame| match frame {
+            RespFrame::Array(array) => array.len(),
+            other => panic!("expected an array, got {:?}", other),
+        };
+        // a negative count may repeat members
+        assert_eq!(len(run(&backend, srandmember)?), 3);
+        let srandmember = b"*3\r\n$11\r\nsrandmember\r\n$1\r\ns\r\n$1\r\n5\r\n";
+        assert_eq!(len(run(&backend, srandmember)?), 2);
+
+        let popped = run(&backend, spop)?;
+        assert!(popped == b"a".into() || popped == b"b".into());
+        let spop = b"*3\r\n$4\r\nspop\r\n$1\r\ns\r\n$1\r\n5\r\n";
+        assert_eq!(len(run(&backend, spop)?), 1);
+        assert!(!backend.exists(b"s"));
+
+        let negative = b"*3\r\n$4\r\nspop\r\n$1\r\ns\r\n$2\r\n-1\r\n";
+        assert!(run(&backend, negative).is_err());
+
+        // a count that would repeat members without bound is refused before anything is allocated
+        for count in ["-1000000000", "-9223372036854775808", "9223372036854775807"] {
+            let request = format!(
+                "*3\r\n$11\r\nsrandmember\r\n$1\r\ns\r\n${}\r\n{}\r\n",
+                count.len(),
+                count
+            );
+            assert!(run(&backend, request.as_bytes()).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_operation_commands() -> Result<()> {
+        let backend = Database::new();
+        backend.sadd_multi(
+            b"a".to_vec(),
+            vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()],
+        )?;
+        backend.sadd_multi(
+            b"b".to_vec(),
+            vec![b"2".to_vec(), b"3".to_vec(), b"4".to_vec()],
+        )?;
+        let sorted = |frame: RespFrame| {
+            let RespFrame::Array(array) = frame else {
+                panic!("expected an array, got {:?}", frame);
+            };
+            let mut members = array.0.into_iter().map(|m| m.to_vec()).collect::<Vec<_>>();
+            members.sort();
+            members
+        };
+        let bulk = |items: &[&str]| {
+            items
+                .iter()
+                .map(|item| RespFrame::from(TBulkString::from(*item)).to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        let sunion = b"*3\r\n$6\r\nsunion\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(sorted(run(&backend, sunion)?), bulk(&["1", "2", "3", "4"]));
+        let sinter = b"*4\r\n$6\r\nSINTER\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, sinter)?.to_vec(), b"*0\r\n");
+        let sdiff = b"*3\r\n$5\r\nsdiff\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(sorted(run(&backend, sdiff)?), bulk(&["1"]));
+
+        // the destination is also a source
+        let sinterstore = b"*4\r\n$11\r\nsinterstore\r\n$1\r\na\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, sinterstore)?, RespFrame::Integer(2));
+        let smembers = b"*2\r\n$8\r\nsmembers\r\n$1\r\na\r\n";
+        assert_eq!(sorted(run(&backend, smembers)?), bulk(&["2", "3"]));
+        let sdiffstore = b"*4\r\n$10\r\nsdiffstore\r\n$1\r\nd\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, sdiffstore)?, RESP_ZERO.clone());
+        assert!(!backend.exists(b"d"));
+
+        let sintercard = b"*4\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert_eq!(run(&backend, sintercard)?, RespFrame::Integer(2));
+        let limited = b"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nlimit\r\n$1\r\n1\r\n";
+        assert_eq!(run(&backend, limited)?, RESP_ONE.clone());
+        let too_many = b"*4\r\n$10\r\nsintercard\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nb\r\n";
+        assert!(run(&backend, too_many).is_err());
+        let negative =
+            b"*6\r\n$10\r\nsintercard\r\n$1\r\n1\r\n$1\r\na\r\n$5\r\nLIMIT\r\n$2\r\n-1\r\n";
+        assert!(run(&backend, negative).is_err());
+
+        backend.set(b"s".to_vec(), b"v".into());
+        let wrong = b"*3\r\n$6\r\nsunion\r\n$1\r\na\r\n$1\r\ns\r\n";
+        assert!(matches!(run(&backend, wrong)?, RespFrame::Error(e) if e.starts_with("WRONGTYPE")));
         Ok(())
     }
 }
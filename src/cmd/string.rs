@@ -0,0 +1,536 @@
+use crate::cmd::{
+    extract_args, is_command, parse_float, parse_integer, parse_key, parse_option, AppendArgs,
+    CommandError, CommandExecute, Expiration, GetDelArgs, GetExArgs, GetRangeArgs, GetSetArgs,
+    IncrArgs, IncrByFloatArgs, MGetArgs, MSetArgs, SetRangeArgs, StrlenArgs, RESP_NULL, RESP_OK,
+    RESP_ONE, RESP_ZERO,
+};
+use crate::database::{string_bytes, Database};
+use crate::resp::{RespFrame, TArray, TBulkString};
+
+impl CommandExecute for AppendArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.append(self.key, &self.value) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for StrlenArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.get(&self.key) {
+            Ok(value) => {
+                let len = value.map(|v| string_bytes(v).len()).unwrap_or_default();
+                RespFrame::Integer(len as i64)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for GetRangeArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let data = match backend.get(&self.key) {
+            Ok(Some(value)) => string_bytes(value),
+            Ok(None) => return TBulkString::new(vec![]).into(),
+            Err(e) => return e.into(),
+        };
+        let len = data.len() as i64;
+        // negative positions count from the end, both ends are clamped to the string like in redis
+        let index = |i: i64| if i < 0 { len + i } else { i };
+        let (start, end) = (
+            index(self.start).max(0),
+            index(self.end).max(0).min(len - 1),
+        );
+        if start > end {
+            return TBulkString::new(vec![]).into();
+        }
+        TBulkString(data.slice(start as usize..=end as usize)).into()
+    }
+}
+
+impl CommandExecute for SetRangeArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // same bound as a single bulk string in a request
+        if self.offset.saturating_add(self.value.len()) > backend.config.proto_max_bulk_len {
+            return CommandError::InvalidArgument(
+                "string exceeds maximum allowed size (proto-max-bulk-len)".to_string(),
+            )
+            .into();
+        }
+        match backend.setrange(self.key, self.offset, &self.value) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for GetSetArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.getset(self.key, self.value) {
+            Ok(old) => old.unwrap_or_else(|| RESP_NULL.clone()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for GetDelArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.getdel(&self.key) {
+            Ok(value) => value.unwrap_or_else(|| RESP_NULL.clone()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for GetExArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        let deadline = self.expiration.map(|expiration| expiration.deadline());
+        match backend.getex(&self.key, deadline, self.persist) {
+            Ok(Some(value)) => value,
+            Ok(None) => RESP_NULL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for MSetArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        if !self.nx {
+            backend.mset(self.pairs);
+            RESP_OK.clone()
+        } else if backend.msetnx(self.pairs) {
+            RESP_ONE.clone()
+        } else {
+            RESP_ZERO.clone()
+        }
+    }
+}
+
+impl CommandExecute for MGetArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // keys holding another type are reported as missing, like in redis
+        let values = self
+            .keys
+            .iter()
+            .map(|key| match backend.get(key) {
+                Ok(Some(value)) => value,
+                _ => RESP_NULL.clone(),
+            })
+            .collect::<Vec<_>>();
+        TArray::new(values).into()
+    }
+}
+
+impl CommandExecute for IncrArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        match backend.incr_by(self.key, self.delta) {
+            Ok(value) => RespFrame::Integer(value),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecute for IncrByFloatArgs {
+    fn execute(self, backend: &Database) -> RespFrame {
+        // replied as a bulk string, the same text that was stored
+        match backend.incr_by_float(self.key, self.delta) {
+            Ok(value) => TBulkString::from(value.to_string()).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn parse_value(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(value)) => Ok(value.0.into()),
+        _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+    }
+}
+
+impl TryFrom<TArray> for AppendArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(AppendArgs {
+            key: parse_key(args.next())?,
+            value: parse_value(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for StrlenArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(StrlenArgs {
+            key: parse_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for GetRangeArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(GetRangeArgs {
+            key: parse_key(args.next())?,
+            start: parse_integer(args.next())?,
+            end: parse_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for SetRangeArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let offset = usize::try_from(parse_integer(args.next())?)
+            .map_err(|_| CommandError::InvalidArgument("offset is out of range".to_string()))?;
+        Ok(SetRangeArgs {
+            key,
+            offset,
+            value: parse_value(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for GetSetArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        match args.next() {
+            Some(value) => Ok(GetSetArgs { key, value }),
+            None => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+        }
+    }
+}
+
+impl TryFrom<TArray> for GetDelArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(GetDelArgs {
+            key: parse_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<TArray> for GetExArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let mut expiration = None;
+        let mut persist = false;
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            if expiration.is_some() || persist {
+                return Err(syntax_error());
+            }
+            let option = parse_option(arg)?;
+            match option.as_slice() {
+                b"PERSIST" => persist = true,
+                b"EX" | b"PX" | b"EXAT" | b"PXAT" => {
+                    let time = parse_integer(args.next())?;
+                    if time <= 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "invalid expire time in 'getex' command".to_string(),
+                        ));
+                    }
+                    expiration = Some(match option.as_slice() {
+                        b"EX" => Expiration::Ex(time),
+                        b"PX" => Expiration::Px(time),
+                        b"EXAT" => Expiration::ExAt(time),
+                        _ => Expiration::PxAt(time),
+                    });
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(GetExArgs {
+            key,
+            expiration,
+            persist,
+        })
+    }
+}
+
+impl TryFrom<TArray> for MSetArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let nx = is_command(&value, "msetnx");
+        let args = extract_args(value, 1)?;
+        if args.is_empty() || args.len() % 2 != 0 {
+            let name = if nx { "msetnx" } else { "mset" };
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        let mut args = args.into_iter();
+        let mut pairs = Vec::with_capacity(args.len() / 2);
+        while let (key @ Some(_), Some(value)) = (args.next(), args.next()) {
+            pairs.push((parse_key(key)?, value));
+        }
+        Ok(MSetArgs { pairs, nx })
+    }
+}
+
+impl TryFrom<TArray> for IncrArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let (by, negate) = match () {
+            _ if is_command(&value, "incr") => (false, false),
+            _ if is_command(&value, "decr") => (false, true),
+            _ if is_command(&value, "incrby") => (true, false),
+            _ => (true, true),
+        };
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let delta = if by { parse_integer(args.next())? } else { 1 };
+        let delta = match negate {
+            false => delta,
+            true => delta.checked_neg().ok_or_else(|| {
+                CommandError::InvalidArgument("decrement would overflow".to_string())
+            })?,
+        };
+        Ok(IncrArgs { key, delta })
+    }
+}
+
+impl TryFrom<TArray> for IncrByFloatArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = parse_key(args.next())?;
+        let delta = parse_float(args.next())?;
+        Ok(IncrByFloatArgs { key, delta })
+    }
+}
+
+impl TryFrom<TArray> for MGetArgs {
+    type Error = CommandError;
+    fn try_from(value: TArray) -> Result<Self, Self::Error> {
+        let keys = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| parse_key(Some(arg)))
+            .collect::<Result<_, _>>()?;
+        Ok(MGetArgs { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::Command;
+    use crate::resp::{decode_frame, RespEncode, TError};
+    use crate::test_util::run;
+
+    use super::*;
+
+    #[test]
+    fn test_append_strlen_getrange_setrange() -> Result<()> {
+        let backend = Database::new();
+        let append = b"*3\r\n$6\r\nappend\r\n$1\r\nk\r\n$11\r\nhello world\r\n";
+        assert_eq!(run(&backend, append)?, RespFrame::Integer(11));
+        let strlen = b"*2\r\n$6\r\nstrlen\r\n$1\r\nk\r\n";
+        assert_eq!(run(&backend, strlen)?, RespFrame::Integer(11));
+        let strlen = b"*2\r\n$6\r\nstrlen\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, strlen)?, RespFrame::Integer(0));
+
+        let getrange = |start: &str, end: &str| {
+            let request = format!(
+                "*4\r\n$8\r\ngetrange\r\n$1\r\nk\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
+                start.len(),
+                start,
+                end.len(),
+                end
+            );
+            run(&backend, request.as_bytes()).map(|frame| frame.to_vec())
+        };
+        assert_eq!(getrange("0", "4")?, b"$5\r\nhello\r\n");
+        assert_eq!(getrange("-5", "-1")?, b"$5\r\nworld\r\n");
+        assert_eq!(getrange("6", "100")?, b"$5\r\nworld\r\n");
+        assert_eq!(getrange("5", "2")?, b"$0\r\n\r\n");
+        assert_eq!(getrange("-1", "-5")?, b"$0\r\n\r\n");
+        assert_eq!(getrange("0", "-100")?, b"$1\r\nh\r\n");
+
+        let setrange = b"*4\r\n$8\r\nsetrange\r\n$1\r\nk\r\n$1\r\n6\r\n$5\r\nredis\r\n";
+        assert_eq!(run(&backend, setrange)?, RespFrame::Integer(11));
+        assert_eq!(backend.get(b"k")?, Some(b"hello redis".into()));
+        let setrange = b"*4\r\n$8\r\nsetrange\r\n$1\r\np\r\n$1\r\n2\r\n$1\r\nx\r\n";
+        assert_eq!(run(&backend, setrange)?, RespFrame::Integer(3));
+        assert_eq!(backend.get(b"p")?, Some(b"\0\0x".into()));
+
+        let setrange = b"*4\r\n$8\r\nsetrange\r\n$1\r\np\r\n$2\r\n-1\r\n$1\r\nx\r\n";
+        assert!(run(&backend, setrange).is_err());
+        let setrange = b"*4\r\n$8\r\nsetrange\r\n$1\r\np\r\n$10\r\n9999999999\r\n$1\r\nx\r\n";
+        assert_eq!(
+            run(&backend, setrange)?,
+            TError::new("ERR string exceeds maximum allowed size (proto-max-bulk-len)").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_getset_getdel_getex() -> Result<()> {
+        let backend = Database::new();
+        let getset = b"*3\r\n$6\r\ngetset\r\n$1\r\nk\r\n$1\r\n1\r\n";
+        assert_eq!(run(&backend, getset)?, RESP_NULL.clone());
+        assert_eq!(run(&backend, getset)?, RespFrame::BulkString(b"1".into()));
+
+        let getex = b"*4\r\n$5\r\ngetex\r\n$1\r\nk\r\n$2\r\nex\r\n$3\r\n100\r\n";
+        assert_eq!(run(&backend, getex)?, RespFrame::BulkString(b"1".into()));
+        assert!(backend.expire_time(b"k").is_some());
+        let getex = b"*3\r\n$5\r\ngetex\r\n$1\r\nk\r\n$7\r\npersist\r\n";
+        assert_eq!(run(&backend, getex)?, RespFrame::BulkString(b"1".into()));
+        assert_eq!(backend.expire_time(b"k"), None);
+        let getex = b"*5\r\n$5\r\ngetex\r\n$1\r\nk\r\n$2\r\nex\r\n$1\r\n1\r\n$7\r\npersist\r\n";
+        assert!(run(&backend, getex).is_err());
+        let getex = b"*4\r\n$5\r\ngetex\r\n$1\r\nk\r\n$2\r\npx\r\n$1\r\n0\r\n";
+        assert!(run(&backend, getex).is_err());
+
+        let getdel = b"*2\r\n$6\r\ngetdel\r\n$1\r\nk\r\n";
+        assert_eq!(run(&backend, getdel)?, RespFrame::BulkString(b"1".into()));
+        assert_eq!(run(&backend, getdel)?, RESP_NULL.clone());
+
+        backend.sadd(b"s".to_vec(), b"m".to_vec())?;
+        let getdel = b"*2\r\n$6\r\ngetdel\r\n$1\r\ns\r\n";
+        let reply = run(&backend, getdel)?;
+        assert!(matches!(reply, RespFrame::Error(e) if e.starts_with("WRONGTYPE")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_incr_family() -> Result<()> {
+        let backend = Database::new();
+        let incr = b"*2\r\n$4\r\nincr\r\n$1\r\nn\r\n";
+        assert_eq!(run(&backend, incr)?, RespFrame::Integer(1));
+        let incrby = b"*3\r\n$6\r\nincrby\r\n$1\r\nn\r\n$2\r\n10\r\n";
+        assert_eq!(run(&backend, incrby)?, RespFrame::Integer(11));
+        let decr = b"*2\r\n$4\r\ndecr\r\n$1\r\nn\r\n";
+        assert_eq!(run(&backend, decr)?, RespFrame::Integer(10));
+        let decrby = b"*3\r\n$6\r\ndecrby\r\n$1\r\nn\r\n$2\r\n20\r\n";
+        assert_eq!(run(&backend, decrby)?, RespFrame::Integer(-10));
+        // the counter is an ordinary string
+        assert_eq!(backend.get(b"n")?, Some(b"-10".into()));
+
+        let not_integer = TError::new("ERR value is not an integer or out of range").into();
+        for value in ["abc", "1.5", "01", " 1", ""] {
+            backend.set(b"s".to_vec(), value.into());
+            let incr = b"*2\r\n$4\r\nincr\r\n$1\r\ns\r\n";
+            assert_eq!(run(&backend, incr)?, not_integer);
+        }
+        let incrby = b"*3\r\n$6\r\nincrby\r\n$1\r\nn\r\n$1\r\nx\r\n";
+        assert!(run(&backend, incrby).is_err());
+
+        backend.set(
+            b"max".to_vec(),
+            TBulkString::from(i64::MAX.to_string()).into(),
+        );
+        let incr = b"*2\r\n$4\r\nincr\r\n$3\r\nmax\r\n";
+        assert_eq!(
+            run(&backend, incr)?,
+            TError::new("ERR increment or decrement would overflow").into()
+        );
+        assert_eq!(
+            backend.get(b"max")?,
+            Some(TBulkString::from(i64::MAX.to_string()).into())
+        );
+        let decrby = b"*3\r\n$6\r\ndecrby\r\n$1\r\nn\r\n$20\r\n-9223372036854775808\r\n";
+        assert!(run(&backend, decrby).is_err());
+
+        backend.sadd(b"set".to_vec(), b"m".to_vec())?;
+        let incr = b"*2\r\n$4\r\nincr\r\n$3\r\nset\r\n";
+        assert!(matches!(run(&backend, incr)?, RespFrame::Error(e) if e.starts_with("WRONGTYPE")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_incrbyfloat() -> Result<()> {
+        let backend = Database::new();
+        let incr = |delta: &str| {
+            let request = format!(
+                "*3\r\n$11\r\nincrbyfloat\r\n$1\r\nf\r\n${}\r\n{}\r\n",
+                delta.len(),
+                delta
+            );
+            run(&backend, request.as_bytes())
+        };
+        assert_eq!(incr("10.5")?, RespFrame::BulkString(b"10.5".into()));
+        assert_eq!(incr("0.1")?, RespFrame::BulkString(b"10.6".into()));
+        assert_eq!(incr("-5.6")?, RespFrame::BulkString(b"5".into()));
+        assert_eq!(incr("2e2")?, RespFrame::BulkString(b"205".into()));
+        assert!(incr("abc").is_err());
+        assert!(incr("inf").is_err());
+
+        backend.set(
+            b"f".to_vec(),
+            TBulkString::from(f64::MAX.to_string()).into(),
+        );
+        assert_eq!(
+            incr(&f64::MAX.to_string())?,
+            TError::new("ERR increment would produce NaN or Infinity").into()
+        );
+        backend.set(b"x".to_vec(), b"abc".into());
+        let request = b"*3\r\n$11\r\nincrbyfloat\r\n$1\r\nx\r\n$1\r\n1\r\n";
+        assert_eq!(
+            run(&backend, request)?,
+            TError::new("ERR value is not a valid float").into()
+        );
+        // a failed increment leaves the value alone
+        assert_eq!(backend.get(b"x")?, Some(b"abc".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_incr() -> Result<()> {
+        let backend = Database::new();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        IncrArgs {
+                            key: b"counter".to_vec(),
+                            delta: 1,
+                        }
+                        .execute(&backend);
+                    }
+                });
+            }
+        });
+        assert_eq!(backend.get(b"counter")?, Some(b"8000".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_msetnx_mget() -> Result<()> {
+        let backend = Database::new();
+        let mset = b"*5\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n";
+        assert_eq!(run(&backend, mset)?, RESP_OK.clone());
+        let msetnx = b"*5\r\n$6\r\nmsetnx\r\n$1\r\nb\r\n$1\r\n3\r\n$1\r\nc\r\n$1\r\n3\r\n";
+        assert_eq!(run(&backend, msetnx)?, RESP_ZERO.clone());
+        assert!(!backend.exists(b"c"));
+
+        backend.sadd(b"s".to_vec(), b"m".to_vec())?;
+        let mget = b"*5\r\n$4\r\nmget\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\ns\r\n$1\r\nc\r\n";
+        let expected = TArray::new([
+            b"1".into(),
+            b"2".into(),
+            RESP_NULL.clone(),
+            RESP_NULL.clone(),
+        ]);
+        assert_eq!(run(&backend, mget)?, expected.into());
+
+        let mset = b"*4\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n";
+        let cmd: Result<Command, CommandError> =
+            decode_frame::<TArray>(&mut BytesMut::from(&mset[..]))?.try_into();
+        assert_eq!(
+            cmd.map(|_| ()).map_err(|e| e.to_string()),
+            Err("ERR wrong number of arguments for 'mset' command".to_string())
+        );
+        Ok(())
+    }
+}
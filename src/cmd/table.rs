@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::cmd::{
+    AppendArgs, BgRewriteAofArgs, BgSaveArgs, Command, CommandArgs, CommandError, CopyArgs,
+    DbSizeArgs, DelArgs, EchoArgs, ExistsArgs, ExpireArgs, FlushArgs, GetArgs, GetDelArgs,
+    GetExArgs, GetRangeArgs, GetSetArgs, HDelArgs, HExistsArgs, HExpireArgs, HGetAllArgs, HGetArgs,
+    HIncrByArgs, HIncrByFloatArgs, HKeysArgs, HLenArgs, HMGetArgs, HPersistArgs, HRandFieldArgs,
+    HSetArgs, HSetNxArgs, HStrlenArgs, HTtlArgs, HelloArgs, IncrArgs, IncrByFloatArgs, KeysArgs,
+    LastSaveArgs, MGetArgs, MSetArgs, MoveArgs, PersistArgs, PingArgs, RenameArgs, SAddArgs,
+    SCardArgs, SInterCardArgs, SMIsMemberArgs, SMembersArgs, SMoveArgs, SPopArgs, SRandMemberArgs,
+    SRemArgs, SaveArgs, ScanArgs, SelectArgs, SetArgs, SetOpArgs, SetRangeArgs, SismemberArgs,
+    StrlenArgs, SwapDbArgs, TtlArgs, TypeArgs,
+};
+use crate::resp::TArray;
+
+use CommandFlag::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    ReadOnly,
+    Fast,
+    Admin,
+    // allowed before the connection authenticated
+    NoAuth,
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    // redis convention: the argument count including the name, negative means "at least"
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    // 1-based positions of the keys in the arguments, 0 when there are none, -1 for the last one
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+    // shown by COMMAND DOCS
+    pub group: &'static str,
+    pub summary: &'static str,
+    parse: fn(TArray) -> Result<Command, CommandError>,
+}
+
+macro_rules! spec {
+    ($name:expr, $arity:expr, $flags:expr, ($first:expr, $last:expr, $step:expr), $args:ty,
+     $group:expr, $summary:expr) => {
+        CommandSpec {
+            name: $name,
+            arity: $arity,
+            flags: $flags,
+            first_key: $first,
+            last_key: $last,
+            step: $step,
+            group: $group,
+            summary: $summary,
+            parse: |v| Ok(<$args>::try_from(v)?.into()),
+        }
+    };
+}
+
+static COMMANDS: &[CommandSpec] = &[
+    spec!("get", 2, &[ReadOnly, Fast], (1, 1, 1), GetArgs, "string",
+        "Returns the string value of a key."),
+    spec!("set", -3, &[Write], (1, 1, 1), SetArgs, "string",
+        "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist."),
+    spec!("append", 3, &[Write, Fast], (1, 1, 1), AppendArgs, "string",
+        "Appends a string to the value of a key. Creates the key if it doesn't exist."),
+    spec!("strlen", 2, &[ReadOnly, Fast], (1, 1, 1), StrlenArgs, "string",
+        "Returns the length of a string value."),
+    spec!("getrange", 4, &[ReadOnly], (1, 1, 1), GetRangeArgs, "string",
+        "Returns a substring of the string stored at a key."),
+    spec!("setrange", 4, &[Write], (1, 1, 1), SetRangeArgs, "string",
+        "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist."),
+    spec!("getset", 3, &[Write, Fast], (1, 1, 1), GetSetArgs, "string",
+        "Returns the previous string value of a key after setting it to a new value."),
+    spec!("getdel", 2, &[Write, Fast], (1, 1, 1), GetDelArgs, "string",
+        "Returns the string value of a key after deleting the key."),
+    spec!("getex", -2, &[Write, Fast], (1, 1, 1), GetExArgs, "string",
+        "Returns the string value of a key after setting its expiration time."),
+    spec!("mset", -3, &[Write], (1, -1, 2), MSetArgs, "string",
+        "Atomically creates or modifies the string values of one or more keys."),
+    spec!("msetnx", -3, &[Write], (1, -1, 2), MSetArgs, "string",
+        "Atomically modifies the string values of one or more keys only when all keys don't exist."),
+    spec!("mget", -2, &[ReadOnly, Fast], (1, -1, 1), MGetArgs, "string",
+        "Atomically returns the string values of one or more keys."),
+    spec!("incr", 2, &[Write, Fast], (1, 1, 1), IncrArgs, "string",
+        "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    spec!("decr", 2, &[Write, Fast], (1, 1, 1), IncrArgs, "string",
+        "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    spec!("incrby", 3, &[Write, Fast], (1, 1, 1), IncrArgs, "string",
+        "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    spec!("decrby", 3, &[Write, Fast], (1, 1, 1), IncrArgs, "string",
+        "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist."),
+    spec!("incrbyfloat", 3, &[Write, Fast], (1, 1, 1), IncrByFloatArgs, "string",
+        "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    spec!("hget", 3, &[ReadOnly, Fast], (1, 1, 1), HGetArgs, "hash",
+        "Returns the value of a field in a hash."),
+    spec!("hset", -4, &[Write, Fast], (1, 1, 1), HSetArgs, "hash",
+        "Creates or modifies the value of a field in a hash."),
+    spec!("hsetnx", 4, &[Write, Fast], (1, 1, 1), HSetNxArgs, "hash",
+        "Sets the value of a field in a hash only when the field doesn't exist."),
+    spec!("hdel", -3, &[Write, Fast], (1, 1, 1), HDelArgs, "hash",
+        "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain."),
+    spec!("hexists", 3, &[ReadOnly, Fast], (1, 1, 1), HExistsArgs, "hash",
+        "Determines whether a field exists in a hash."),
+    spec!("hlen", 2, &[ReadOnly, Fast], (1, 1, 1), HLenArgs, "hash",
+        "Returns the number of fields in a hash."),
+    spec!("hkeys", 2, &[ReadOnly], (1, 1, 1), HKeysArgs, "hash",
+        "Returns all fields in a hash."),
+    spec!("hvals", 2, &[ReadOnly], (1, 1, 1), HKeysArgs, "hash",
+        "Returns all values in a hash."),
+    spec!("hstrlen", 3, &[ReadOnly, Fast], (1, 1, 1), HStrlenArgs, "hash",
+        "Returns the length of the value of a field."),
+    spec!("hincrby", 4, &[Write, Fast], (1, 1, 1), HIncrByArgs, "hash",
+        "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist."),
+    spec!("hincrbyfloat", 4, &[Write, Fast], (1, 1, 1), HIncrByFloatArgs, "hash",
+        "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist."),
+    spec!("hrandfield", -2, &[ReadOnly], (1, 1, 1), HRandFieldArgs, "hash",
+        "Returns one or more random fields from a hash."),
+    spec!("hexpire", -6, &[Write, Fast], (1, 1, 1), HExpireArgs, "hash",
+        "Set expiry for hash field using relative time to expire (seconds)."),
+    spec!("hpexpire", -6, &[Write, Fast], (1, 1, 1), HExpireArgs, "hash",
+        "Set expiry for hash field using relative time to expire (milliseconds)."),
+    spec!("hexpireat", -6, &[Write, Fast], (1, 1, 1), HExpireArgs, "hash",
+        "Set expiry for hash field using an absolute Unix timestamp (seconds)."),
+    spec!("hpexpireat", -6, &[Write, Fast], (1, 1, 1), HExpireArgs, "hash",
+        "Set expiry for hash field using an absolute Unix timestamp (milliseconds)."),
+    spec!("httl", -5, &[ReadOnly, Fast], (1, 1, 1), HTtlArgs, "hash",
+        "Returns the TTL in seconds of a hash field."),
+    spec!("hpttl", -5, &[ReadOnly, Fast], (1, 1, 1), HTtlArgs, "hash",
+        "Returns the TTL in milliseconds of a hash field."),
+    spec!("hpersist", -5, &[Write, Fast], (1, 1, 1), HPersistArgs, "hash",
+        "Removes the expiration time for each specified field."),
+    spec!("hgetall", 2, &[ReadOnly], (1, 1, 1), HGetAllArgs, "hash",
+        "Returns all fields and values in a hash."),
+    spec!("hmget", -3, &[ReadOnly, Fast], (1, 1, 1), HMGetArgs, "hash",
+        "Returns the values of all fields in a hash."),
+    spec!("ping", -1, &[Fast], (0, 0, 0), PingArgs, "connection",
+        "Returns the server's liveliness response."),
+    spec!("echo", 2, &[Fast], (0, 0, 0), EchoArgs, "connection",
+        "Returns the given string."),
+    spec!("sadd", -3, &[Write, Fast], (1, 1, 1), SAddArgs, "set",
+        "Adds one or more members to a set. Creates the key if it doesn't exist."),
+    spec!("sismember", 3, &[ReadOnly, Fast], (1, 1, 1), SismemberArgs, "set",
+        "Determines whether a member belongs to a set."),
+    spec!("smismember", -3, &[ReadOnly, Fast], (1, 1, 1), SMIsMemberArgs, "set",
+        "Determines whether multiple members belong to a set."),
+    spec!("srem", -3, &[Write, Fast], (1, 1, 1), SRemArgs, "set",
+        "Removes one or more members from a set. Deletes the set if the last member was removed."),
+    spec!("scard", 2, &[ReadOnly, Fast], (1, 1, 1), SCardArgs, "set",
+        "Returns the number of members in a set."),
+    spec!("smembers", 2, &[ReadOnly], (1, 1, 1), SMembersArgs, "set",
+        "Returns all members of a set."),
+    spec!("spop", -2, &[Write, Fast], (1, 1, 1), SPopArgs, "set",
+        "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped."),
+    spec!("srandmember", -2, &[ReadOnly], (1, 1, 1), SRandMemberArgs, "set",
+        "Get one or multiple random members from a set."),
+    spec!("smove", 4, &[Write, Fast], (1, 2, 1), SMoveArgs, "set",
+        "Moves a member from one set to another."),
+    spec!("sunion", -2, &[ReadOnly], (1, -1, 1), SetOpArgs, "set",
+        "Returns the union of multiple sets."),
+    spec!("sunionstore", -3, &[Write], (1, -1, 1), SetOpArgs, "set",
+        "Stores the union of multiple sets in a key."),
+    spec!("sinter", -2, &[ReadOnly], (1, -1, 1), SetOpArgs, "set",
+        "Returns the intersect of multiple sets."),
+    spec!("sinterstore", -3, &[Write], (1, -1, 1), SetOpArgs, "set",
+        "Stores the intersect of multiple sets in a key."),
+    spec!("sdiff", -2, &[ReadOnly], (1, -1, 1), SetOpArgs, "set",
+        "Returns the difference of multiple sets."),
+    spec!("sdiffstore", -3, &[Write], (1, -1, 1), SetOpArgs, "set",
+        "Stores the difference of multiple sets in a key."),
+    // the keys follow numkeys, so their positions depend on the request
+    spec!("sintercard", -3, &[ReadOnly], (0, 0, 0), SInterCardArgs, "set",
+        "Returns the number of members of the intersect of multiple sets."),
+    spec!("expire", -3, &[Write, Fast], (1, 1, 1), ExpireArgs, "generic",
+        "Sets the expiration time of a key in seconds."),
+    spec!("pexpire", -3, &[Write, Fast], (1, 1, 1), ExpireArgs, "generic",
+        "Sets the expiration time of a key in milliseconds."),
+    spec!("expireat", -3, &[Write, Fast], (1, 1, 1), ExpireArgs, "generic",
+        "Sets the expiration time of a key to a Unix timestamp."),
+    spec!("pexpireat", -3, &[Write, Fast], (1, 1, 1), ExpireArgs, "generic",
+        "Sets the expiration time of a key to a Unix milliseconds timestamp."),
+    spec!("ttl", 2, &[ReadOnly, Fast], (1, 1, 1), TtlArgs, "generic",
+        "Returns the expiration time in seconds of a key."),
+    spec!("pttl", 2, &[ReadOnly, Fast], (1, 1, 1), TtlArgs, "generic",
+        "Returns the expiration time in milliseconds of a key."),
+    spec!("persist", 2, &[Write, Fast], (1, 1, 1), PersistArgs, "generic",
+        "Removes the expiration time of a key."),
+    spec!("del", -2, &[Write], (1, -1, 1), DelArgs, "generic",
+        "Deletes one or more keys."),
+    spec!("unlink", -2, &[Write, Fast], (1, -1, 1), DelArgs, "generic",
+        "Asynchronously deletes one or more keys."),
+    spec!("exists", -2, &[ReadOnly, Fast], (1, -1, 1), ExistsArgs, "generic",
+        "Determines whether one or more keys exist."),
+    // there is no lru clock to update, so TOUCH only counts the keys like EXISTS
+    spec!("touch", -2, &[ReadOnly, Fast], (1, -1, 1), ExistsArgs, "generic",
+        "Returns the number of existing keys out of those specified after updating the time they were last accessed."),
+    spec!("type", 2, &[ReadOnly, Fast], (1, 1, 1), TypeArgs, "generic",
+        "Determines the type of value stored at a key."),
+    spec!("rename", 3, &[Write], (1, 2, 1), RenameArgs, "generic",
+        "Renames a key and overwrites the destination."),
+    spec!("renamenx", 3, &[Write, Fast], (1, 2, 1), RenameArgs, "generic",
+        "Renames a key only when the target key name doesn't exist."),
+    spec!("copy", -3, &[Write], (1, 2, 1), CopyArgs, "generic",
+        "Copies the value of a key to a new key."),
+    spec!("keys", 2, &[ReadOnly], (0, 0, 0), KeysArgs, "generic",
+        "Returns all key names that match a pattern."),
+    spec!("scan", -2, &[ReadOnly], (0, 0, 0), ScanArgs, "generic",
+        "Iterates over the key names in the database."),
+    spec!("hscan", -3, &[ReadOnly], (1, 1, 1), ScanArgs, "hash",
+        "Iterates over fields and values of a hash."),
+    spec!("sscan", -3, &[ReadOnly], (1, 1, 1), ScanArgs, "set",
+        "Iterates over members of a set."),
+    spec!("move", 3, &[Write, Fast], (1, 1, 1), MoveArgs, "generic",
+        "Moves a key to another database."),
+    spec!("select", 2, &[Fast], (0, 0, 0), SelectArgs, "connection",
+        "Changes the selected database."),
+    spec!("swapdb", 3, &[Write, Fast], (0, 0, 0), SwapDbArgs, "server",
+        "Swaps two Redis databases."),
+    spec!("flushdb", -1, &[Write], (0, 0, 0), FlushArgs, "server",
+        "Removes all keys from the current database."),
+    spec!("flushall", -1, &[Write], (0, 0, 0), FlushArgs, "server",
+        "Removes all keys from all databases."),
+    spec!("dbsize", 1, &[ReadOnly, Fast], (0, 0, 0), DbSizeArgs, "server",
+        "Returns the number of keys in the database."),
+    spec!("save", 1, &[Admin], (0, 0, 0), SaveArgs, "server",
+        "Synchronously saves the database(s) to disk."),
+    spec!("bgsave", 1, &[Admin], (0, 0, 0), BgSaveArgs, "server",
+        "Asynchronously saves the database(s) to disk."),
+    spec!("lastsave", 1, &[Admin, Fast], (0, 0, 0), LastSaveArgs, "server",
+        "Returns the Unix timestamp of the last successful save to disk."),
+    spec!("bgrewriteaof", 1, &[Admin], (0, 0, 0), BgRewriteAofArgs, "server",
+        "Asynchronously rewrites the append-only file to disk."),
+    spec!("hello", -1, &[Fast, NoAuth], (0, 0, 0), HelloArgs, "connection",
+        "Handshakes with the Redis server."),
+    spec!("command", -1, &[], (0, 0, 0), CommandArgs, "server",
+        "Returns detailed information about all commands."),
+];
+
+lazy_static! {
+    static ref COMMAND_TABLE: HashMap<&'static str, &'static CommandSpec> =
+        COMMANDS.iter().map(|spec| (spec.name, spec)).collect();
+}
+
+// every registered command, in registration order
+pub fn commands() -> &'static [CommandSpec] {
+    COMMANDS
+}
+
+pub fn lookup_command(name: &[u8]) -> Option<&'static CommandSpec> {
+    let name = std::str::from_utf8(name).ok()?.to_ascii_lowercase();
+    COMMAND_TABLE.get(name.as_str()).copied()
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Write => "write",
+            ReadOnly => "readonly",
+            Fast => "fast",
+            Admin => "admin",
+            NoAuth => "no_auth",
+        }
+    }
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn check_arity(&self, argc: usize) -> bool {
+        if self.arity >= 0 {
+            argc as i64 == self.arity
+        } else {
+            argc as i64 >= -self.arity
+        }
+    }
+
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        self.flags.iter().map(|flag| flag.name()).collect()
+    }
+
+    // acl categories derived from the flags, e.g. @write @fast
+    pub fn acl_categories(&self) -> Vec<&'static str> {
+        let mut categories = Vec::new();
+        for flag in self.flags {
+            match flag {
+                Write => categories.push("@write"),
+                ReadOnly => categories.push("@read"),
+                Fast => categories.push("@fast"),
+                Admin => categories.push("@admin"),
+                NoAuth => {}
+            }
+        }
+        if !self.has_flag(Fast) {
+            categories.push("@slow");
+        }
+        if self.has_flag(Admin) {
+            categories.push("@dangerous");
+        }
+        categories.push(match self.group {
+            "string" => "@string",
+            "hash" => "@hash",
+            "set" => "@set",
+            "generic" => "@keyspace",
+            _ => "@connection",
+        });
+        categories
+    }
+
+    // positions of the keys in a full request (name included), following first/last/step
+    pub fn key_positions(&self, argc: usize) -> Vec<usize> {
+        if self.first_key <= 0 || self.step <= 0 {
+            return Vec::new();
+        }
+        let last = if self.last_key < 0 {
+            argc as i64 + self.last_key
+        } else {
+            self.last_key.min(argc as i64 - 1)
+        };
+        (self.first_key..=last)
+            .step_by(self.step as usize)
+            .map(|i| i as usize)
+            .collect()
+    }
+
+    // validate the argument count, then build the command from the request
+    pub fn parse(&self, value: TArray) -> Result<Command, CommandError> {
+        if !self.check_arity(value.len()) {
+            return Err(CommandError::WrongArity(self.name.to_string()));
+        }
+        (self.parse)(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_command() {
+        let spec = lookup_command(b"GeT").expect("get must be registered");
+        assert_eq!(spec.name, "get");
+        assert!(spec.has_flag(ReadOnly));
+        assert!(!spec.has_flag(Write));
+        assert!(lookup_command(b"nope").is_none());
+        assert!(lookup_command(b"\xff").is_none());
+    }
+
+    #[test]
+    fn test_check_arity() {
+        let get = lookup_command(b"get").unwrap();
+        assert!(get.check_arity(2));
+        assert!(!get.check_arity(3));
+
+        let set = lookup_command(b"set").unwrap();
+        assert!(!set.check_arity(2));
+        assert!(set.check_arity(3));
+        assert!(set.check_arity(6));
+    }
+
+    #[test]
+    fn test_key_positions() {
+        let get = lookup_command(b"get").unwrap();
+        assert_eq!(get.key_positions(2), vec![1]);
+        assert_eq!(get.acl_categories(), vec!["@read", "@fast", "@string"]);
+
+        let echo = lookup_command(b"echo").unwrap();
+        assert!(echo.key_positions(2).is_empty());
+    }
+}
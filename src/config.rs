@@ -0,0 +1,201 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::resp::RespLimits;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind: String,
+    pub port: u16,
+    pub dir: PathBuf,
+    pub dbfilename: String,
+    pub appendonly: bool,
+    pub appendfilename: String,
+    pub appendfsync: FsyncPolicy,
+    // number of numbered dbs, SELECT takes an index below it
+    pub databases: usize,
+    // password of the default user, None means no authentication
+    pub requirepass: Option<String>,
+    // what a single request may contain before the connection is closed
+    pub proto_max_bulk_len: usize,
+    pub proto_max_multibulk_len: usize,
+    pub proto_max_nesting: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    Always,
+    EverySec,
+    No,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(FsyncPolicy::Always),
+            "everysec" => Ok(FsyncPolicy::EverySec),
+            "no" => Ok(FsyncPolicy::No),
+            _ => Err(anyhow!("Invalid appendfsync policy: {}", s)),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let limits = RespLimits::default();
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: 6379,
+            dir: PathBuf::from("."),
+            dbfilename: "dump.rdb".to_string(),
+            appendonly: false,
+            appendfilename: "appendonly.aof".to_string(),
+            appendfsync: FsyncPolicy::EverySec,
+            databases: 16,
+            requirepass: None,
+            proto_max_bulk_len: limits.max_bulk_len,
+            proto_max_multibulk_len: limits.max_multibulk_len,
+            proto_max_nesting: limits.max_nesting,
+        }
+    }
+}
+
+impl Config {
+    // parse redis-server style options, e.g. `--port 6380 --dir /data`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+        while let Some(name) = args.next() {
+            let option = name
+                .strip_prefix("--")
+                .ok_or_else(|| anyhow!("Invalid option: {}", name))?
+                .to_ascii_lowercase();
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for option: {}", name))?;
+            match option.as_str() {
+                "bind" => config.bind = value,
+                "port" => config.port = value.parse()?,
+                "dir" => config.dir = PathBuf::from(value),
+                "dbfilename" => config.dbfilename = value,
+                "appendonly" => config.appendonly = parse_yes_no(&value)?,
+                "appendfilename" => config.appendfilename = value,
+                "appendfsync" => config.appendfsync = value.parse()?,
+                "databases" => match value.parse()? {
+                    0 => return Err(anyhow!("Invalid number of databases: {}", value)),
+                    databases => config.databases = databases,
+                },
+                "requirepass" => config.requirepass = Some(value).filter(|v| !v.is_empty()),
+                "proto-max-bulk-len" => config.proto_max_bulk_len = parse_memory(&value)?,
+                "proto-max-multibulk-len" => config.proto_max_multibulk_len = value.parse()?,
+                "proto-max-nesting" => config.proto_max_nesting = value.parse()?,
+                _ => return Err(anyhow!("Unknown option: {}", name)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.bind, self.port)
+    }
+
+    pub fn rdb_path(&self) -> PathBuf {
+        self.dir.join(&self.dbfilename)
+    }
+
+    pub fn aof_path(&self) -> PathBuf {
+        self.dir.join(&self.appendfilename)
+    }
+
+    pub fn resp_limits(&self) -> RespLimits {
+        RespLimits {
+            max_bulk_len: self.proto_max_bulk_len,
+            max_multibulk_len: self.proto_max_multibulk_len,
+            max_nesting: self.proto_max_nesting,
+            ..RespLimits::default()
+        }
+    }
+}
+
+fn parse_yes_no(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(anyhow!("Argument must be 'yes' or 'no': {}", value)),
+    }
+}
+
+// a byte count with an optional redis memory unit, e.g. `512mb` or `1g`
+fn parse_memory(value: &str) -> Result<usize> {
+    let lower = value.to_ascii_lowercase();
+    let digits = lower.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit: usize = match &lower[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow!("Invalid memory value: {}", value)),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| anyhow!("Invalid memory value: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_args() -> Result<()> {
+        let args = ["--port", "6380", "--dir", "/tmp", "--dbfilename", "a.rdb"];
+        let config = Config::from_args(args.iter().map(|s| s.to_string()))?;
+        assert_eq!(config.addr(), "0.0.0.0:6380");
+        assert_eq!(config.rdb_path(), PathBuf::from("/tmp/a.rdb"));
+        assert!(!config.appendonly);
+
+        let args = ["--appendonly", "yes", "--appendfsync", "always"];
+        let config = Config::from_args(args.iter().map(|s| s.to_string()))?;
+        assert!(config.appendonly);
+        assert_eq!(config.appendfsync, FsyncPolicy::Always);
+        assert_eq!(config.aof_path(), PathBuf::from("./appendonly.aof"));
+        assert_eq!(config.requirepass, None);
+        assert_eq!(config.databases, 16);
+
+        let args = ["--requirepass", "secret", "--databases", "4"];
+        let config = Config::from_args(args.iter().map(|s| s.to_string()))?;
+        assert_eq!(config.requirepass.as_deref(), Some("secret"));
+        assert_eq!(config.databases, 4);
+
+        let args = ["--databases", "0"];
+        assert!(Config::from_args(args.iter().map(|s| s.to_string())).is_err());
+
+        let args = ["--proto-max-bulk-len", "1mb", "--proto-max-nesting", "1"];
+        let config = Config::from_args(args.iter().map(|s| s.to_string()))?;
+        let limits = config.resp_limits();
+        assert_eq!(limits.max_bulk_len, 1024 * 1024);
+        assert_eq!(
+            limits.max_multibulk_len,
+            RespLimits::default().max_multibulk_len
+        );
+        assert_eq!(limits.max_nesting, 1);
+
+        let args = ["--proto-max-bulk-len", "1xb"];
+        assert!(Config::from_args(args.iter().map(|s| s.to_string())).is_err());
+
+        let args = ["--port"];
+        assert!(Config::from_args(args.iter().map(|s| s.to_string())).is_err());
+
+        let args = ["--unknown", "1"];
+        assert!(Config::from_args(args.iter().map(|s| s.to_string())).is_err());
+        Ok(())
+    }
+}
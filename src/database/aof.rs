@@ -0,0 +1,534 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use bytes::BytesMut;
+use tracing::{info, warn};
+
+use crate::cmd::{Command, CommandExecute};
+use crate::config::FsyncPolicy;
+use crate::database::rdb::{RdbEntry, RdbValue};
+use crate::database::Database;
+use crate::network::Session;
+use crate::resp::{RespDecoder, RespEncode, RespFrame, TArray, TBulkString};
+
+const AOF_FSYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) struct Aof {
+    path: PathBuf,
+    policy: FsyncPolicy,
+    file: Arc<Mutex<File>>,
+    // set when there are writes not yet fsynced, only used by the everysec policy
+    dirty: Arc<AtomicBool>,
+    // Some while a rewrite runs, collects the writes made after its snapshot was taken
+    rewrite_buffer: Mutex<Option<Vec<u8>>>,
+    // db of the last command written, a SELECT is written first when the next one differs
+    selected: Mutex<Option<usize>>,
+    // held while a write is applied and appended, so writes are logged in the order they happened
+    order: Mutex<()>,
+}
+
+impl Aof {
+    fn open(path: PathBuf, policy: FsyncPolicy) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let aof = Aof {
+            path,
+            policy,
+            file: Arc::new(Mutex::new(file)),
+            dirty: Arc::new(AtomicBool::new(false)),
+            rewrite_buffer: Mutex::new(None),
+            selected: Mutex::new(None),
+            order: Mutex::new(()),
+        };
+        if policy == FsyncPolicy::EverySec {
+            spawn_fsync_thread(Arc::downgrade(&aof.file), aof.dirty.clone());
+        }
+        Ok(aof)
+    }
+
+    fn file(&self) -> Result<MutexGuard<'_, File>> {
+        self.file
+            .lock()
+            .map_err(|_| anyhow!("AOF file lock poisoned"))
+    }
+
+    fn rewrite_buffer(&self) -> Result<MutexGuard<'_, Option<Vec<u8>>>> {
+        self.rewrite_buffer
+            .lock()
+            .map_err(|_| anyhow!("AOF rewrite buffer lock poisoned"))
+    }
+
+    fn selected(&self) -> Result<MutexGuard<'_, Option<usize>>> {
+        self.selected
+            .lock()
+            .map_err(|_| anyhow!("AOF selected db lock poisoned"))
+    }
+
+    fn append(&self, db: usize, frame: TArray) -> Result<()> {
+        // held until the command is written, so the SELECT and the command stay together
+        let mut selected = self.selected()?;
+        let mut buf = BytesMut::new();
+        if *selected != Some(db) {
+            select_frame(db).encode(&mut buf);
+        }
+        frame.encode(&mut buf);
+        self.write(&buf)?;
+        *selected = Some(db);
+        Ok(())
+    }
+
+    fn write(&self, data: &[u8]) -> Result<()> {
+        let mut file = self.file()?;
+        file.write_all(data)?;
+        match self.policy {
+            FsyncPolicy::Always => file.sync_data()?,
+            FsyncPolicy::EverySec => self.dirty.store(true, Ordering::SeqCst),
+            FsyncPolicy::No => {}
+        }
+        if let Some(buffer) = self.rewrite_buffer()?.as_mut() {
+            buffer.extend_from_slice(data);
+        }
+        Ok(())
+    }
+
+    fn rewrite(&self, entries: Vec<RdbEntry>) -> Result<()> {
+        let name = self
+            .path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid aof path"))?;
+        let tmp = self.path.with_file_name(format!(
+            "temp-rewriteaof-{}-{}",
+            std::process::id(),
+            name.to_string_lossy()
+        ));
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        let mut buf = BytesMut::new();
+        let mut db = None;
+        for entry in entries {
+            if db != Some(entry.db) {
+                db = Some(entry.db);
+                select_frame(entry.db).encode(&mut buf);
+            }
+            for frame in entry_to_frames(entry) {
+                frame.encode(&mut buf);
+                writer.write_all(&buf)?;
+                buf.clear();
+            }
+        }
+        let mut new_file = writer.into_inner().map_err(|e| e.into_error())?;
+
+        // block appends while the writes made during the rewrite are spliced in and the files swapped
+        let mut file = self.file()?;
+        let mut buffer = self.rewrite_buffer()?;
+        new_file.write_all(&buffer.take().unwrap_or_default())?;
+        new_file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        *file = new_file;
+        Ok(())
+    }
+}
+
+fn spawn_fsync_thread(file: Weak<Mutex<File>>, dirty: Arc<AtomicBool>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(AOF_FSYNC_INTERVAL);
+        // the aof was dropped together with its database
+        let Some(file) = file.upgrade() else {
+            break;
+        };
+        if !dirty.swap(false, Ordering::SeqCst) {
+            continue;
+        }
+        // sync through a cloned handle so appends are not blocked by the fsync
+        let handle = file.lock().ok().and_then(|f| f.try_clone().ok());
+        if let Some(Err(e)) = handle.map(|f| f.sync_data()) {
+            warn!("AOF fsync error: {:?}", e);
+        }
+    });
+}
+
+fn select_frame(db: usize) -> TArray {
+    TArray::new([b"select".into(), TBulkString::from(db.to_string()).into()])
+}
+
+// the minimal commands that recreate one key
+fn entry_to_frames(entry: RdbEntry) -> Vec<TArray> {
+    let key: RespFrame = TBulkString::new(entry.key).into();
+    let mut frames = match entry.value {
+        RdbValue::String(value) => vec![TArray::new([
+            b"set".into(),
+            key.clone(),
+            TBulkString::new(value).into(),
+        ])],
+        RdbValue::Hash(fields) => fields
+            .into_iter()
+            .map(|(field, value)| {
+                TArray::new([
+                    b"hset".into(),
+                    key.clone(),
+                    TBulkString::new(field).into(),
+                    TBulkString::new(value).into(),
+                ])
+            })
+            .collect(),
+        RdbValue::HashMetadata(fields) => {
+            let mut frames = Vec::with_capacity(fields.len());
+            let mut expires = Vec::new();
+            for (field, value, deadline) in fields {
+                let field: RespFrame = TBulkString::new(field).into();
+                frames.push(TArray::new([
+                    b"hset".into(),
+                    key.clone(),
+                    field.clone(),
+                    TBulkString::new(value).into(),
+                ]));
+                if let Some(deadline) = deadline {
+                    expires.push(TArray::new([
+                        b"hpexpireat".into(),
+                        key.clone(),
+                        TBulkString::from(deadline.to_string()).into(),
+                        b"fields".into(),
+                        b"1".into(),
+                        field,
+                    ]));
+                }
+            }
+            frames.extend(expires);
+            frames
+        }
+        RdbValue::Set(members) => members
+            .into_iter()
+            .map(|member| {
+                TArray::new([b"sadd".into(), key.clone(), TBulkString::new(member).into()])
+            })
+            .collect(),
+    };
+    if let Some(deadline) = entry.expire {
+        frames.push(TArray::new([
+            b"pexpireat".into(),
+            key,
+            TBulkString::from(deadline.to_string()).into(),
+        ]));
+    }
+    frames
+}
+
+impl Database {
+    // replay the configured aof, then keep it open for appending
+    pub fn load_aof(&self) -> Result<usize> {
+        let path = self.config.aof_path();
+        let replayed = match fs::read(&path) {
+            Ok(data) => self.replay_aof(&path, data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let aof = Aof::open(path, self.config.appendfsync)?;
+        self.aof
+            .set(aof)
+            .map_err(|_| anyhow!("AOF is already open"))?;
+        info!("DB loaded from append only file: {} commands", replayed);
+        Ok(replayed)
+    }
+
+    pub fn aof_enabled(&self) -> bool {
+        self.aof.get().is_some()
+    }
+
+    pub fn append_aof(&self, frame: TArray) -> Result<()> {
+        match self.aof.get() {
+            Some(aof) => aof.append(self.index, frame),
+            None => Ok(()),
+        }
+    }
+
+    // run a write and append the command it returns as one step, so two clients writing the same
+    // key can't be logged in the opposite order they were applied and a replay ends up the same
+    pub(crate) fn apply_logged<T>(&self, apply: impl FnOnce() -> (T, Option<TArray>)) -> Result<T> {
+        let Some(aof) = self.aof.get() else {
+            return Ok(apply().0);
+        };
+        // a write that panicked did not leave the log half written, keep going
+        let _order = aof.order.lock().unwrap_or_else(|e| e.into_inner());
+        let (ret, logged) = apply();
+        if let Some(frame) = logged {
+            aof.append(self.index, frame)?;
+        }
+        Ok(ret)
+    }
+
+    // compact the aof from the live dataset on a background thread, returns false if one is running
+    pub fn bgrewriteaof(&self) -> Result<bool> {
+        let aof = self
+            .aof
+            .get()
+            .ok_or_else(|| anyhow!("Append only file is disabled"))?;
+        let keys = {
+            // taken with writes held off, so each write is either in the copy or in the buffer
+            // and a replay never applies an INCR twice
+            let _order = aof.order.lock().unwrap_or_else(|e| e.into_inner());
+            let mut selected = aof.selected()?;
+            let mut buffer = aof.rewrite_buffer()?;
+            if buffer.is_some() {
+                return Ok(false);
+            }
+            // the buffered writes begin with their own SELECT since the rewritten file ends on
+            // any db
+            *buffer = Some(Vec::new());
+            *selected = None;
+            self.copy_keys()
+        };
+        // the copy is turned into commands on the background thread, a large dataset would
+        // otherwise hold up the caller's worker
+        let db = self.clone();
+        std::thread::spawn(move || {
+            let Some(aof) = db.aof.get() else {
+                return;
+            };
+            match aof.rewrite(keys.into_entries()) {
+                Ok(()) => info!("Background AOF rewrite terminated with success"),
+                Err(e) => {
+                    warn!("Background AOF rewrite error: {:?}", e);
+                    if let Ok(mut buffer) = aof.rewrite_buffer() {
+                        *buffer = None;
+                    }
+                }
+            }
+        });
+        Ok(true)
+    }
+
+    pub fn aof_rewrite_in_progress(&self) -> bool {
+        self.aof
+            .get()
+            .and_then(|aof| aof.rewrite_buffer().ok().map(|b| b.is_some()))
+            .unwrap_or(false)
+    }
+
+    fn replay_aof(&self, path: &Path, data: Vec<u8>) -> Result<usize> {
+        let total = data.len();
+        let mut buf = BytesMut::from(&data[..]);
+        // the same decoder and limits as client connections, a damaged file can't make it allocate
+        // more than a client could
+        let mut decoder = RespDecoder::with_limits(self.config.resp_limits());
+        let mut replayed = 0;
+        let mut session = Session::new(self);
+        let mut db = self.clone();
+        while !buf.is_empty() {
+            // every command starts with an idle decoder, this is where the current one begins
+            let start = total - buf.len();
+            match decoder.decode(&mut buf)? {
+                // SELECT only switches the db the following commands apply to
+                Some(frame) => match Command::try_from(frame)? {
+                    Command::Select(args) => {
+                        if let RespFrame::Error(e) = args.select(self, &mut session) {
+                            bail!("AOF selects a missing db: {}", e.as_str());
+                        }
+                        db = self.select(session.db).unwrap_or_else(|| self.clone());
+                    }
+                    cmd => {
+                        cmd.execute(&db);
+                        replayed += 1;
+                    }
+                },
+                None => {
+                    // a crash in the middle of a write, drop the partial command like aof-load-truncated
+                    warn!("AOF truncated, dropping the last {} bytes", total - start);
+                    OpenOptions::new()
+                        .write(true)
+                        .open(path)?
+                        .set_len(start as u64)?;
+                    break;
+                }
+            }
+        }
+        Ok(replayed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::test_util::temp_config;
+
+    use super::*;
+
+    fn aof_config(name: &str) -> Config {
+        Config {
+            appendonly: true,
+            appendfsync: FsyncPolicy::Always,
+            ..temp_config(name)
+        }
+    }
+
+    #[test]
+    fn test_aof_append_replay() -> Result<()> {
+        let config = aof_config("append-replay");
+        let _ = fs::remove_file(config.aof_path());
+
+        let db = Database::with_config(config.clone());
+        assert_eq!(db.load_aof()?, 0);
+        assert!(db.aof_enabled());
+        db.append_aof(TArray::new([
+            b"set".into(),
+            b"hello".into(),
+            b"world".into(),
+        ]))?;
+        db.append_aof(TArray::new([
+            b"hset".into(),
+            b"map".into(),
+            b"f".into(),
+            b"v".into(),
+        ]))?;
+        let other = db.select(3).expect("db 3 exists");
+        other.append_aof(TArray::new([b"set".into(), b"hello".into(), b"3".into()]))?;
+        drop((db, other));
+
+        // simulate a crash in the middle of a write
+        let mut file = OpenOptions::new().append(true).open(config.aof_path())?;
+        file.write_all(b"*3\r\n$3\r\nset\r\n$1\r\na")?;
+
+        let db = Database::with_config(config.clone());
+        assert_eq!(db.load_aof()?, 3);
+        assert_eq!(db.get(b"hello")?, Some(b"world".into()));
+        let other = db.select(3).expect("db 3 exists");
+        assert_eq!(other.get(b"hello")?, Some(b"3".into()));
+        assert_eq!(db.hget(b"map", b"f")?, Some(b"v".into()));
+        assert!(!fs::read(config.aof_path())?.ends_with(b"$1\r\na"));
+
+        fs::remove_file(config.aof_path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_logs_writes_in_apply_order() -> Result<()> {
+        let config = aof_config("apply-order");
+        let _ = fs::remove_file(config.aof_path());
+
+        let db = Database::with_config(config.clone());
+        db.load_aof()?;
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let db = db.clone();
+                scope.spawn(move || {
+                    for j in 0..50 {
+                        let value = format!("{}-{}", i, j);
+                        let frame =
+                            TArray::new([b"set".into(), b"k".into(), value.as_bytes().into()]);
+                        let _ = db.apply_logged(|| {
+                            db.set(b"k".to_vec(), value.as_bytes().into());
+                            ((), Some(frame))
+                        });
+                    }
+                });
+            }
+        });
+        let last = db.get(b"k")?;
+        drop(db);
+
+        // the last write applied is the last one replayed
+        let db = Database::with_config(config.clone());
+        assert_eq!(db.load_aof()?, 400);
+        assert_eq!(db.get(b"k")?, last);
+
+        fs::remove_file(config.aof_path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_rewrite() -> Result<()> {
+        let config = aof_config("rewrite");
+        let _ = fs::remove_file(config.aof_path());
+
+        let db = Database::with_config(config.clone());
+        db.load_aof()?;
+        for i in 0..10 {
+            let frame = TArray::new([
+                b"set".into(),
+                b"hello".into(),
+                i.to_string().as_bytes().into(),
+            ]);
+            db.append_aof(frame)?;
+            db.set(b"hello".to_vec(), i.to_string().as_bytes().into());
+        }
+        db.sadd(b"set".to_vec(), b"m".to_vec())?;
+        db.set_expire(b"set", crate::database::now_ms() + 60_000);
+        let deadline = crate::database::now_ms() + 60_000;
+        let fields = [b"a".to_vec(), b"b".to_vec()];
+        db.hset(b"h".to_vec(), b"a".to_vec(), b"1".into())?;
+        db.hset(b"h".to_vec(), b"b".to_vec(), b"2".into())?;
+        db.hexpire(b"h", &fields[..1], deadline, |_| true)?;
+        let other = db.select(2).expect("db 2 exists");
+        other.set(b"hello".to_vec(), b"2".into());
+        let before = fs::metadata(config.aof_path())?.len();
+
+        assert!(db.bgrewriteaof()?);
+        while db.aof_rewrite_in_progress() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(fs::metadata(config.aof_path())?.len() < before);
+        db.append_aof(TArray::new([b"set".into(), b"after".into(), b"1".into()]))?;
+        drop((db, other));
+
+        let db = Database::with_config(config.clone());
+        assert_eq!(db.load_aof()?, 8);
+        assert_eq!(db.get(b"hello")?, Some(b"9".into()));
+        let other = db.select(2).expect("db 2 exists");
+        assert_eq!(other.get(b"hello")?, Some(b"2".into()));
+        assert_eq!(db.get(b"after")?, Some(b"1".into()));
+        assert!(db.expire_time(b"set").is_some());
+        assert_eq!(db.hexpire_time(b"h", &fields)?, vec![deadline, -1]);
+
+        fs::remove_file(config.aof_path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_rewrite_during_writes() -> Result<()> {
+        // no fsync, so the writer keeps up with the rewrites
+        let config = Config {
+            appendfsync: FsyncPolicy::No,
+            ..aof_config("rewrite-during-writes")
+        };
+        let _ = fs::remove_file(config.aof_path());
+
+        let db = Database::with_config(config.clone());
+        db.load_aof()?;
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let writer = db.clone();
+            let done = &done;
+            scope.spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let frame = TArray::new([b"incr".into(), b"counter".into()]);
+                    let _ = writer.apply_logged(|| {
+                        let _ = writer.incr_by(b"counter".to_vec(), 1);
+                        ((), Some(frame))
+                    });
+                }
+            });
+            // rewrite again and again while the counter is being increased
+            for _ in 0..20 {
+                std::thread::sleep(Duration::from_millis(2));
+                let _ = db.bgrewriteaof();
+                while db.aof_rewrite_in_progress() {
+                    std::thread::yield_now();
+                }
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+        let counter = db.get(b"counter")?;
+        drop(db);
+
+        // every increment is replayed exactly once
+        let db = Database::with_config(config.clone());
+        db.load_aof()?;
+        assert_eq!(db.get(b"counter")?, counter);
+
+        fs::remove_file(config.aof_path())?;
+        Ok(())
+    }
+}
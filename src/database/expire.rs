@@ -0,0 +1,212 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::info;
+
+use crate::database::Database;
+
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+const ACTIVE_EXPIRE_BATCH: usize = 200;
+
+pub(crate) fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+impl Database {
+    // drop the key if its deadline has passed, returns true if it was expired
+    pub(crate) fn expire_if_needed(&self, key: &[u8]) -> bool {
+        let now = now_ms();
+        let due = |deadline: &i64| *deadline <= now;
+        // most keys are not due, check without taking the keyspace lock
+        if !self
+            .db
+            .expires
+            .get(key)
+            .is_some_and(|deadline| due(&deadline))
+        {
+            return false;
+        }
+        // checked again under the key's guard, a concurrent SET may have given it a new value
+        self.db
+            .keyspace
+            .remove_if(key, |key, _| {
+                self.db
+                    .expires
+                    .remove_if(key, |_, deadline| due(deadline))
+                    .is_some()
+            })
+            .is_some()
+    }
+
+    // set an absolute deadline (unix ms) on an existing key, returns false if the key does not exist
+    pub fn set_expire(&self, key: &[u8], deadline: i64) -> bool {
+        self.expire_if_needed(key);
+        // the guard keeps a concurrent DEL from leaving the ttl behind for the next value
+        let Some(_guard) = self.db.keyspace.get(key) else {
+            return false;
+        };
+        self.db.expires.insert(key.to_vec(), deadline);
+        true
+    }
+
+    pub fn expire_time(&self, key: &[u8]) -> Option<i64> {
+        self.expire_if_needed(key);
+        self.db.expires.get(key).map(|v| *v.value())
+    }
+
+    pub fn persist(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        // under the guard like `set_expire`, so the ttl a concurrent SET gives a new value stays
+        let Some(_guard) = self.db.keyspace.get(key) else {
+            return false;
+        };
+        self.db.expires.remove(key).is_some()
+    }
+
+    // reclaim up to one batch of expired keys of the selected db, returns how many were removed
+    pub fn active_expire_cycle(&self) -> usize {
+        let now = now_ms();
+        // collect first: removing while iterating would deadlock on the shard lock
+        let expired = self
+            .db
+            .expires
+            .iter()
+            .filter(|v| *v.value() <= now)
+            .take(ACTIVE_EXPIRE_BATCH)
+            .map(|v| v.key().clone())
+            .collect::<Vec<Vec<u8>>>();
+        expired
+            .iter()
+            .filter(|key| self.expire_if_needed(key))
+            .count()
+    }
+
+    // drop the expired fields of up to one batch of hashes, returns how many hashes were visited
+    pub fn active_expire_fields_cycle(&self) -> usize {
+        let now = now_ms();
+        // entries of keys that were deleted or overwritten since are dropped by `purge_fields`
+        let due = self
+            .db
+            .field_expires
+            .iter()
+            .filter(|v| *v.value() <= now)
+            .take(ACTIVE_EXPIRE_BATCH)
+            .map(|v| v.key().clone())
+            .collect::<Vec<Vec<u8>>>();
+        for key in &due {
+            self.purge_fields(key);
+        }
+        due.len()
+    }
+
+    pub async fn run_active_expire(self) {
+        let mut interval = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut total = 0;
+            let mut hashes = 0;
+            // resolved on every tick, SWAPDB and FLUSHDB replace the dbs
+            for db in (0..self.databases()).filter_map(|index| self.select(index)) {
+                let mut removed = db.active_expire_cycle();
+                total += removed;
+                // a full batch means there is probably more garbage, keep going without hogging the worker
+                while removed == ACTIVE_EXPIRE_BATCH {
+                    tokio::task::yield_now().await;
+                    removed = db.active_expire_cycle();
+                    total += removed;
+                }
+                let mut visited = db.active_expire_fields_cycle();
+                hashes += visited;
+                while visited == ACTIVE_EXPIRE_BATCH {
+                    tokio::task::yield_now().await;
+                    visited = db.active_expire_fields_cycle();
+                    hashes += visited;
+                }
+            }
+            if total > 0 {
+                info!("Active expire removed {} keys", total);
+            }
+            if hashes > 0 {
+                info!("Active expire trimmed fields of {} hashes", hashes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Value;
+    use crate::resp::RespFrame;
+
+    use super::*;
+
+    #[test]
+    fn test_lazy_expire() {
+        let db = Database::new();
+        db.set_with_expire(b"hello".to_vec(), b"world".into(), now_ms() - 1);
+        assert!(db.db.keyspace.contains_key(b"hello".as_slice()));
+        assert_eq!(db.get(b"hello"), Ok(None));
+        assert!(!db.db.keyspace.contains_key(b"hello".as_slice()));
+        assert!(!db.db.expires.contains_key(b"hello".as_slice()));
+    }
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let db = Database::new();
+        db.set_with_expire(b"a".to_vec(), b"1".into(), now_ms() - 1);
+        db.hset(b"b".to_vec(), b"f".to_vec(), b"2".into()).unwrap();
+        assert!(db.set_expire(b"b", now_ms() - 1));
+        db.set_with_expire(b"c".to_vec(), b"3".into(), now_ms() + 60_000);
+        db.set(b"d".to_vec(), RespFrame::Integer(4));
+
+        assert_eq!(db.active_expire_cycle(), 2);
+        assert!(!db.db.keyspace.contains_key(b"a".as_slice()));
+        assert!(!db.db.keyspace.contains_key(b"b".as_slice()));
+        assert!(db.db.keyspace.contains_key(b"c".as_slice()));
+        assert!(db.db.keyspace.contains_key(b"d".as_slice()));
+    }
+
+    #[test]
+    fn test_active_expire_fields_cycle() -> anyhow::Result<()> {
+        let db = Database::new();
+        db.hset(b"h".to_vec(), b"a".to_vec(), b"1".into())?;
+        db.hset(b"h".to_vec(), b"b".to_vec(), b"2".into())?;
+        db.hset(b"gone".to_vec(), b"a".to_vec(), b"1".into())?;
+        let soon = now_ms() + 20;
+        db.hexpire(b"h", &[b"a".to_vec()], soon, |_| true)?;
+        db.hexpire(b"gone", &[b"a".to_vec()], soon, |_| true)?;
+        // a key overwritten since leaves a stale entry behind
+        db.hset(b"s".to_vec(), b"a".to_vec(), b"1".into())?;
+        db.hexpire(b"s", &[b"a".to_vec()], soon, |_| true)?;
+        db.set(b"s".to_vec(), b"v".into());
+        assert_eq!(db.active_expire_fields_cycle(), 0);
+
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        assert_eq!(db.active_expire_fields_cycle(), 3);
+        let Some(Value::Hash(hash)) = db.db.keyspace.get(b"h".as_slice()).map(|v| v.clone()) else {
+            panic!("expected the hash to survive");
+        };
+        assert_eq!(hash.fields.len(), 1);
+        assert!(!db.db.keyspace.contains_key(b"gone".as_slice()));
+        assert!(db.db.field_expires.is_empty());
+        assert_eq!(db.get(b"s")?, Some(b"v".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_expire_and_persist() {
+        let db = Database::new();
+        assert!(!db.set_expire(b"missing", now_ms() + 1000));
+
+        db.sadd(b"s".to_vec(), b"m".to_vec()).unwrap();
+        let deadline = now_ms() + 1000;
+        assert!(db.set_expire(b"s", deadline));
+        assert_eq!(db.expire_time(b"s"), Some(deadline));
+        assert!(db.persist(b"s"));
+        assert!(!db.persist(b"s"));
+        assert_eq!(db.expire_time(b"s"), None);
+        assert!(!db.persist(b"missing"));
+    }
+}
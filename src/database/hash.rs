@@ -0,0 +1,418 @@
+use crate::database::keyspace::Entry;
+use crate::database::random::sample;
+use crate::database::string::{parse_f64, parse_i64, string_bytes};
+use crate::database::table::Entry as FieldEntry;
+use crate::database::{now_ms, Database, Fields, HashValue, Key, NumericError, Value, WrongType};
+use crate::resp::{RespFrame, TBulkString};
+
+impl HashValue {
+    // drop the fields whose deadline has passed, returns how many were removed
+    pub(crate) fn remove_expired(&mut self, now: i64) -> usize {
+        let expired = self
+            .expires
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(field, _)| field.clone())
+            .collect::<Vec<_>>();
+        for field in &expired {
+            self.expires.remove(field);
+            self.fields.remove(field);
+        }
+        expired.len()
+    }
+
+    // the earliest field deadline, None when no field has a ttl
+    pub(crate) fn next_expire(&self) -> Option<i64> {
+        self.expires.values().min().copied()
+    }
+
+    fn remove(&mut self, field: &[u8]) -> bool {
+        self.expires.remove(field);
+        self.fields.remove(field).is_some()
+    }
+}
+
+impl Database {
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => Ok(hash.fields.get(field).cloned()),
+            Some(_) => Err(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // returns true if the field is new
+    pub fn hset(&self, key: Key, field: Vec<u8>, value: RespFrame) -> Result<bool, WrongType> {
+        self.hset_multi(key, vec![(field, value)])
+            .map(|added| added == 1)
+    }
+
+    // set every pair at once, returns how many fields are new
+    pub fn hset_multi(
+        &self,
+        key: Key,
+        pairs: Vec<(Vec<u8>, RespFrame)>,
+    ) -> Result<usize, WrongType> {
+        let added = self.update_hash(key, true, |hash| {
+            let len = hash.fields.len();
+            for (field, value) in pairs {
+                // overwriting a field clears its ttl
+                hash.expires.remove(&field);
+                hash.fields.insert(field, value);
+            }
+            Ok::<_, WrongType>(hash.fields.len() - len)
+        })?;
+        Ok(added.unwrap_or_default())
+    }
+
+    // returns true if the field was set, false if it already existed
+    pub fn hsetnx(&self, key: Key, field: Vec<u8>, value: RespFrame) -> Result<bool, WrongType> {
+        let set = self.update_hash(key, true, |hash| match hash.fields.entry(field) {
+            FieldEntry::Occupied(_) => Ok::<_, WrongType>(false),
+            FieldEntry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(true)
+            }
+        })?;
+        Ok(set.unwrap_or_default())
+    }
+
+    // returns how many fields were removed, the key goes away with its last field
+    pub fn hdel(&self, key: Key, fields: &[Vec<u8>]) -> Result<usize, WrongType> {
+        let removed = self.update_hash(key, false, |hash| {
+            let removed = fields.iter().filter(|field| hash.remove(field)).count();
+            Ok::<_, WrongType>(removed)
+        })?;
+        Ok(removed.unwrap_or_default())
+    }
+
+    pub fn hlen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => Ok(hash.fields.len()),
+            Some(_) => Err(WrongType),
+            None => Ok(0),
+        }
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Result<Option<Fields>, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => Ok(Some(hash.fields.clone())),
+            Some(_) => Err(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // add to the integer stored in the field, a missing field counts as 0
+    pub fn hincr_by(&self, key: Key, field: Vec<u8>, delta: i64) -> Result<i64, NumericError> {
+        let value = self.update_hash::<_, NumericError>(key, true, |hash| {
+            let current = match hash.fields.get(&field) {
+                Some(value) => {
+                    parse_i64(&string_bytes(value.clone())).ok_or(NumericError::HashNotInteger)?
+                }
+                None => 0,
+            };
+            let value = current.checked_add(delta).ok_or(NumericError::Overflow)?;
+            // unlike HSET, incrementing keeps the ttl of the field
+            hash.fields
+                .insert(field, TBulkString::from(value.to_string()).into());
+            Ok(value)
+        })?;
+        Ok(value.unwrap_or_default())
+    }
+
+    pub fn hincr_by_float(
+        &self,
+        key: Key,
+        field: Vec<u8>,
+        delta: f64,
+    ) -> Result<f64, NumericError> {
+        let value = self.update_hash::<_, NumericError>(key, true, |hash| {
+            let current = match hash.fields.get(&field) {
+                Some(value) => {
+                    parse_f64(&string_bytes(value.clone())).ok_or(NumericError::HashNotFloat)?
+                }
+                None => 0.0,
+            };
+            let value = current + delta;
+            if !value.is_finite() {
+                return Err(NumericError::NotFinite);
+            }
+            hash.fields
+                .insert(field, TBulkString::from(value.to_string()).into());
+            Ok(value)
+        })?;
+        Ok(value.unwrap_or_default())
+    }
+
+    // random fields with their values, see `sample` for the meaning of `count`
+    pub fn hrandfield(
+        &self,
+        key: &[u8],
+        count: i64,
+    ) -> Result<Vec<(Vec<u8>, RespFrame)>, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => Ok(sample(hash.fields.iter(), count)
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()),
+            Some(_) => Err(WrongType),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // set the deadline of each existing field, `allowed` decides from the current deadline whether
+    // it may change (HEXPIRE NX/XX/GT/LT). Per field: -2 missing, 0 not allowed, 1 set, and 2 when
+    // the deadline has already passed and the field was deleted
+    pub fn hexpire(
+        &self,
+        key: &[u8],
+        fields: &[Vec<u8>],
+        deadline: i64,
+        allowed: impl Fn(Option<i64>) -> bool,
+    ) -> Result<Vec<i64>, WrongType> {
+        let now = now_ms();
+        let codes = self.update_hash(key.to_vec(), false, |hash| {
+            let codes = fields
+                .iter()
+                .map(|field| {
+                    if !hash.fields.contains_key(field) {
+                        -2
+                    } else if !allowed(hash.expires.get(field).copied()) {
+                        0
+                    } else if deadline <= now {
+                        hash.remove(field);
+                        2
+                    } else {
+                        hash.expires.insert(field.clone(), deadline);
+                        1
+                    }
+                })
+                .collect();
+            Ok::<_, WrongType>(codes)
+        })?;
+        Ok(codes.unwrap_or_else(|| vec![-2; fields.len()]))
+    }
+
+    // per field: -2 missing, -1 no ttl, otherwise the deadline in unix milliseconds
+    pub fn hexpire_time(&self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<i64>, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => Ok(fields
+                .iter()
+                .map(|field| match hash.expires.get(field) {
+                    Some(deadline) => *deadline,
+                    None if hash.fields.contains_key(field) => -1,
+                    None => -2,
+                })
+                .collect()),
+            Some(_) => Err(WrongType),
+            None => Ok(vec![-2; fields.len()]),
+        }
+    }
+
+    // per field: -2 missing, -1 no ttl, 1 the ttl was removed
+    pub fn hpersist(&self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<i64>, WrongType> {
+        let codes = self.update_hash(key.to_vec(), false, |hash| {
+            let codes = fields
+                .iter()
+                .map(|field| match hash.expires.remove(field) {
+                    Some(_) => 1,
+                    None if hash.fields.contains_key(field) => -1,
+                    None => -2,
+                })
+                .collect();
+            Ok::<_, WrongType>(codes)
+        })?;
+        Ok(codes.unwrap_or_else(|| vec![-2; fields.len()]))
+    }
+
+    // like `expire_if_needed`, and also drop the expired fields when the key holds a hash
+    pub(crate) fn expire_fields_if_needed(&self, key: &[u8]) {
+        self.expire_if_needed(key);
+        let now = now_ms();
+        let due = matches!(
+            self.db.keyspace.get(key).as_deref(),
+            Some(Value::Hash(hash)) if hash.next_expire().is_some_and(|next| next <= now)
+        );
+        if due {
+            self.purge_fields(key);
+        }
+    }
+
+    // drop the expired fields of the hash and fix its entry in `field_expires`, also when the key
+    // has gone or no longer holds a hash
+    pub(crate) fn purge_fields(&self, key: &[u8]) {
+        // a wrong type is no error here, update_hash still forgets the key
+        let _ = self.update_hash(key.to_vec(), false, |_| Ok::<_, WrongType>(()));
+    }
+
+    // keep `field_expires` in step with the hash stored under `key`, only call it while holding the
+    // key's entry so the index never lags behind a concurrent write of the key
+    pub(crate) fn index_field_expires(&self, key: &[u8], next: Option<i64>) {
+        match next {
+            Some(next) => {
+                self.db.field_expires.insert(key.to_vec(), next);
+            }
+            None => {
+                self.db.field_expires.remove(key);
+            }
+        }
+    }
+
+    // run `f` on the hash under the shard lock and drop the key once it is empty, so no command
+    // can observe an empty hash; expired fields are gone before `f` runs and a failed `f` must
+    // leave the hash untouched. None when the key is missing and `create` is not set
+    fn update_hash<T, E: From<WrongType>>(
+        &self,
+        key: Key,
+        create: bool,
+        f: impl FnOnce(&mut HashValue) -> Result<T, E>,
+    ) -> Result<Option<T>, E> {
+        self.expire_if_needed(&key);
+        match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let Value::Hash(hash) = entry.get_mut() else {
+                    self.index_field_expires(entry.key(), None);
+                    return Err(WrongType.into());
+                };
+                hash.remove_expired(now_ms());
+                let ret = f(hash).map(Some);
+                let (next, empty) = (hash.next_expire(), hash.fields.is_empty());
+                self.index_field_expires(entry.key(), next);
+                if empty {
+                    self.db.expires.remove(entry.key());
+                    entry.remove();
+                }
+                ret
+            }
+            Entry::Vacant(entry) if create => {
+                let mut hash = HashValue::default();
+                let ret = f(&mut hash).map(Some);
+                self.index_field_expires(entry.key(), hash.next_expire());
+                if !hash.fields.is_empty() {
+                    entry.insert(Value::Hash(hash));
+                }
+                ret
+            }
+            Entry::Vacant(entry) => {
+                self.index_field_expires(entry.key(), None);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_hset_multi_hdel() -> Result<()> {
+        let db = Database::new();
+        let pairs = vec![(b"a".to_vec(), b"1".into()), (b"b".to_vec(), b"2".into())];
+        assert_eq!(db.hset_multi(b"h".to_vec(), pairs)?, 2);
+        let pairs = vec![(b"b".to_vec(), b"3".into()), (b"c".to_vec(), b"4".into())];
+        assert_eq!(db.hset_multi(b"h".to_vec(), pairs)?, 1);
+        assert_eq!(db.hget(b"h", b"b")?, Some(b"3".into()));
+        assert_eq!(db.hlen(b"h")?, 3);
+
+        assert!(!db.hsetnx(b"h".to_vec(), b"a".to_vec(), b"x".into())?);
+        assert_eq!(db.hget(b"h", b"a")?, Some(b"1".into()));
+
+        db.set_expire(b"h", crate::database::now_ms() + 60_000);
+        let fields = [b"a".to_vec(), b"x".to_vec()];
+        assert_eq!(db.hdel(b"h".to_vec(), &fields)?, 1);
+        let fields = [b"b".to_vec(), b"c".to_vec()];
+        assert_eq!(db.hdel(b"h".to_vec(), &fields)?, 2);
+        // the last field takes the key and its ttl with it
+        assert!(!db.exists(b"h"));
+        assert_eq!(db.expire_time(b"h"), None);
+        assert_eq!(db.hdel(b"h".to_vec(), &fields)?, 0);
+        assert!(!db.exists(b"h"));
+
+        db.set(b"s".to_vec(), b"v".into());
+        assert_eq!(db.hdel(b"s".to_vec(), &fields), Err(WrongType));
+        assert_eq!(db.hlen(b"s"), Err(WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincr_by() -> Result<()> {
+        let db = Database::new();
+        assert_eq!(db.hincr_by(b"h".to_vec(), b"n".to_vec(), 5)?, 5);
+        assert_eq!(db.hincr_by(b"h".to_vec(), b"n".to_vec(), -7)?, -2);
+        assert_eq!(db.hincr_by_float(b"h".to_vec(), b"n".to_vec(), 0.5)?, -1.5);
+        assert_eq!(
+            db.hincr_by(b"h".to_vec(), b"n".to_vec(), 1),
+            Err(NumericError::HashNotInteger)
+        );
+
+        db.hset(b"h".to_vec(), b"m".to_vec(), b"max".into())?;
+        assert_eq!(
+            db.hincr_by_float(b"h".to_vec(), b"m".to_vec(), 1.0),
+            Err(NumericError::HashNotFloat)
+        );
+        assert_eq!(
+            db.hincr_by(b"new".to_vec(), b"n".to_vec(), i64::MIN)?,
+            i64::MIN
+        );
+        assert_eq!(
+            db.hincr_by(b"new".to_vec(), b"n".to_vec(), -1),
+            Err(NumericError::Overflow)
+        );
+        // an overflow leaves the field alone
+        assert_eq!(
+            db.hget(b"new", b"n")?,
+            Some(TBulkString::from(i64::MIN.to_string()).into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_expire() -> Result<()> {
+        let db = Database::new();
+        let pairs = vec![
+            (b"a".to_vec(), b"1".into()),
+            (b"b".to_vec(), b"2".into()),
+            (b"c".to_vec(), b"3".into()),
+        ];
+        db.hset_multi(b"h".to_vec(), pairs)?;
+        let deadline = now_ms() + 60_000;
+        let fields = [b"a".to_vec(), b"b".to_vec()];
+        assert_eq!(db.hexpire(b"h", &fields, deadline, |_| true)?, vec![1, 1]);
+        assert_eq!(
+            db.db.field_expires.get(b"h".as_slice()).map(|v| *v),
+            Some(deadline)
+        );
+
+        // overwriting clears the ttl, incrementing keeps it
+        db.hset(b"h".to_vec(), b"a".to_vec(), b"4".into())?;
+        db.hincr_by(b"h".to_vec(), b"b".to_vec(), 1)?;
+        assert_eq!(db.hexpire_time(b"h", &fields)?, vec![-1, deadline]);
+
+        // an expired field is invisible to readers and gone from the hash
+        db.hexpire(b"h", &[b"a".to_vec()], now_ms() + 60_000, |_| true)?;
+        if let Entry::Occupied(mut entry) = db.db.keyspace.entry(b"h".to_vec()) {
+            if let Value::Hash(hash) = entry.get_mut() {
+                hash.expires.insert(b"a".to_vec(), now_ms() - 1);
+            }
+        }
+        assert_eq!(db.hget(b"h", b"a")?, None);
+        assert_eq!(db.hgetall(b"h")?.map(|hash| hash.len()), Some(2));
+        assert_eq!(db.hexpire_time(b"h", &fields)?, vec![-2, deadline]);
+
+        // rename carries the field ttls and their index entry along
+        db.rename(b"h", b"r".to_vec(), false);
+        assert!(db.db.field_expires.contains_key(b"r".as_slice()));
+        assert!(!db.db.field_expires.contains_key(b"h".as_slice()));
+        assert_eq!(db.hpersist(b"r", &fields)?, vec![-2, 1]);
+        assert!(!db.db.field_expires.contains_key(b"r".as_slice()));
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::table::{Position, ScanBudget, Table};
+use super::{Key, Value};
+
+// the keyspace is split in 2^SHARD_BITS tables, each behind its own lock
+const SHARD_BITS: u32 = 6;
+
+/*
+- the keys of a db, sharded like a DashMap but over our own tables so SCAN can walk them with a
+  cursor that survives resizes
+- a key picks its shard by the top bits of its hash and its bucket by the low bits, the shards
+  share one hasher so a key is hashed once
+*/
+pub struct Keyspace {
+    shards: Box<[RwLock<Table<Key, Value>>]>,
+    hasher: RandomState,
+}
+
+impl Keyspace {
+    pub fn new() -> Self {
+        let hasher = RandomState::new();
+        Keyspace {
+            shards: (0..1 << SHARD_BITS)
+                .map(|_| RwLock::new(Table::with_hasher(hasher.clone())))
+                .collect(),
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_>>
+    where
+        Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (shard, hash) = self.locate(key);
+        let guard = read(shard);
+        let position = guard.find(hash, key)?;
+        Some(Ref { guard, position })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    // returns the value the key held before
+    pub fn insert(&self, key: Key, value: Value) -> Option<Value> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    // lock the key's shard for writing until the entry is dropped
+    pub fn entry(&self, key: Key) -> Entry<'_> {
+        let (shard, hash) = self.locate(&key);
+        let guard = write(shard);
+        match guard.find(hash, &key) {
+            Some(position) => Entry::Occupied(OccupiedEntry { guard, position }),
+            None => Entry::Vacant(VacantEntry { guard, hash, key }),
+        }
+    }
+
+    // write lock the shards of every key at once, in shard order so two callers locking
+    // overlapping keys never wait on each other
+    pub fn lock<'k>(&self, keys: impl IntoIterator<Item = &'k Key>) -> Locked<'_> {
+        let mut shards = keys
+            .into_iter()
+            .map(|key| self.shard_of(self.hasher.hash_one(key)))
+            .collect::<Vec<_>>();
+        shards.sort_unstable();
+        shards.dedup();
+        let guards = shards
+            .into_iter()
+            .map(|shard| (shard, write(&self.shards[shard])))
+            .collect();
+        Locked {
+            keyspace: self,
+            guards,
+        }
+    }
+
+    // remove the key if `remove` returns true for it
+    pub fn remove_if<Q>(
+        &self,
+        key: &Q,
+        remove: impl FnOnce(&Key, &Value) -> bool,
+    ) -> Option<(Key, Value)>
+    where
+        Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (shard, hash) = self.locate(key);
+        let mut guard = write(shard);
+        let position = guard.find(hash, key)?;
+        if !remove(guard.key_at(position), guard.value_at(position)) {
+            return None;
+        }
+        Some(guard.remove_at(position))
+    }
+
+    // visit every key, one shard is read locked at a time
+    pub fn for_each(&self, mut visit: impl FnMut(&Key, &Value)) {
+        for shard in self.shards.iter() {
+            for (key, value) in read(shard).iter() {
+                visit(key, value);
+            }
+        }
+    }
+
+    /*
+    - one SCAN step: the low SHARD_BITS of the cursor are the shard, the rest is the cursor
+      inside that shard's table. A shard is walked to its end before the next one starts
+    - returns the cursor to continue from, 0 once every shard was walked
+    */
+    pub(crate) fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        mut visit: impl FnMut(&Key, &Value),
+    ) -> u64 {
+        let mut shard = (cursor & ((1 << SHARD_BITS) - 1)) as usize;
+        let mut inner = cursor >> SHARD_BITS;
+        let mut budget = ScanBudget::new(count);
+        while let Some(table) = self.shards.get(shard) {
+            inner = read(table).scan(inner, &mut budget, &mut visit);
+            if inner != 0 {
+                return (inner << SHARD_BITS) | shard as u64;
+            }
+            shard += 1;
+            if budget.spent() {
+                break;
+            }
+        }
+        if shard < self.shards.len() {
+            shard as u64
+        } else {
+            0
+        }
+    }
+
+    fn locate<Q: Hash + ?Sized>(&self, key: &Q) -> (&RwLock<Table<Key, Value>>, u64) {
+        let hash = self.hasher.hash_one(key);
+        (&self.shards[self.shard_of(hash)], hash)
+    }
+
+    fn shard_of(&self, hash: u64) -> usize {
+        (hash >> (u64::BITS - SHARD_BITS)) as usize
+    }
+}
+
+// a panic while a shard was locked leaves its table consistent, every change to it is one step
+fn read(shard: &RwLock<Table<Key, Value>>) -> RwLockReadGuard<'_, Table<Key, Value>> {
+    shard.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(shard: &RwLock<Table<Key, Value>>) -> RwLockWriteGuard<'_, Table<Key, Value>> {
+    shard.write().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Default for Keyspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Keyspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyspace")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+// a key read locked in its shard
+pub struct Ref<'a> {
+    guard: RwLockReadGuard<'a, Table<Key, Value>>,
+    position: Position,
+}
+
+impl Ref<'_> {
+    pub fn value(&self) -> &Value {
+        self.guard.value_at(self.position)
+    }
+}
+
+impl Deref for Ref<'_> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        self.value()
+    }
+}
+
+// a key write locked in its shard
+pub struct RefMut<'a> {
+    guard: RwLockWriteGuard<'a, Table<Key, Value>>,
+    position: Position,
+}
+
+impl RefMut<'_> {
+    pub fn value(&self) -> &Value {
+        self.guard.value_at(self.position)
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value {
+        self.guard.value_at_mut(self.position)
+    }
+}
+
+impl Deref for RefMut<'_> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        self.value()
+    }
+}
+
+impl DerefMut for RefMut<'_> {
+    fn deref_mut(&mut self) -> &mut Value {
+        self.value_mut()
+    }
+}
+
+// the shards of a set of keys, locked by `Keyspace::lock`
+pub struct Locked<'a> {
+    keyspace: &'a Keyspace,
+    guards: Vec<(usize, RwLockWriteGuard<'a, Table<Key, Value>>)>,
+}
+
+impl Locked<'_> {
+    // the value of one of the locked keys
+    pub fn get(&self, key: &[u8]) -> Option<&Value> {
+        let shard = self.shard_of(key);
+        match self.guards.iter().find(|(locked, _)| *locked == shard) {
+            Some((_, guard)) => guard.get(key),
+            None => panic!("the shard of the key is not locked"),
+        }
+    }
+
+    // the table holding `key`, which must be one of the locked keys
+    pub fn table(&mut self, key: &[u8]) -> &mut Table<Key, Value> {
+        let shard = self.shard_of(key);
+        match self.guards.iter_mut().find(|(locked, _)| *locked == shard) {
+            Some((_, guard)) => guard,
+            None => panic!("the shard of the key is not locked"),
+        }
+    }
+
+    fn shard_of(&self, key: &[u8]) -> usize {
+        self.keyspace.shard_of(self.keyspace.hasher.hash_one(key))
+    }
+}
+
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+pub struct OccupiedEntry<'a> {
+    guard: RwLockWriteGuard<'a, Table<Key, Value>>,
+    position: Position,
+}
+
+impl OccupiedEntry<'_> {
+    pub fn key(&self) -> &Key {
+        self.guard.key_at(self.position)
+    }
+
+    pub fn get(&self) -> &Value {
+        self.guard.value_at(self.position)
+    }
+
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.guard.value_at_mut(self.position)
+    }
+
+    // replace the value, returns the old one
+    pub fn insert(&mut self, value: Value) -> Value {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(mut self) -> Value {
+        self.guard.remove_at(self.position).1
+    }
+}
+
+pub struct VacantEntry<'a> {
+    guard: RwLockWriteGuard<'a, Table<Key, Value>>,
+    hash: u64,
+    key: Key,
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn insert(mut self, value: Value) -> RefMut<'a> {
+        let position = self.guard.insert_new(self.hash, self.key, value);
+        RefMut {
+            guard: self.guard,
+            position,
+        }
+    }
+}
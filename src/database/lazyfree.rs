@@ -0,0 +1,84 @@
+use std::sync::mpsc::{self, Sender};
+
+use std::sync::Arc;
+
+use crate::database::{Database, Db, Value};
+
+// values with more elements than this are dropped off the executor, like lazyfree in redis
+const LAZYFREE_THRESHOLD: usize = 64;
+
+impl Value {
+    // roughly the number of allocations freed by dropping the value
+    pub(crate) fn free_effort(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::Hash(hash) => hash.fields.len(),
+            Value::Set(set) => set.len(),
+        }
+    }
+}
+
+impl Database {
+    // remove the key now, but free a large value on the background thread
+    pub fn unlink(&self, key: &[u8]) -> bool {
+        if self.expire_if_needed(key) {
+            return false;
+        }
+        match self.take(key) {
+            Some((_, value, _)) => {
+                self.free_async(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn free_async(&self, value: Value) {
+        if value.free_effort() > LAZYFREE_THRESHOLD {
+            self.free_in_background(Box::new(value));
+        }
+    }
+
+    // a flushed db, handles still using it keep it alive until they are done
+    pub(crate) fn drop_db(&self, db: Arc<Db>, lazy: bool) {
+        if lazy && db.keyspace.len() > LAZYFREE_THRESHOLD {
+            self.free_in_background(Box::new(db));
+        }
+    }
+
+    fn free_in_background(&self, garbage: Box<dyn Send>) {
+        let sender = self.lazyfree.get_or_init(spawn_lazyfree_thread);
+        // the thread lives as long as the database, a failed send just drops it here
+        let _ = sender.send(garbage);
+    }
+}
+
+// the thread exits when its sender is dropped together with the database
+fn spawn_lazyfree_thread() -> Sender<Box<dyn Send>> {
+    let (sender, receiver) = mpsc::channel::<Box<dyn Send>>();
+    std::thread::spawn(move || receiver.into_iter().for_each(drop));
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Members;
+
+    use super::*;
+
+    #[test]
+    fn test_unlink() {
+        let db = Database::new();
+        let members = (0..1000).map(|i: i32| i.to_be_bytes().to_vec());
+        db.db
+            .keyspace
+            .insert(b"big".to_vec(), Value::Set(members.collect::<Members>()));
+        db.set(b"small".to_vec(), b"v".into());
+
+        assert!(db.unlink(b"big"));
+        assert!(db.lazyfree.get().is_some());
+        assert!(!db.exists(b"big"));
+        assert!(db.unlink(b"small"));
+        assert!(!db.unlink(b"small"));
+    }
+}
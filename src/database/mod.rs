@@ -1,42 +1,106 @@
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI64};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock, RwLock};
 
-use dashmap::{DashMap, DashSet};
+use dashmap::DashMap;
 
+use crate::config::Config;
 use crate::resp::RespFrame;
 
+pub(crate) use expire::now_ms;
+pub use scan::ScanPage;
+pub(crate) use string::string_bytes;
+pub use value::{Fields, HashValue, Key, Members, NumericError, Value, WrongType};
+
+use aof::Aof;
+use keyspace::{Entry, Keyspace};
+
+mod aof;
+mod crc64;
+mod expire;
+mod hash;
+mod keyspace;
+mod lazyfree;
+mod random;
+mod rdb;
+mod scan;
+mod set;
+mod string;
+mod table;
+mod value;
+
+// a handle on one of the numbered databases, cheap to clone
 #[derive(Debug, Clone)]
-pub struct Database(Arc<Backend>);
+pub struct Database {
+    backend: Arc<Backend>,
+    // the selected db, resolved when the handle was made so a SWAPDB doesn't affect it midway
+    pub(crate) db: Arc<Db>,
+    pub(crate) index: usize,
+}
 
 #[derive(Debug)]
 pub struct Backend {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) hset: DashMap<String, DashSet<String>>,
+    // SWAPDB and FLUSHDB replace whole dbs, so each slot can be swapped on its own
+    pub(crate) dbs: Vec<RwLock<Arc<Db>>>,
+    pub(crate) config: Config,
+    // unix seconds of the last successful snapshot
+    pub(crate) last_save: AtomicI64,
+    pub(crate) bgsave_in_progress: AtomicBool,
+    // opened by `load_aof` when appendonly is enabled
+    pub(crate) aof: OnceLock<Aof>,
+    // feeds the thread that frees unlinked values and flushed dbs, started on first use
+    pub(crate) lazyfree: OnceLock<Sender<Box<dyn Send>>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Db {
+    pub(crate) keyspace: Keyspace,
+    // key -> absolute deadline in unix milliseconds
+    pub(crate) expires: DashMap<Key, i64>,
+    // hashes with field ttls -> their earliest field deadline, drives the active field expiry
+    pub(crate) field_expires: DashMap<Key, i64>,
 }
 
 impl Deref for Database {
     type Target = Backend;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.backend
     }
 }
 
 impl Default for Database {
     fn default() -> Self {
-        Self(Arc::new(Backend::default()))
+        Self::with_config(Config::default())
     }
 }
 
-impl Default for Backend {
-    fn default() -> Self {
+impl Backend {
+    pub fn new(config: Config) -> Self {
         Self {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-            hset: DashMap::new(),
+            dbs: (0..config.databases.max(1))
+                .map(|_| RwLock::new(Arc::default()))
+                .collect(),
+            config,
+            last_save: AtomicI64::new(now_ms() / 1000),
+            bgsave_in_progress: AtomicBool::new(false),
+            aof: OnceLock::new(),
+            lazyfree: OnceLock::new(),
         }
     }
+
+    fn slot(&self, index: usize) -> Option<Arc<Db>> {
+        let slot = self.dbs.get(index)?;
+        Some(slot.read().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    // put a new db in the slot, returns the one it replaced
+    fn replace_slot(&self, index: usize, db: Arc<Db>) -> Option<Arc<Db>> {
+        let slot = self.dbs.get(index)?;
+        let mut slot = slot.write().unwrap_or_else(|e| e.into_inner());
+        Some(std::mem::replace(&mut *slot, db))
+    }
 }
 
 impl Database {
@@ -44,35 +108,237 @@ impl Database {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub fn with_config(config: Config) -> Self {
+        let backend = Arc::new(Backend::new(config));
+        let db = backend.slot(0).unwrap_or_default();
+        Self {
+            backend,
+            db,
+            index: 0,
+        }
+    }
+
+    // a handle on another db of the same server, None if the index is out of range
+    pub fn select(&self, index: usize) -> Option<Database> {
+        Some(Self {
+            backend: self.backend.clone(),
+            db: self.backend.slot(index)?,
+            index,
+        })
+    }
+
+    pub fn databases(&self) -> usize {
+        self.dbs.len()
+    }
+
+    pub fn dbsize(&self) -> usize {
+        self.db.keyspace.len()
+    }
+
+    // empty the selected db, with `lazy` the old content is freed in the background
+    pub fn flushdb(&self, lazy: bool) {
+        if let Some(old) = self.backend.replace_slot(self.index, Arc::default()) {
+            self.drop_db(old, lazy);
+        }
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    pub fn flushall(&self, lazy: bool) {
+        for index in 0..self.databases() {
+            if let Some(old) = self.backend.replace_slot(index, Arc::default()) {
+                self.drop_db(old, lazy);
+            }
+        }
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    // exchange the content of two dbs, clients see the other data on their next command
+    pub fn swapdb(&self, first: usize, second: usize) -> bool {
+        if first >= self.databases() || second >= self.databases() {
+            return false;
+        }
+        if first != second {
+            // lock in index order so two concurrent swaps can't deadlock
+            let (low, high) = (first.min(second), first.max(second));
+            let mut low_slot = self.dbs[low].write().unwrap_or_else(|e| e.into_inner());
+            let mut high_slot = self.dbs[high].write().unwrap_or_else(|e| e.into_inner());
+            std::mem::swap(&mut *low_slot, &mut *high_slot);
+        }
+        true
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+    // move the key with its ttl to another db, false if it's missing here or exists there
+    pub fn move_key(&self, key: &[u8], target: &Database) -> bool {
+        if !self.exists(key) || target.exists(key) {
+            return false;
+        }
+        let Some((key, value, deadline)) = self.take(key) else {
+            return false;
+        };
+        self.db.field_expires.remove(&key);
+        target.install(key, value, deadline);
+        true
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    pub fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(WrongType),
+            None => Ok(None),
+        }
     }
 
-    pub fn sadd(&self, key: String, val: String) {
-        let hdata = self.hset.entry(key).or_default();
-        hdata.insert(val);
+    // SET replaces whatever the key held, whatever its type
+    pub fn set(&self, key: Key, value: RespFrame) {
+        let _ = self.set_with_options(key, value, None, false, None, false);
     }
 
-    pub fn sall(&self, key: &str) -> Option<DashSet<String>> {
-        self.hset.get(key).map(|v| v.clone())
+    pub fn set_keep_ttl(&self, key: Key, value: RespFrame) {
+        let _ = self.set_with_options(key, value, None, true, None, false);
+    }
+
+    pub fn set_with_expire(&self, key: Key, value: RespFrame, deadline: i64) {
+        let _ = self.set_with_options(key, value, Some(deadline), false, None, false);
+    }
+
+    // SET with its options, the existence check, the old value and the write all happen under the
+    // key's guard so concurrent writers can't interleave. The write only happens when `exists`
+    // matches the key (NX/XX); with `get` a value that is not a string fails it. Returns whether
+    // the value was written and the string it replaced
+    pub fn set_with_options(
+        &self,
+        key: Key,
+        value: RespFrame,
+        deadline: Option<i64>,
+        keep_ttl: bool,
+        exists: Option<bool>,
+        get: bool,
+    ) -> Result<(bool, Option<RespFrame>), WrongType> {
+        self.expire_if_needed(&key);
+        let (replaced, old) = match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old = match entry.get() {
+                    Value::String(old) => Some(old.clone()),
+                    _ if get => return Err(WrongType),
+                    _ => None,
+                };
+                if exists == Some(false) {
+                    return Ok((false, old));
+                }
+                self.store_expire(entry.key(), deadline, keep_ttl);
+                (Some(entry.insert(Value::String(value))), old)
+            }
+            Entry::Vacant(entry) => {
+                if exists == Some(true) {
+                    return Ok((false, None));
+                }
+                self.store_expire(entry.key(), deadline, keep_ttl);
+                entry.insert(Value::String(value));
+                (None, None)
+            }
+        };
+        // a replaced hash with field ttls leaves its index entry to the active expiry
+        if let Some(replaced) = replaced {
+            self.free_async(replaced);
+        }
+        Ok((true, old))
+    }
+
+    pub fn exists(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        self.db.keyspace.contains_key(key)
+    }
+
+    pub fn key_type(&self, key: &[u8]) -> Option<&'static str> {
+        self.expire_if_needed(key);
+        self.db.keyspace.get(key).map(|v| v.type_name())
+    }
+
+    pub fn remove(&self, key: &[u8]) -> bool {
+        // an expired key doesn't count as removed
+        if self.expire_if_needed(key) {
+            return false;
+        }
+        self.take(key).is_some()
+    }
+
+    // move the value and its ttl to `newkey`, replacing what was there; None if `key` is missing,
+    // false if `nx` is set and `newkey` already exists
+    pub fn rename(&self, key: &[u8], newkey: Key, nx: bool) -> Option<bool> {
+        if !self.exists(key) {
+            return None;
+        }
+        if key == newkey.as_slice() {
+            return Some(!nx);
+        }
+        if nx && self.exists(&newkey) {
+            return Some(false);
+        }
+        let (_, value, deadline) = self.take(key)?;
+        self.db.field_expires.remove(key);
+        self.install(newkey, value, deadline);
+        Some(true)
+    }
+
+    // copy the value and its ttl to `destination`, false if nothing was copied
+    pub fn copy(&self, source: &[u8], destination: Key, replace: bool) -> bool {
+        self.expire_if_needed(source);
+        let Some(value) = self.db.keyspace.get(source).map(|v| v.value().clone()) else {
+            return false;
+        };
+        if !replace && self.exists(&destination) {
+            return false;
+        }
+        let deadline = self.db.expires.get(source).map(|v| *v.value());
+        self.install(destination, value, deadline);
+        true
+    }
+
+    // store a value under `key` with the given deadline, freeing what it replaces off the executor
+    fn install(&self, key: Key, value: Value, deadline: Option<i64>) {
+        let next = match &value {
+            Value::Hash(hash) => hash.next_expire(),
+            _ => None,
+        };
+        let old = match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                self.index_field_expires(entry.key(), next);
+                self.store_expire(entry.key(), deadline, false);
+                Some(entry.insert(value))
+            }
+            Entry::Vacant(entry) => {
+                self.index_field_expires(entry.key(), next);
+                self.store_expire(entry.key(), deadline, false);
+                entry.insert(value);
+                None
+            }
+        };
+        if let Some(old) = old {
+            self.free_async(old);
+        }
+    }
+
+    // remove the key together with its ttl, both under the key's guard so a concurrent write of
+    // the same key keeps the ttl it was given
+    pub(crate) fn take(&self, key: &[u8]) -> Option<(Key, Value, Option<i64>)> {
+        let mut deadline = None;
+        let (key, value) = self.db.keyspace.remove_if(key, |key, _| {
+            deadline = self.db.expires.remove(key).map(|(_, deadline)| deadline);
+            true
+        })?;
+        Some((key, value, deadline))
+    }
+
+    // the ttl of a key being written, only call it while holding the key's entry: the ttl of a
+    // key is never changed without its guard, the keyspace lock is always taken first
+    fn store_expire(&self, key: &[u8], deadline: Option<i64>, keep_ttl: bool) {
+        match deadline {
+            Some(deadline) => {
+                self.db.expires.insert(key.to_vec(), deadline);
+            }
+            None if keep_ttl => {}
+            None => {
+                self.db.expires.remove(key);
+            }
+        }
     }
 }
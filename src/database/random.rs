@@ -0,0 +1,107 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+thread_local! {
+    // seeded from the random keys std gives every hasher, no need for a crypto grade source
+    static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+}
+
+// xorshift64*, good enough to pick random fields and members
+pub(crate) fn random_u64() -> u64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+// a uniform index below `len`, which must not be 0
+pub(crate) fn random_index(len: usize) -> usize {
+    (random_u64() % len as u64) as usize
+}
+
+// redis count semantics: a positive count picks that many distinct items (at most all of them),
+// a negative one picks `-count` items that may repeat. `items` is walked once and only the picked
+// items are returned, so callers can sample straight from the collection under its guard
+pub(crate) fn sample<I>(items: I, count: i64) -> Vec<I::Item>
+where
+    I: ExactSizeIterator,
+    I::Item: Clone,
+{
+    let len = items.len();
+    if len == 0 || count == 0 {
+        return Vec::new();
+    }
+    if count < 0 {
+        // sorted positions let one pass pick them all, the picks are shuffled back afterwards
+        let mut positions = (0..count.unsigned_abs())
+            .map(|_| random_index(len))
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        let mut positions = positions.into_iter().peekable();
+        let mut picked = Vec::with_capacity(positions.len());
+        for (i, item) in items.enumerate() {
+            while positions.next_if_eq(&i).is_some() {
+                picked.push(item.clone());
+            }
+        }
+        shuffle(&mut picked);
+        return picked;
+    }
+    // selection sampling: each item is picked with the odds of filling the remaining slots
+    let mut wanted = (count as usize).min(len);
+    let mut picked = Vec::with_capacity(wanted);
+    for (i, item) in items.enumerate() {
+        if wanted == 0 {
+            break;
+        }
+        if random_index(len - i) < wanted {
+            picked.push(item);
+            wanted -= 1;
+        }
+    }
+    shuffle(&mut picked);
+    picked
+}
+
+// fisher-yates
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random_index(i + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let items = (0..10).collect::<Vec<_>>();
+        let picked = sample(items.iter(), 5);
+        assert_eq!(picked.len(), 5);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 5);
+        assert_eq!(sample(items.iter(), 100).len(), 10);
+        assert_eq!(sample(items.iter(), 0).len(), 0);
+
+        let picked = sample([1].iter(), -5);
+        assert_eq!(picked, vec![&1; 5]);
+        assert!(sample(Vec::<u8>::new().iter(), -5).is_empty());
+
+        // every item shows up eventually, either way of counting
+        let seen = (0..1000)
+            .flat_map(|_| sample(items.iter(), -1))
+            .collect::<HashSet<_>>();
+        assert_eq!(seen.len(), 10);
+        let seen = (0..1000)
+            .flat_map(|_| sample(items.iter(), 1))
+            .collect::<HashSet<_>>();
+        assert_eq!(seen.len(), 10);
+    }
+}
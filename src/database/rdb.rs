@@ -0,0 +1,835 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{anyhow, bail, Result};
+use tracing::{info, warn};
+
+use crate::database::crc64::crc64;
+use crate::database::value::frame_to_bytes;
+use crate::database::{now_ms, Database, HashValue, Key, Value};
+use crate::resp::TBulkString;
+
+/*
+- rdb layout written by `save`:
+    - header: "REDIS0009", or "REDIS0012" when a hash with field ttls needs type 24
+    - aux fields: 0xfa <key> <value>
+    - select db: 0xfe <db-number>
+    - resize db: 0xfb <keys-size> <expires-size>
+    - per key: [0xfc <unix-ms u64 le>] <value-type> <key> <value>
+        - string (0): <string>
+        - set (2): <len> <member-1>...<member-n>
+        - hash (4): <len> <field-1><value-1>...<field-n><value-n>
+        - hash with field ttls (24): <min-deadline u64 le> <len> <ttl-1><field-1><value-1>...
+          where a ttl of 0 means none, otherwise it is deadline - min-deadline + 1
+    - eof: 0xff <crc64 u64 le>
+- length encoding: 00|6bit, 01|14bit, 0x80 + u32 be, 0x81 + u64 be, 11|enc for special strings
+*/
+
+const RDB_MAGIC: &[u8] = b"REDIS";
+const RDB_VERSION: u32 = 9;
+// the first version with RDB_TYPE_HASH_METADATA, written only when a dump needs it so older
+// readers keep loading the others
+const RDB_VERSION_HASH_METADATA: u32 = 12;
+
+const RDB_OPCODE_FREQ: u8 = 0xf8;
+const RDB_OPCODE_IDLE: u8 = 0xf9;
+const RDB_OPCODE_AUX: u8 = 0xfa;
+const RDB_OPCODE_RESIZEDB: u8 = 0xfb;
+const RDB_OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const RDB_OPCODE_EXPIRETIME: u8 = 0xfd;
+const RDB_OPCODE_SELECTDB: u8 = 0xfe;
+const RDB_OPCODE_EOF: u8 = 0xff;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_SET: u8 = 2;
+const RDB_TYPE_HASH: u8 = 4;
+const RDB_TYPE_HASH_METADATA: u8 = 24;
+
+const RDB_ENC_INT8: u64 = 0;
+const RDB_ENC_INT16: u64 = 1;
+const RDB_ENC_INT32: u64 = 2;
+const RDB_ENC_LZF: u64 = 3;
+
+// the most a back reference expands to: 3 bytes of input for a run of 264 bytes
+const LZF_MAX_EXPANSION: usize = 88;
+
+#[derive(Debug, PartialEq)]
+pub(super) enum RdbValue {
+    String(Vec<u8>),
+    Set(Vec<Vec<u8>>),
+    Hash(Vec<(Vec<u8>, Vec<u8>)>),
+    // a hash where some fields have a deadline
+    HashMetadata(Vec<(Vec<u8>, Vec<u8>, Option<i64>)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct RdbEntry {
+    pub(super) db: usize,
+    pub(super) key: Vec<u8>,
+    pub(super) value: RdbValue,
+    // absolute deadline in unix milliseconds
+    pub(super) expire: Option<i64>,
+}
+
+impl Database {
+    // SAVE and BGSAVE share the in progress flag, so only one of them writes at a time
+    pub fn save(&self) -> Result<()> {
+        if !self.claim_save() {
+            bail!("Background save already in progress");
+        }
+        let entries = self.snapshot();
+        let saved = write_rdb(&self.config.rdb_path(), &entries);
+        self.bgsave_in_progress.store(false, Ordering::SeqCst);
+        saved?;
+        self.last_save.store(now_ms() / 1000, Ordering::SeqCst);
+        info!("DB saved on disk: {} keys", entries.len());
+        Ok(())
+    }
+
+    /*
+    - snapshot and write on a background thread, so a large dataset doesn't hold up the caller;
+      returns false if a save is running
+    - unlike the fork of redis the dump is not taken at one point in time: the keys are copied
+      one shard at a time while writes go on, so a write that lands during the copy may be in
+      the dump or not, and two writes to keys in different shards may be saved one without the
+      other
+    */
+    pub fn bgsave(&self) -> bool {
+        if !self.claim_save() {
+            return false;
+        }
+        let db = self.clone();
+        std::thread::spawn(move || {
+            let entries = db.snapshot();
+            match write_rdb(&db.config.rdb_path(), &entries) {
+                Ok(()) => {
+                    db.last_save.store(now_ms() / 1000, Ordering::SeqCst);
+                    info!("Background saving terminated with success");
+                }
+                Err(e) => warn!("Background saving error: {:?}", e),
+            }
+            db.bgsave_in_progress.store(false, Ordering::SeqCst);
+        });
+        true
+    }
+
+    fn claim_save(&self) -> bool {
+        self.bgsave_in_progress
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
+    pub fn last_save(&self) -> i64 {
+        self.last_save.load(Ordering::SeqCst)
+    }
+
+    // load the configured dump file, a missing file is not an error
+    pub fn load(&self) -> Result<usize> {
+        let path = self.config.rdb_path();
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let now = now_ms();
+        let mut loaded = 0;
+        for entry in parse_rdb(&data)? {
+            if matches!(entry.expire, Some(deadline) if deadline <= now) {
+                continue;
+            }
+            let Some(db) = self.select(entry.db) else {
+                bail!(
+                    "The rdb file contains key of db {}, but only {} databases are configured",
+                    entry.db,
+                    self.databases()
+                );
+            };
+            let key = entry.key;
+            let value = match entry.value {
+                RdbValue::String(value) => Value::String(TBulkString::new(value).into()),
+                RdbValue::Set(members) => Value::Set(members.into_iter().collect()),
+                RdbValue::Hash(fields) => Value::Hash(HashValue {
+                    fields: fields
+                        .into_iter()
+                        .map(|(field, value)| (field, TBulkString::new(value).into()))
+                        .collect(),
+                    expires: HashMap::new(),
+                }),
+                RdbValue::HashMetadata(fields) => {
+                    let mut hash = HashValue::default();
+                    for (field, value, deadline) in fields {
+                        match deadline {
+                            Some(deadline) if deadline <= now => continue,
+                            Some(deadline) => {
+                                hash.expires.insert(field.clone(), deadline);
+                            }
+                            None => {}
+                        }
+                        hash.fields.insert(field, TBulkString::new(value).into());
+                    }
+                    // every field has expired
+                    if hash.fields.is_empty() {
+                        continue;
+                    }
+                    if let Some(next) = hash.next_expire() {
+                        db.db.field_expires.insert(key.clone(), next);
+                    }
+                    Value::Hash(hash)
+                }
+            };
+            db.db.keyspace.insert(key.clone(), value);
+            if let Some(deadline) = entry.expire {
+                db.db.expires.insert(key, deadline);
+            }
+            loaded += 1;
+        }
+        info!("DB loaded from disk: {} keys", loaded);
+        Ok(loaded)
+    }
+
+    // the entries of every db, grouped by db in index order
+    pub(super) fn snapshot(&self) -> Vec<RdbEntry> {
+        self.copy_keys().into_entries()
+    }
+
+    // every live key as it is now, a plain copy so callers that hold writes off while taking it
+    // only wait for the clone and not for the conversion to entries
+    pub(super) fn copy_keys(&self) -> KeyCopy {
+        let now = now_ms();
+        let mut keys = Vec::new();
+        for db in (0..self.databases()).filter_map(|index| self.select(index)) {
+            keys.reserve(db.db.keyspace.len());
+            db.db.keyspace.for_each(|key, value| {
+                let expire = db.db.expires.get(key).map(|v| *v.value());
+                if !matches!(expire, Some(deadline) if deadline <= now) {
+                    keys.push((db.index, key.clone(), value.clone(), expire));
+                }
+            });
+        }
+        KeyCopy { now, keys }
+    }
+}
+
+// the keys copied by `copy_keys`, with the time expired fields are dropped against
+pub(super) struct KeyCopy {
+    now: i64,
+    keys: Vec<(usize, Key, Value, Option<i64>)>,
+}
+
+impl KeyCopy {
+    pub(super) fn into_entries(self) -> Vec<RdbEntry> {
+        let now = self.now;
+        self.keys
+            .into_iter()
+            .filter_map(|(db, key, value, expire)| {
+                let value = match value {
+                    Value::String(value) => RdbValue::String(frame_to_bytes(&value).into()),
+                    Value::Hash(hash) if hash.expires.is_empty() => RdbValue::Hash(
+                        hash.fields
+                            .into_iter()
+                            .map(|(field, value)| (field, frame_to_bytes(&value).into()))
+                            .collect(),
+                    ),
+                    Value::Hash(hash) => {
+                        let fields = hash
+                            .fields
+                            .into_iter()
+                            .map(|(field, value)| {
+                                let deadline = hash.expires.get(&field).copied();
+                                (field, frame_to_bytes(&value).into(), deadline)
+                            })
+                            .filter(|(_, _, deadline)| !deadline.is_some_and(|d| d <= now))
+                            .collect::<Vec<_>>();
+                        if fields.is_empty() {
+                            return None;
+                        }
+                        RdbValue::HashMetadata(fields)
+                    }
+                    Value::Set(set) => RdbValue::Set(set.into_iter().collect()),
+                };
+                Some(RdbEntry {
+                    db,
+                    key,
+                    value,
+                    expire,
+                })
+            })
+            .collect()
+    }
+}
+
+fn write_rdb(path: &Path, entries: &[RdbEntry]) -> Result<()> {
+    // write to a temp file and rename, so a crash never leaves a truncated dump behind
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid rdb path"))?;
+    // unique per write, two writers never share a temp file even across databases
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let tmp = path.with_file_name(format!(
+        "temp-{}-{}-{}",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed),
+        name.to_string_lossy()
+    ));
+    let file = File::create(&tmp)?;
+    let mut writer = RdbWriter::new(BufWriter::new(file));
+    writer.write_header(rdb_version(entries))?;
+    writer.write_entries(entries)?;
+    let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// the oldest version that can hold every entry
+fn rdb_version(entries: &[RdbEntry]) -> u32 {
+    match entries
+        .iter()
+        .any(|entry| matches!(entry.value, RdbValue::HashMetadata(_)))
+    {
+        true => RDB_VERSION_HASH_METADATA,
+        false => RDB_VERSION,
+    }
+}
+
+struct RdbWriter<W: Write> {
+    inner: W,
+    crc: u64,
+}
+
+impl<W: Write> RdbWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, crc: 0 }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.crc = crc64(self.crc, data);
+        self.inner.write_all(data)?;
+        Ok(())
+    }
+
+    fn write_length(&mut self, len: usize) -> Result<()> {
+        if len < 1 << 6 {
+            self.write(&[len as u8])
+        } else if len < 1 << 14 {
+            self.write(&[((len >> 8) as u8) | 0x40, len as u8])
+        } else if len <= u32::MAX as usize {
+            self.write(&[0x80])?;
+            self.write(&(len as u32).to_be_bytes())
+        } else {
+            self.write(&[0x81])?;
+            self.write(&(len as u64).to_be_bytes())
+        }
+    }
+
+    fn write_string(&mut self, s: &[u8]) -> Result<()> {
+        self.write_length(s.len())?;
+        self.write(s)
+    }
+
+    fn write_aux(&mut self, key: &str, value: &str) -> Result<()> {
+        self.write(&[RDB_OPCODE_AUX])?;
+        self.write_string(key.as_bytes())?;
+        self.write_string(value.as_bytes())
+    }
+
+    fn write_header(&mut self, version: u32) -> Result<()> {
+        self.write(RDB_MAGIC)?;
+        self.write(format!("{:04}", version).as_bytes())?;
+        // the redis release that introduced the version
+        let redis_ver = match version {
+            RDB_VERSION_HASH_METADATA => "7.4.0",
+            _ => "7.0.0",
+        };
+        self.write_aux("redis-ver", redis_ver)?;
+        self.write_aux("redis-bits", "64")?;
+        self.write_aux("ctime", &(now_ms() / 1000).to_string())
+    }
+
+    // entries must be grouped by db, each group gets its own select and resize opcodes
+    fn write_entries(&mut self, entries: &[RdbEntry]) -> Result<()> {
+        for group in entries.chunk_by(|a, b| a.db == b.db) {
+            self.write_db(group)?;
+        }
+        Ok(())
+    }
+
+    fn write_db(&mut self, entries: &[RdbEntry]) -> Result<()> {
+        let expires = entries.iter().filter(|e| e.expire.is_some()).count();
+        self.write(&[RDB_OPCODE_SELECTDB])?;
+        self.write_length(entries[0].db)?;
+        self.write(&[RDB_OPCODE_RESIZEDB])?;
+        self.write_length(entries.len())?;
+        self.write_length(expires)?;
+        for entry in entries {
+            if let Some(deadline) = entry.expire {
+                self.write(&[RDB_OPCODE_EXPIRETIME_MS])?;
+                self.write(&(deadline as u64).to_le_bytes())?;
+            }
+            match &entry.value {
+                RdbValue::String(value) => {
+                    self.write(&[RDB_TYPE_STRING])?;
+                    self.write_string(&entry.key)?;
+                    self.write_string(value)?;
+                }
+                RdbValue::Set(members) => {
+                    self.write(&[RDB_TYPE_SET])?;
+                    self.write_string(&entry.key)?;
+                    self.write_length(members.len())?;
+                    for member in members {
+                        self.write_string(member)?;
+                    }
+                }
+                RdbValue::Hash(fields) => {
+                    self.write(&[RDB_TYPE_HASH])?;
+                    self.write_string(&entry.key)?;
+                    self.write_length(fields.len())?;
+                    for (field, value) in fields {
+                        self.write_string(field)?;
+                        self.write_string(value)?;
+                    }
+                }
+                RdbValue::HashMetadata(fields) => {
+                    let min = fields.iter().filter_map(|(_, _, d)| *d).min();
+                    let min = min.unwrap_or_default();
+                    self.write(&[RDB_TYPE_HASH_METADATA])?;
+                    self.write_string(&entry.key)?;
+                    self.write(&(min as u64).to_le_bytes())?;
+                    self.write_length(fields.len())?;
+                    for (field, value, deadline) in fields {
+                        let ttl = deadline.map_or(0, |deadline| (deadline - min) as usize + 1);
+                        self.write_length(ttl)?;
+                        self.write_string(field)?;
+                        self.write_string(value)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        self.write(&[RDB_OPCODE_EOF])?;
+        let crc = self.crc;
+        self.inner.write_all(&crc.to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+struct RdbReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RdbReader<'a> {
+    fn read_bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            bail!("Unexpected end of rdb file");
+        }
+        let data = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(data)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    // returns (length, is_encoded); encoded lengths carry the special string encoding type
+    fn read_length(&mut self) -> Result<(u64, bool)> {
+        let first = self.read_u8()?;
+        match first >> 6 {
+            0 => Ok(((first & 0x3f) as u64, false)),
+            1 => Ok((
+                (((first & 0x3f) as u64) << 8) | self.read_u8()? as u64,
+                false,
+            )),
+            2 => match first {
+                0x80 => {
+                    let data = self.read_bytes(4)?;
+                    Ok((u32::from_be_bytes(data.try_into()?) as u64, false))
+                }
+                0x81 => {
+                    let data = self.read_bytes(8)?;
+                    Ok((u64::from_be_bytes(data.try_into()?), false))
+                }
+                _ => bail!("Invalid rdb length encoding: {:#x}", first),
+            },
+            _ => Ok(((first & 0x3f) as u64, true)),
+        }
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        match self.read_length()? {
+            (len, false) => Ok(len as usize),
+            (_, true) => bail!("Unexpected encoded length"),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>> {
+        let (len, encoded) = self.read_length()?;
+        if !encoded {
+            return Ok(self.read_bytes(len as usize)?.to_vec());
+        }
+        match len {
+            RDB_ENC_INT8 => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            RDB_ENC_INT16 => {
+                let data = self.read_bytes(2)?;
+                Ok(i16::from_le_bytes(data.try_into()?)
+                    .to_string()
+                    .into_bytes())
+            }
+            RDB_ENC_INT32 => {
+                let data = self.read_bytes(4)?;
+                Ok(i32::from_le_bytes(data.try_into()?)
+                    .to_string()
+                    .into_bytes())
+            }
+            RDB_ENC_LZF => {
+                let compressed_len = self.read_len()?;
+                let len = self.read_len()?;
+                lzf_decompress(self.read_bytes(compressed_len)?, len)
+            }
+            _ => bail!("Unknown rdb string encoding: {}", len),
+        }
+    }
+}
+
+fn parse_rdb(data: &[u8]) -> Result<Vec<RdbEntry>> {
+    let mut reader = RdbReader { buf: data, pos: 0 };
+    if reader.read_bytes(RDB_MAGIC.len())? != RDB_MAGIC {
+        bail!("Wrong signature trying to load DB from file");
+    }
+    let version: u32 = std::str::from_utf8(reader.read_bytes(4)?)?.parse()?;
+    if version == 0 || version > 12 {
+        bail!("Can't handle RDB format version {}", version);
+    }
+
+    let mut entries = Vec::new();
+    let mut db = 0;
+    let mut expire = None;
+    loop {
+        let kind = reader.read_u8()?;
+        match kind {
+            RDB_OPCODE_EOF => break,
+            RDB_OPCODE_AUX => {
+                reader.read_string()?;
+                reader.read_string()?;
+            }
+            RDB_OPCODE_SELECTDB => {
+                db = reader.read_len()?;
+            }
+            RDB_OPCODE_RESIZEDB => {
+                reader.read_len()?;
+                reader.read_len()?;
+            }
+            RDB_OPCODE_EXPIRETIME_MS => {
+                let data = reader.read_bytes(8)?;
+                expire = Some(u64::from_le_bytes(data.try_into()?) as i64);
+            }
+            RDB_OPCODE_EXPIRETIME => {
+                let data = reader.read_bytes(4)?;
+                expire = Some(u32::from_le_bytes(data.try_into()?) as i64 * 1000);
+            }
+            RDB_OPCODE_FREQ => {
+                reader.read_u8()?;
+            }
+            RDB_OPCODE_IDLE => {
+                reader.read_len()?;
+            }
+            RDB_TYPE_STRING | RDB_TYPE_SET | RDB_TYPE_HASH | RDB_TYPE_HASH_METADATA => {
+                let key = reader.read_string()?;
+                let value = match kind {
+                    RDB_TYPE_STRING => RdbValue::String(reader.read_string()?),
+                    RDB_TYPE_SET => {
+                        let len = reader.read_len()?;
+                        let mut members = Vec::with_capacity(len.min(1024));
+                        for _ in 0..len {
+                            members.push(reader.read_string()?);
+                        }
+                        RdbValue::Set(members)
+                    }
+                    RDB_TYPE_HASH_METADATA => {
+                        let data = reader.read_bytes(8)?;
+                        let min = u64::from_le_bytes(data.try_into()?) as i64;
+                        let len = reader.read_len()?;
+                        let mut fields = Vec::with_capacity(len.min(1024));
+                        for _ in 0..len {
+                            let ttl = reader.read_len()?;
+                            let deadline = (ttl > 0).then(|| min + ttl as i64 - 1);
+                            fields.push((reader.read_string()?, reader.read_string()?, deadline));
+                        }
+                        RdbValue::HashMetadata(fields)
+                    }
+                    _ => {
+                        let len = reader.read_len()?;
+                        let mut fields = Vec::with_capacity(len.min(1024));
+                        for _ in 0..len {
+                            fields.push((reader.read_string()?, reader.read_string()?));
+                        }
+                        RdbValue::Hash(fields)
+                    }
+                };
+                entries.push(RdbEntry {
+                    db,
+                    key,
+                    value,
+                    expire: expire.take(),
+                });
+            }
+            _ => bail!("Unsupported rdb value type: {}", kind),
+        }
+    }
+
+    // checksum exists since version 5, a zero checksum means it was disabled when saving
+    if version >= 5 {
+        let expected = crc64(0, &data[..reader.pos]);
+        let data = reader.read_bytes(8)?;
+        let checksum = u64::from_le_bytes(data.try_into()?);
+        if checksum != 0 && checksum != expected {
+            bail!("Wrong RDB checksum");
+        }
+    }
+    Ok(entries)
+}
+
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>> {
+    let invalid = || anyhow!("Invalid LZF compressed string");
+    // the length comes from the file, don't allocate more than the input can expand to
+    if len > input.len().saturating_mul(LZF_MAX_EXPANSION) {
+        return Err(invalid());
+    }
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+        if ctrl < 1 << 5 {
+            // literal run of ctrl + 1 bytes
+            let run = input.get(i..i + ctrl + 1).ok_or_else(invalid)?;
+            out.extend_from_slice(run);
+            i += ctrl + 1;
+        } else {
+            // back reference
+            let mut run = ctrl >> 5;
+            if run == 7 {
+                run += *input.get(i).ok_or_else(invalid)? as usize;
+                i += 1;
+            }
+            let offset = ((ctrl & 0x1f) << 8) + *input.get(i).ok_or_else(invalid)? as usize + 1;
+            i += 1;
+            let start = out.len().checked_sub(offset).ok_or_else(invalid)?;
+            for j in 0..run + 2 {
+                out.push(out[start + j]);
+            }
+        }
+        if out.len() > len {
+            return Err(invalid());
+        }
+    }
+    if out.len() != len {
+        return Err(invalid());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::resp::RespFrame;
+    use crate::test_util::temp_config;
+
+    use super::*;
+
+    fn cleanup(path: PathBuf) {
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_rdb_write_parse() -> Result<()> {
+        let entries = vec![
+            RdbEntry {
+                db: 0,
+                key: b"hello".to_vec(),
+                value: RdbValue::String(b"world".to_vec()),
+                expire: None,
+            },
+            RdbEntry {
+                db: 0,
+                key: b"map".to_vec(),
+                value: RdbValue::Hash(vec![(b"f".to_vec(), vec![b'v'; 100])]),
+                expire: Some(1_900_000_000_000),
+            },
+            RdbEntry {
+                db: 3,
+                key: b"set".to_vec(),
+                value: RdbValue::Set(vec![b"a".to_vec(), vec![b'b'; 20000]]),
+                expire: None,
+            },
+        ];
+        let write = |entries: &[RdbEntry]| -> Result<Vec<u8>> {
+            let mut writer = RdbWriter::new(Vec::new());
+            writer.write_header(rdb_version(entries))?;
+            writer.write_entries(entries)?;
+            writer.finish()
+        };
+        let data = write(&entries)?;
+
+        assert!(data.starts_with(b"REDIS0009"));
+        assert_eq!(parse_rdb(&data)?, entries);
+
+        // field ttls need type 24, which only exists since version 12
+        let with_ttls = vec![RdbEntry {
+            db: 0,
+            key: b"ttls".to_vec(),
+            value: RdbValue::HashMetadata(vec![(b"f".to_vec(), b"v".to_vec(), Some(1))]),
+            expire: None,
+        }];
+        let ttl_data = write(&with_ttls)?;
+        assert!(ttl_data.starts_with(b"REDIS0012"));
+        assert_eq!(parse_rdb(&ttl_data)?, with_ttls);
+
+        let mut corrupted = data.clone();
+        corrupted[20] ^= 0xff;
+        assert!(parse_rdb(&corrupted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rdb_parse_encoded_strings() -> Result<()> {
+        let mut data = b"REDIS0009".to_vec();
+        // key "n" holding int8 encoded -2
+        data.extend_from_slice(&[RDB_TYPE_STRING, 0x01, b'n', 0xc0, 0xfe]);
+        // key "s" holding lzf compressed "abcabc"
+        data.extend_from_slice(&[RDB_TYPE_STRING, 0x01, b's', 0xc3, 0x06, 0x06]);
+        data.extend_from_slice(&[0x02, b'a', b'b', b'c', 0x20, 0x02]);
+        data.push(RDB_OPCODE_EOF);
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        let entries = parse_rdb(&data)?;
+        assert_eq!(entries[0].value, RdbValue::String(b"-2".to_vec()));
+        assert_eq!(entries[1].value, RdbValue::String(b"abcabc".to_vec()));
+
+        // a length the input can't expand to is rejected before anything is allocated for it
+        assert!(lzf_decompress(&[0x02, b'a', b'b', b'c'], usize::MAX).is_err());
+        assert!(lzf_decompress(&[0x02, b'a', b'b', b'c', 0x20, 0x02], 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_load() -> Result<()> {
+        let config = temp_config("save-load");
+        let db = Database::with_config(config.clone());
+        db.set(b"hello".to_vec(), RespFrame::BulkString(b"world".into()));
+        db.set(b"num".to_vec(), RespFrame::Integer(42));
+        db.hset(b"map".to_vec(), b"f".to_vec(), b"v".into())?;
+        db.sadd(b"set".to_vec(), b"m".to_vec())?;
+        db.set_with_expire(b"ttl".to_vec(), b"v".into(), now_ms() + 60_000);
+        db.set_with_expire(b"gone".to_vec(), b"v".into(), now_ms() - 1);
+        db.hset(b"\xff\x00".to_vec(), b"\xfe".to_vec(), b"\x80".into())?;
+        let other = db.select(5).expect("db 5 exists");
+        other.set(b"hello".to_vec(), b"other".into());
+        db.save()?;
+
+        let loaded = Database::with_config(config.clone());
+        assert_eq!(loaded.load()?, 7);
+        let other = loaded.select(5).expect("db 5 exists");
+        assert_eq!(other.get(b"hello")?, Some(b"other".into()));
+        assert_eq!(other.dbsize(), 1);
+        assert_eq!(loaded.hget(b"\xff\x00", b"\xfe")?, Some(b"\x80".into()));
+        assert_eq!(loaded.get(b"hello")?, Some(b"world".into()));
+        assert_eq!(loaded.get(b"num")?, Some(b"42".into()));
+        assert_eq!(loaded.hget(b"map", b"f")?, Some(b"v".into()));
+        assert!(loaded
+            .sall(b"set")?
+            .is_some_and(|s| s.contains(b"m".as_slice())));
+        assert!(loaded.expire_time(b"ttl").is_some());
+        assert_eq!(loaded.get(b"gone")?, None);
+
+        // a dump with more dbs than configured is refused
+        let fewer = Config {
+            databases: 4,
+            ..config.clone()
+        };
+        assert!(Database::with_config(fewer).load().is_err());
+
+        cleanup(config.rdb_path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_load_field_ttls() -> Result<()> {
+        let config = temp_config("field-ttls");
+        let deadline = now_ms() + 60_000;
+        let entries = vec![
+            RdbEntry {
+                db: 0,
+                key: b"h".to_vec(),
+                value: RdbValue::HashMetadata(vec![
+                    (b"a".to_vec(), b"1".to_vec(), Some(deadline)),
+                    (b"b".to_vec(), b"2".to_vec(), None),
+                    (b"c".to_vec(), b"3".to_vec(), Some(now_ms() - 1)),
+                ]),
+                expire: None,
+            },
+            // every field is gone, so is the key
+            RdbEntry {
+                db: 0,
+                key: b"gone".to_vec(),
+                value: RdbValue::HashMetadata(vec![(b"a".to_vec(), b"1".to_vec(), Some(1))]),
+                expire: None,
+            },
+        ];
+        write_rdb(&config.rdb_path(), &entries)?;
+        assert_eq!(parse_rdb(&fs::read(config.rdb_path())?)?, entries);
+
+        let db = Database::with_config(config.clone());
+        assert_eq!(db.load()?, 1);
+        assert_eq!(db.hlen(b"h")?, 2);
+        let fields = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        assert_eq!(db.hexpire_time(b"h", &fields)?, vec![deadline, -1, -2]);
+        assert!(!db.exists(b"gone"));
+        assert!(db.db.field_expires.contains_key(b"h".as_slice()));
+
+        // and back out through a snapshot
+        db.save()?;
+        let loaded = Database::with_config(config.clone());
+        assert_eq!(loaded.load()?, 1);
+        assert_eq!(loaded.hexpire_time(b"h", &fields)?, vec![deadline, -1, -2]);
+
+        cleanup(config.rdb_path());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bgsave() -> Result<()> {
+        let config = temp_config("bgsave");
+        let db = Database::with_config(config.clone());
+        db.set(b"hello".to_vec(), b"world".into());
+        assert!(db.bgsave());
+        while db.bgsave_in_progress.load(Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        // a save never starts while another one is writing
+        db.bgsave_in_progress.store(true, Ordering::SeqCst);
+        assert!(db.save().is_err());
+        assert!(!db.bgsave());
+        db.bgsave_in_progress.store(false, Ordering::SeqCst);
+        db.save()?;
+        assert!(!db.bgsave_in_progress.load(Ordering::SeqCst));
+
+        let loaded = Database::with_config(config.clone());
+        assert_eq!(loaded.load()?, 1);
+        assert_eq!(Database::with_config(temp_config("missing")).load()?, 0);
+
+        cleanup(config.rdb_path());
+        Ok(())
+    }
+}
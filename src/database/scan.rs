@@ -0,0 +1,296 @@
+use crate::database::{now_ms, Database, Key, Value, WrongType};
+use crate::resp::RespFrame;
+
+use super::table::ScanBudget;
+
+// the cursor to continue from (0 when the iteration is over) and the elements of one step
+pub type ScanPage<T> = (u64, Vec<T>);
+
+impl Database {
+    // every live key matching the glob pattern
+    pub fn keys(&self, pattern: &[u8]) -> Vec<Key> {
+        let now = now_ms();
+        let mut keys = Vec::new();
+        self.db.keyspace.for_each(|key, _| {
+            if glob_match(pattern, key) && !self.is_expired(key, now) {
+                keys.push(key.clone());
+            }
+        });
+        keys
+    }
+
+    // one step of a SCAN, the cursor counts in reverse binary like in redis so an iteration
+    // returns every key that exists from its start to its end, even if tables resize midway
+    pub fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        type_name: Option<&str>,
+    ) -> ScanPage<Key> {
+        let now = now_ms();
+        let mut keys = Vec::new();
+        let cursor = self.db.keyspace.scan(cursor, count, |key, value| {
+            let wanted = pattern.is_none_or(|pattern| glob_match(pattern, key))
+                && type_name.is_none_or(|name| value.type_name() == name)
+                && !self.is_expired(key, now);
+            if wanted {
+                keys.push(key.clone());
+            }
+        });
+        (cursor, keys)
+    }
+
+    pub fn hscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<ScanPage<(Vec<u8>, RespFrame)>, WrongType> {
+        self.expire_fields_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Hash(hash)) => {
+                let mut entries = Vec::new();
+                let mut budget = ScanBudget::new(count);
+                let cursor = hash.fields.scan(cursor, &mut budget, |field, value| {
+                    if pattern.is_none_or(|pattern| glob_match(pattern, field)) {
+                        entries.push((field.clone(), value.clone()));
+                    }
+                });
+                Ok((cursor, entries))
+            }
+            Some(_) => Err(WrongType),
+            None => Ok((0, Vec::new())),
+        }
+    }
+
+    pub fn sscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<ScanPage<Vec<u8>>, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Set(set)) => {
+                let mut members = Vec::new();
+                let mut budget = ScanBudget::new(count);
+                let cursor = set.scan(cursor, &mut budget, |member| {
+                    if pattern.is_none_or(|pattern| glob_match(pattern, member)) {
+                        members.push(member.clone());
+                    }
+                });
+                Ok((cursor, members))
+            }
+            Some(_) => Err(WrongType),
+            None => Ok((0, Vec::new())),
+        }
+    }
+
+    // checked without removing, the caller may be iterating over the keyspace
+    fn is_expired(&self, key: &[u8], now: i64) -> bool {
+        self.db
+            .expires
+            .get(key)
+            .is_some_and(|deadline| *deadline <= now)
+    }
+}
+
+// redis glob syntax: `*`, `?`, `[abc]`, `[^a-z]` and `\` to escape
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // the pattern right after the last `*` and how much of the string that star absorbed
+    let mut star = None;
+    while s < string.len() {
+        if p < pattern.len() {
+            if pattern[p] == b'*' {
+                p += 1;
+                star = Some((p, s));
+                continue;
+            }
+            if let Some(len) = match_one(&pattern[p..], string[s]) {
+                p += len;
+                s += 1;
+                continue;
+            }
+        }
+        // mismatch: let the last star absorb one more byte and retry from there
+        match star {
+            Some((after, absorbed)) => {
+                p = after;
+                s = absorbed + 1;
+                star = Some((after, absorbed + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+// the length of the pattern token at the start of `pattern` if it matches `c`
+fn match_one(pattern: &[u8], c: u8) -> Option<usize> {
+    match pattern[0] {
+        b'?' => Some(1),
+        b'\\' if pattern.len() > 1 => (pattern[1] == c).then_some(2),
+        b'[' => match_class(pattern, c),
+        other => (other == c).then_some(1),
+    }
+}
+
+// a `[...]` class, an unterminated class runs to the end of the pattern like in redis
+fn match_class(pattern: &[u8], c: u8) -> Option<usize> {
+    let mut i = 1;
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != b']' {
+        if pattern[i] == b'\\' && i + 1 < pattern.len() {
+            matched |= pattern[i + 1] == c;
+            i += 2;
+        } else if i + 2 < pattern.len() && pattern[i + 1] == b'-' {
+            let (start, end) = (pattern[i], pattern[i + 2]);
+            let (start, end) = if start > end {
+                (end, start)
+            } else {
+                (start, end)
+            };
+            matched |= (start..=end).contains(&c);
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    let len = (i + 1).min(pattern.len());
+    (matched != negate).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h*llo", b"heeeello"));
+        assert!(!glob_match(b"h*llo", b"hellx"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo"));
+        assert!(glob_match(b"h[z-a]llo", b"hbllo"));
+        assert!(glob_match(b"user:*:name", b"user:42:name"));
+        assert!(glob_match(br"h\*llo", b"h*llo"));
+        assert!(!glob_match(br"h\*llo", b"hello"));
+        assert!(glob_match(b"*a*b*", b"xxaxxbxx"));
+        assert!(!glob_match(b"*a*b", b"xxaxxbxx"));
+    }
+
+    #[test]
+    fn test_scan_survives_resize() {
+        let db = Database::new();
+        for i in 0..100 {
+            db.set(format!("key:{}", i).into_bytes(), b"v".into());
+        }
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut steps = 0;
+        loop {
+            let (next, keys) = db.scan(cursor, 10, None, None);
+            // whole buckets are returned, so a step may go a little over COUNT like in redis
+            assert!(keys.len() < 20);
+            seen.extend(keys);
+            // grow the table in the middle of the iteration
+            if steps == 2 {
+                for i in 0..2_000 {
+                    db.set(format!("new:{}", i).into_bytes(), b"v".into());
+                }
+            }
+            steps += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        for i in 0..100 {
+            assert!(seen.contains(format!("key:{}", i).as_bytes()));
+        }
+
+        db.hset(b"h".to_vec(), b"f".to_vec(), b"v".into()).unwrap();
+        let (cursor, keys) = db.scan(0, 100_000, Some(b"*"), Some("hash"));
+        assert_eq!((cursor, keys), (0, vec![b"h".to_vec()]));
+        assert_eq!(db.keys(b"key:9?").len(), 10);
+    }
+
+    #[test]
+    fn test_sscan_steps_are_bounded() {
+        let db = Database::new();
+        let members = (0..10_000)
+            .map(|i| format!("m:{}", i).into_bytes())
+            .collect();
+        db.sadd_multi(b"s".to_vec(), members).unwrap();
+        let (cursor, page) = db.sscan(b"s", 0, 10, None).unwrap();
+        assert_ne!(cursor, 0);
+        assert!((10..20).contains(&page.len()));
+
+        // removing shrinks the table, a step still stops after the buckets it was asked for
+        let removed = (10..10_000)
+            .map(|i| format!("m:{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        db.srem(b"s".to_vec(), &removed).unwrap();
+        let (cursor, page) = db.sscan(b"s", 0, 1, None).unwrap();
+        assert_ne!(cursor, 0);
+        assert!(page.len() < 10);
+
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, page) = db.sscan(b"s", cursor, 3, None).unwrap();
+            seen.extend(page);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 10);
+    }
+
+    #[test]
+    fn test_sscan_survives_churn() {
+        let db = Database::new();
+        let kept = (0..100)
+            .map(|i| format!("kept:{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        db.sadd_multi(b"s".to_vec(), kept.clone()).unwrap();
+        let mut seen = HashSet::new();
+        let mut added = Vec::new();
+        let mut cursor = 0;
+        let mut step = 0;
+        loop {
+            let (next, page) = db.sscan(b"s", cursor, 10, None).unwrap();
+            seen.extend(page);
+            // members come and go between steps, so the table grows and later shrinks underneath
+            // a cursor that has moved well into it
+            if step % 20 == 0 {
+                added = (0..1_000)
+                    .map(|i| format!("tmp:{}:{}", step, i).into_bytes())
+                    .collect();
+                db.sadd_multi(b"s".to_vec(), added.clone()).unwrap();
+            } else if step % 20 == 19 {
+                db.srem(b"s".to_vec(), &added).unwrap();
+            }
+            step += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert!(kept.iter().all(|member| seen.contains(member)));
+    }
+}
@@ -0,0 +1,407 @@
+use crate::database::keyspace::Entry;
+use crate::database::random::sample;
+use crate::database::{Database, Key, Members, Value, WrongType};
+
+impl Database {
+    // returns true if the member was not in the set yet
+    pub fn sadd(&self, key: Key, member: Vec<u8>) -> Result<bool, WrongType> {
+        self.sadd_multi(key, vec![member]).map(|added| added == 1)
+    }
+
+    // returns how many members were not in the set yet
+    pub fn sadd_multi(&self, key: Key, members: Vec<Vec<u8>>) -> Result<usize, WrongType> {
+        let added = self.update_set(key, true, |set| {
+            Ok(members
+                .into_iter()
+                .filter(|member| set.insert(member.clone()))
+                .count())
+        })?;
+        Ok(added.unwrap_or_default())
+    }
+
+    // returns how many members were removed, the key goes away with its last member
+    pub fn srem(&self, key: Key, members: &[Vec<u8>]) -> Result<usize, WrongType> {
+        let removed = self.update_set(key, false, |set| {
+            Ok(members.iter().filter(|member| set.remove(*member)).count())
+        })?;
+        Ok(removed.unwrap_or_default())
+    }
+
+    pub fn scard(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Set(set)) => Ok(set.len()),
+            Some(_) => Err(WrongType),
+            None => Ok(0),
+        }
+    }
+
+    // one answer per member, a missing key holds no member
+    pub fn smismember(&self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<bool>, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Set(set)) => Ok(members.iter().map(|m| set.contains(m)).collect()),
+            Some(_) => Err(WrongType),
+            None => Ok(vec![false; members.len()]),
+        }
+    }
+
+    pub fn sall(&self, key: &[u8]) -> Result<Option<Members>, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Set(set)) => Ok(Some(set.clone())),
+            Some(_) => Err(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // remove and return up to `count` distinct random members
+    pub fn spop(&self, key: Key, count: usize) -> Result<Vec<Vec<u8>>, WrongType> {
+        let popped = self.update_set(key, false, |set| {
+            let popped = sample(set.iter(), count as i64)
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>();
+            for member in &popped {
+                set.remove(member);
+            }
+            Ok(popped)
+        })?;
+        Ok(popped.unwrap_or_default())
+    }
+
+    // random members without removing them, see `sample` for the meaning of `count`
+    pub fn srandmember(&self, key: &[u8], count: i64) -> Result<Vec<Vec<u8>>, WrongType> {
+        let members = self.with_set(key, |set| {
+            sample(set.iter(), count).into_iter().cloned().collect()
+        })?;
+        Ok(members.unwrap_or_default())
+    }
+
+    // move `member` from `source` to `destination`, false when it is not in `source`. Both keys
+    // must hold sets, a missing destination is created
+    pub fn smove(
+        &self,
+        source: &[u8],
+        destination: Key,
+        member: Vec<u8>,
+    ) -> Result<bool, WrongType> {
+        let source = source.to_vec();
+        self.expire_if_needed(&source);
+        self.expire_if_needed(&destination);
+        // both keys stay locked from the type checks to the move, so the member is never seen in
+        // both sets or in neither
+        let mut locked = self.db.keyspace.lock([&source, &destination]);
+        if !matches!(
+            locked.table(&destination).get(&destination),
+            None | Some(Value::Set(_))
+        ) {
+            return Err(WrongType);
+        }
+        let table = locked.table(&source);
+        let removed = match table.get_mut(&source) {
+            Some(Value::Set(set)) if source == destination => return Ok(set.contains(&member)),
+            Some(Value::Set(set)) => set.remove(&member),
+            Some(_) => return Err(WrongType),
+            None => false,
+        };
+        if !removed {
+            return Ok(false);
+        }
+        // the source goes away with its last member
+        if matches!(table.get(&source), Some(Value::Set(set)) if set.is_empty()) {
+            self.db.expires.remove(&source);
+            table.remove(&source);
+        }
+        let table = locked.table(&destination);
+        match table.get_mut(&destination) {
+            Some(Value::Set(set)) => {
+                set.insert(member);
+            }
+            _ => {
+                table.insert(destination, Value::Set(Members::from_iter([member])));
+            }
+        }
+        Ok(true)
+    }
+
+    // the members in any of the sets, missing keys count as empty sets
+    pub fn sunion(&self, keys: &[Key]) -> Result<Members, WrongType> {
+        let mut union = Members::default();
+        for key in keys {
+            self.with_set(key, |set| union.extend(set.iter().cloned()))?;
+        }
+        Ok(union)
+    }
+
+    // the members in every set
+    pub fn sinter(&self, keys: &[Key]) -> Result<Members, WrongType> {
+        let inter = self.with_sets(keys, |smallest, others| {
+            smallest
+                .iter()
+                .filter(|member| others.iter().all(|set| set.contains(*member)))
+                .cloned()
+                .collect()
+        })?;
+        Ok(inter.unwrap_or_default())
+    }
+
+    // how many members are in every set, counting stops at `limit` when it is not 0
+    pub fn sintercard(&self, keys: &[Key], limit: usize) -> Result<usize, WrongType> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let count = self.with_sets(keys, |smallest, others| {
+            smallest
+                .iter()
+                .filter(|member| others.iter().all(|set| set.contains(*member)))
+                .take(limit)
+                .count()
+        })?;
+        Ok(count.unwrap_or_default())
+    }
+
+    // the members of the first set that are in none of the others
+    pub fn sdiff(&self, keys: &[Key]) -> Result<Members, WrongType> {
+        let Some((first, others)) = keys.split_first() else {
+            return Ok(Members::default());
+        };
+        let mut diff = self.sall(first)?.unwrap_or_default();
+        for key in others {
+            self.with_set(key, |set| diff.retain(|member| !set.contains(member)))?;
+        }
+        Ok(diff)
+    }
+
+    // store the result of a set operation, replacing whatever `destination` held and its ttl; an
+    // empty result deletes it. Returns the size of the stored set
+    pub fn sstore(&self, destination: Key, set: Members) -> usize {
+        let len = set.len();
+        if len == 0 {
+            self.unlink(&destination);
+        } else {
+            self.install(destination, Value::Set(set), None);
+        }
+        len
+    }
+
+    // run `f` on the set under a read guard, None when the key is missing. Only one guard is
+    // held at a time, so operations over several keys can't deadlock on their shards
+    fn with_set<T>(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&Members) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        self.expire_if_needed(key);
+        match self.db.keyspace.get(key).as_deref() {
+            Some(Value::Set(set)) => Ok(Some(f(set))),
+            Some(_) => Err(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // run `f` on the smallest of the sets and the others, with the shards of all the keys locked
+    // so they are seen at one point in time. Every key is type checked before a missing one makes
+    // it None
+    fn with_sets<T>(
+        &self,
+        keys: &[Key],
+        f: impl FnOnce(&Members, &[&Members]) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        for key in keys {
+            self.expire_if_needed(key);
+        }
+        let locked = self.db.keyspace.lock(keys);
+        let mut sets = Vec::with_capacity(keys.len());
+        let mut missing = false;
+        for key in keys {
+            match locked.get(key) {
+                Some(Value::Set(set)) => sets.push(set),
+                Some(_) => return Err(WrongType),
+                None => missing = true,
+            }
+        }
+        // the smallest set bounds the result, so it's the one walked and the others only probed
+        sets.sort_by_key(|set| set.len());
+        match sets.split_first() {
+            Some((smallest, others)) if !missing => Ok(Some(f(smallest, others))),
+            _ => Ok(None),
+        }
+    }
+
+    // run `f` on the set under the shard lock and drop the key once it is empty, so no command can
+    // observe an empty set. None when the key is missing and `create` is not set
+    fn update_set<T>(
+        &self,
+        key: Key,
+        create: bool,
+        f: impl FnOnce(&mut Members) -> Result<T, WrongType>,
+    ) -> Result<Option<T>, WrongType> {
+        self.expire_if_needed(&key);
+        let ret = match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let Value::Set(set) = entry.get_mut() else {
+                    return Err(WrongType);
+                };
+                let ret = f(set)?;
+                if set.is_empty() {
+                    self.db.expires.remove(entry.key());
+                    entry.remove();
+                }
+                ret
+            }
+            Entry::Vacant(entry) if create => {
+                let mut set = Members::default();
+                let ret = f(&mut set)?;
+                if !set.is_empty() {
+                    entry.insert(Value::Set(set));
+                }
+                ret
+            }
+            Entry::Vacant(_) => return Ok(None),
+        };
+        Ok(Some(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    fn members(items: &[&str]) -> Vec<Vec<u8>> {
+        items.iter().map(|m| m.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_sadd_srem() -> Result<()> {
+        let db = Database::new();
+        assert_eq!(db.sadd_multi(b"s".to_vec(), members(&["a", "b", "a"]))?, 2);
+        assert_eq!(db.sadd_multi(b"s".to_vec(), members(&["b", "c"]))?, 1);
+        assert_eq!(db.scard(b"s")?, 3);
+        assert_eq!(
+            db.smismember(b"s", &members(&["a", "x"]))?,
+            vec![true, false]
+        );
+        assert_eq!(db.smismember(b"missing", &members(&["a"]))?, vec![false]);
+
+        db.set_expire(b"s", crate::database::now_ms() + 60_000);
+        assert_eq!(db.srem(b"s".to_vec(), &members(&["a", "b", "x"]))?, 2);
+        assert_eq!(db.srem(b"s".to_vec(), &members(&["c"]))?, 1);
+        // the last member takes the key and its ttl with it
+        assert!(!db.exists(b"s"));
+        assert_eq!(db.expire_time(b"s"), None);
+
+        db.set(b"str".to_vec(), b"v".into());
+        assert_eq!(db.sadd(b"str".to_vec(), b"a".to_vec()), Err(WrongType));
+        assert_eq!(db.scard(b"str"), Err(WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember() -> Result<()> {
+        let db = Database::new();
+        db.sadd_multi(b"s".to_vec(), members(&["a", "b", "c"]))?;
+        assert_eq!(db.srandmember(b"s", -5)?.len(), 5);
+        assert_eq!(db.srandmember(b"s", 5)?.len(), 3);
+        assert_eq!(db.scard(b"s")?, 3);
+
+        let popped = db.spop(b"s".to_vec(), 2)?;
+        assert_eq!(popped.len(), 2);
+        assert_eq!(db.smismember(b"s", &popped)?, vec![false, false]);
+        assert_eq!(db.spop(b"s".to_vec(), 5)?.len(), 1);
+        assert!(!db.exists(b"s"));
+        assert!(db.spop(b"s".to_vec(), 1)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove() -> Result<()> {
+        let db = Database::new();
+        db.sadd_multi(b"src".to_vec(), members(&["a", "b"]))?;
+        assert!(db.smove(b"src", b"dst".to_vec(), b"a".to_vec())?);
+        assert!(!db.smove(b"src", b"dst".to_vec(), b"a".to_vec())?);
+        assert!(db.smove(b"src", b"dst".to_vec(), b"b".to_vec())?);
+        assert!(!db.exists(b"src"));
+        assert_eq!(db.scard(b"dst")?, 2);
+        assert!(db.smove(b"dst", b"dst".to_vec(), b"a".to_vec())?);
+
+        // a destination of another type fails even when there is nothing to move
+        db.set(b"str".to_vec(), b"v".into());
+        assert_eq!(
+            db.smove(b"missing", b"str".to_vec(), b"a".to_vec()),
+            Err(WrongType)
+        );
+        assert_eq!(
+            db.smove(b"dst", b"str".to_vec(), b"a".to_vec()),
+            Err(WrongType)
+        );
+        assert_eq!(db.scard(b"dst")?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_is_atomic() -> Result<()> {
+        let db = Database::new();
+        db.sadd(b"a".to_vec(), b"m".to_vec())?;
+        let (a, b) = (b"a".to_vec(), b"b".to_vec());
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..2_000 {
+                    let _ = db.smove(&a, b.clone(), b"m".to_vec());
+                    let _ = db.smove(&b, a.clone(), b"m".to_vec());
+                }
+            });
+            // with both keys locked the member is always in exactly one of the sets
+            for _ in 0..2_000 {
+                let mut locked = db.db.keyspace.lock([&a, &b]);
+                let holders = [&a, &b]
+                    .into_iter()
+                    .filter(|key| {
+                        matches!(locked.table(key).get(*key), Some(Value::Set(set)) if set.contains(b"m".as_slice()))
+                    })
+                    .count();
+                assert_eq!(holders, 1);
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra() -> Result<()> {
+        let db = Database::new();
+        db.sadd_multi(b"a".to_vec(), members(&["1", "2", "3", "4"]))?;
+        db.sadd_multi(b"b".to_vec(), members(&["2", "3", "5"]))?;
+        db.sadd_multi(b"c".to_vec(), members(&["3", "2", "6"]))?;
+        let keys = |keys: &[&str]| members(keys);
+        let set = |items: &[&str]| members(items).into_iter().collect::<Members>();
+
+        assert_eq!(
+            db.sunion(&keys(&["b", "c", "missing"]))?,
+            set(&["2", "3", "5", "6"])
+        );
+        assert_eq!(db.sinter(&keys(&["a", "b", "c"]))?, set(&["2", "3"]));
+        assert_eq!(db.sintercard(&keys(&["a", "b", "c"]), 0)?, 2);
+        assert_eq!(db.sintercard(&keys(&["a", "b", "c"]), 1)?, 1);
+        assert!(db.sinter(&keys(&["a", "missing"]))?.is_empty());
+        assert_eq!(db.sdiff(&keys(&["a", "b", "missing"]))?, set(&["1", "4"]));
+        assert!(db.sdiff(&keys(&["missing", "a"]))?.is_empty());
+
+        // a wrong type fails even when a missing key already decided the result
+        db.set(b"str".to_vec(), b"v".into());
+        assert_eq!(db.sinter(&keys(&["missing", "str"])), Err(WrongType));
+        assert_eq!(db.sunion(&keys(&["a", "str"])), Err(WrongType));
+        assert_eq!(db.sdiff(&keys(&["missing", "str"])), Err(WrongType));
+
+        // the destination may be a source, and loses its ttl
+        db.set_expire(b"a", crate::database::now_ms() + 60_000);
+        let inter = db.sinter(&keys(&["a", "b"]))?;
+        assert_eq!(db.sstore(b"a".to_vec(), inter), 2);
+        assert_eq!(db.sall(b"a")?, Some(set(&["2", "3"])));
+        assert_eq!(db.expire_time(b"a"), None);
+        assert_eq!(db.sstore(b"str".to_vec(), set(&["x"])), 1);
+        assert_eq!(db.key_type(b"str"), Some("set"));
+        assert_eq!(db.sstore(b"str".to_vec(), Members::default()), 0);
+        assert!(!db.exists(b"str"));
+        Ok(())
+    }
+}
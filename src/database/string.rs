@@ -0,0 +1,386 @@
+use bytes::Bytes;
+
+use crate::database::keyspace::Entry;
+use crate::database::value::frame_to_bytes;
+use crate::database::{now_ms, Database, Key, NumericError, Value, WrongType};
+use crate::resp::{RespFrame, TBulkString};
+
+impl Database {
+    // returns the length of the string after the append, a missing key starts out empty
+    pub fn append(&self, key: Key, value: &[u8]) -> Result<usize, WrongType> {
+        let len = self.update_string(key, true, |data, _| {
+            data.extend_from_slice(value);
+            Ok::<_, WrongType>(data.len())
+        })?;
+        Ok(len.unwrap_or_default())
+    }
+
+    // overwrite part of the string, zero-padding it up to `offset` first; returns the new length
+    pub fn setrange(&self, key: Key, offset: usize, value: &[u8]) -> Result<usize, WrongType> {
+        // an empty value only reports the length and never creates the key
+        let len = self.update_string(key, !value.is_empty(), |data, _| {
+            let end = offset + value.len();
+            if !value.is_empty() {
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[offset..end].copy_from_slice(value);
+            }
+            Ok::<_, WrongType>(data.len())
+        })?;
+        Ok(len.unwrap_or_default())
+    }
+
+    // set the string and return the old one, the ttl is discarded like SET does
+    pub fn getset(&self, key: Key, value: RespFrame) -> Result<Option<RespFrame>, WrongType> {
+        self.expire_if_needed(&key);
+        match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let Value::String(old) = entry.get() else {
+                    return Err(WrongType);
+                };
+                let old = old.clone();
+                self.db.expires.remove(entry.key());
+                entry.insert(Value::String(value));
+                Ok(Some(old))
+            }
+            Entry::Vacant(entry) => {
+                self.db.expires.remove(entry.key());
+                entry.insert(Value::String(value));
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn getdel(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.expire_if_needed(key);
+        let removed = self.db.keyspace.remove_if(key, |key, value| {
+            let string = matches!(value, Value::String(_));
+            if string {
+                self.db.expires.remove(key);
+            }
+            string
+        });
+        match removed {
+            Some((_, Value::String(value))) => Ok(Some(value)),
+            _ if self.db.keyspace.contains_key(key) => Err(WrongType),
+            _ => Ok(None),
+        }
+    }
+
+    // add to the integer stored at the key, a missing key counts as 0
+    pub fn incr_by(&self, key: Key, delta: i64) -> Result<i64, NumericError> {
+        let value = self.update_string::<_, NumericError>(key, true, |data, exists| {
+            let current = if !exists {
+                0
+            } else {
+                parse_i64(data).ok_or(NumericError::NotInteger)?
+            };
+            let value = current.checked_add(delta).ok_or(NumericError::Overflow)?;
+            *data = value.to_string().into_bytes();
+            Ok(value)
+        })?;
+        Ok(value.unwrap_or_default())
+    }
+
+    // like `incr_by` for floats, the result is stored in its shortest textual form
+    pub fn incr_by_float(&self, key: Key, delta: f64) -> Result<f64, NumericError> {
+        let value = self.update_string::<_, NumericError>(key, true, |data, exists| {
+            let current = if !exists {
+                0.0
+            } else {
+                parse_f64(data).ok_or(NumericError::NotFloat)?
+            };
+            let value = current + delta;
+            if !value.is_finite() {
+                return Err(NumericError::NotFinite);
+            }
+            *data = value.to_string().into_bytes();
+            Ok(value)
+        })?;
+        Ok(value.unwrap_or_default())
+    }
+
+    // read the string and change its ttl in one step: a deadline already passed deletes the key,
+    // `persist` drops the ttl. None when the key is missing
+    pub fn getex(
+        &self,
+        key: &[u8],
+        deadline: Option<i64>,
+        persist: bool,
+    ) -> Result<Option<RespFrame>, WrongType> {
+        self.expire_if_needed(key);
+        let Entry::Occupied(entry) = self.db.keyspace.entry(key.to_vec()) else {
+            return Ok(None);
+        };
+        let Value::String(value) = entry.get() else {
+            return Err(WrongType);
+        };
+        let value = value.clone();
+        match deadline {
+            Some(deadline) if deadline <= now_ms() => {
+                self.db.expires.remove(key);
+                entry.remove();
+            }
+            Some(deadline) => {
+                self.db.expires.insert(key.to_vec(), deadline);
+            }
+            None if persist => {
+                self.db.expires.remove(key);
+            }
+            None => {}
+        }
+        Ok(Some(value))
+    }
+
+    // the pairs are written with the shards of all the keys locked, so a reader never sees some of
+    // them written and others not
+    pub fn mset(&self, pairs: Vec<(Key, RespFrame)>) {
+        self.set_pairs(pairs, false);
+    }
+
+    // set every pair only when none of the keys exists. The check and the writes happen with the
+    // shards of all the keys locked, so no writer can create one of them in between
+    pub fn msetnx(&self, pairs: Vec<(Key, RespFrame)>) -> bool {
+        self.set_pairs(pairs, true)
+    }
+
+    fn set_pairs(&self, pairs: Vec<(Key, RespFrame)>, nx: bool) -> bool {
+        let now = now_ms();
+        let mut locked = self.db.keyspace.lock(pairs.iter().map(|(key, _)| key));
+        // an expired key counts as missing, it is overwritten below
+        let live = |key: &[u8]| {
+            self.db
+                .expires
+                .get(key)
+                .is_none_or(|deadline| *deadline > now)
+        };
+        if nx
+            && pairs
+                .iter()
+                .any(|(key, _)| locked.table(key).contains_key(key) && live(key))
+        {
+            return false;
+        }
+        let mut replaced = Vec::new();
+        for (key, value) in pairs {
+            self.db.expires.remove(&key);
+            replaced.extend(locked.table(&key).insert(key, Value::String(value)));
+        }
+        drop(locked);
+        for value in replaced {
+            self.free_async(value);
+        }
+        true
+    }
+
+    // run `f` on the bytes of the string under the shard lock, so concurrent writers don't
+    // interleave; `f` is told whether the key existed, a failed `f` must leave the bytes untouched
+    // and never creates the key. None when the key is missing and `create` is not set
+    fn update_string<T, E: From<WrongType>>(
+        &self,
+        key: Key,
+        create: bool,
+        f: impl FnOnce(&mut Vec<u8>, bool) -> Result<T, E>,
+    ) -> Result<Option<T>, E> {
+        self.expire_if_needed(&key);
+        match self.db.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => match entry.get_mut() {
+                Value::String(frame) => {
+                    let mut data = take_bytes(frame);
+                    let ret = f(&mut data, true);
+                    *frame = TBulkString::new(data).into();
+                    ret.map(Some)
+                }
+                _ => Err(WrongType.into()),
+            },
+            Entry::Vacant(entry) if create => {
+                let mut data = Vec::new();
+                let ret = f(&mut data, false)?;
+                entry.insert(Value::String(TBulkString::new(data).into()));
+                Ok(Some(ret))
+            }
+            Entry::Vacant(_) => Ok(None),
+        }
+    }
+}
+
+// the bulk string buffer is reused when nothing else shares it
+fn take_bytes(frame: &mut RespFrame) -> Vec<u8> {
+    match frame {
+        RespFrame::BulkString(s) => std::mem::take(&mut s.0).into(),
+        other => frame_to_bytes(other).into(),
+    }
+}
+
+// only the canonical form counts as an integer, so "01", "+1" or " 1" are rejected like in redis
+pub(super) fn parse_i64(data: &[u8]) -> Option<i64> {
+    let s = std::str::from_utf8(data).ok()?;
+    s.parse::<i64>().ok().filter(|n| n.to_string() == s)
+}
+
+pub(super) fn parse_f64(data: &[u8]) -> Option<f64> {
+    let s = std::str::from_utf8(data).ok()?;
+    s.parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+// the bytes of a string value, cheap for bulk strings which share their buffer
+pub(crate) fn string_bytes(frame: RespFrame) -> Bytes {
+    match frame {
+        RespFrame::BulkString(s) => s.0,
+        other => frame_to_bytes(&other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_append_setrange() -> Result<()> {
+        let db = Database::new();
+        assert_eq!(db.append(b"k".to_vec(), b"hello")?, 5);
+        assert_eq!(db.append(b"k".to_vec(), b" world")?, 11);
+        assert_eq!(db.get(b"k")?, Some(b"hello world".into()));
+
+        assert_eq!(db.setrange(b"k".to_vec(), 6, b"redis")?, 11);
+        assert_eq!(db.get(b"k")?, Some(b"hello redis".into()));
+
+        // the gap is zero-padded
+        assert_eq!(db.setrange(b"p".to_vec(), 3, b"x")?, 4);
+        assert_eq!(db.get(b"p")?, Some(b"\0\0\0x".into()));
+        assert_eq!(db.setrange(b"missing".to_vec(), 10, b"")?, 0);
+        assert!(!db.exists(b"missing"));
+
+        // scalar frames are edited in their textual form
+        db.set(b"n".to_vec(), RespFrame::Integer(12));
+        assert_eq!(db.append(b"n".to_vec(), b"3")?, 3);
+        assert_eq!(db.get(b"n")?, Some(b"123".into()));
+
+        db.sadd(b"s".to_vec(), b"m".to_vec())?;
+        assert_eq!(db.append(b"s".to_vec(), b"x"), Err(WrongType));
+        assert_eq!(db.setrange(b"s".to_vec(), 0, b"x"), Err(WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_getset_getdel_msetnx() -> Result<()> {
+        let db = Database::new();
+        db.set_with_expire(
+            b"k".to_vec(),
+            b"1".into(),
+            crate::database::now_ms() + 60_000,
+        );
+        assert_eq!(db.getset(b"k".to_vec(), b"2".into())?, Some(b"1".into()));
+        assert_eq!(db.expire_time(b"k"), None);
+        assert_eq!(db.getset(b"new".to_vec(), b"3".into())?, None);
+
+        assert_eq!(db.getdel(b"k")?, Some(b"2".into()));
+        assert!(!db.exists(b"k"));
+        assert_eq!(db.getdel(b"k")?, None);
+        db.sadd(b"s".to_vec(), b"m".to_vec())?;
+        assert_eq!(db.getdel(b"s"), Err(WrongType));
+        assert!(db.exists(b"s"));
+
+        let pairs = |keys: &[&str]| {
+            keys.iter()
+                .map(|k| (k.as_bytes().to_vec(), RespFrame::from(b"v")))
+                .collect::<Vec<_>>()
+        };
+        assert!(!db.msetnx(pairs(&["a", "new"])));
+        assert!(!db.exists(b"a"));
+        assert!(db.msetnx(pairs(&["a", "b"])));
+        assert_eq!(db.get(b"b")?, Some(b"v".into()));
+
+        // an expired key counts as missing and loses its ttl when overwritten
+        db.set_with_expire(b"gone".to_vec(), b"1".into(), now_ms() - 1);
+        assert!(db.msetnx(pairs(&["gone", "c"])));
+        assert_eq!(db.get(b"gone")?, Some(b"v".into()));
+        assert_eq!(db.expire_time(b"gone"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_msetnx_is_atomic() {
+        let db = Database::new();
+        // every call shares "b" with the others, only one of them may set its keys
+        let winners = std::thread::scope(|scope| {
+            let handles = ["a", "c", "d", "e"]
+                .map(|key| {
+                    let db = db.clone();
+                    scope.spawn(move || {
+                        let pairs = vec![
+                            (key.as_bytes().to_vec(), RespFrame::from(b"v")),
+                            (b"b".to_vec(), RespFrame::from(key.as_bytes())),
+                        ];
+                        db.msetnx(pairs)
+                    })
+                })
+                .into_iter()
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .filter(|won| *won)
+                .count()
+        });
+        assert_eq!(winners, 1);
+        assert_eq!(db.dbsize(), 2);
+    }
+
+    #[test]
+    fn test_msetnx_races_set_nx() {
+        let db = Database::new();
+        // a plain SET NX racing an MSETNX on the same key: exactly one of them may create it
+        for i in 0..200 {
+            let key = format!("k:{}", i).into_bytes();
+            let (multi, single) = std::thread::scope(|scope| {
+                let multi = scope.spawn(|| {
+                    let other = format!("other:{}", i).into_bytes();
+                    db.msetnx(vec![
+                        (key.clone(), RespFrame::from(b"multi")),
+                        (other, RespFrame::from(b"multi")),
+                    ])
+                });
+                let single = scope.spawn(|| {
+                    db.set_with_options(
+                        key.clone(),
+                        b"single".into(),
+                        None,
+                        false,
+                        Some(false),
+                        false,
+                    )
+                });
+                (multi.join(), single.join())
+            });
+            let (Ok(multi), Ok(Ok((single, _)))) = (multi, single) else {
+                panic!("a writer failed");
+            };
+            assert_ne!(multi, single);
+        }
+    }
+
+    #[test]
+    fn test_getex() -> Result<()> {
+        let db = Database::new();
+        assert_eq!(db.getex(b"k", None, true)?, None);
+        db.set(b"k".to_vec(), b"v".into());
+        let deadline = crate::database::now_ms() + 60_000;
+        assert_eq!(db.getex(b"k", Some(deadline), false)?, Some(b"v".into()));
+        assert_eq!(db.expire_time(b"k"), Some(deadline));
+        assert_eq!(db.getex(b"k", None, false)?, Some(b"v".into()));
+        assert_eq!(db.expire_time(b"k"), Some(deadline));
+        assert_eq!(db.getex(b"k", None, true)?, Some(b"v".into()));
+        assert_eq!(db.expire_time(b"k"), None);
+        // a deadline in the past still returns the value, then the key is gone
+        assert_eq!(db.getex(b"k", Some(1), false)?, Some(b"v".into()));
+        assert!(!db.exists(b"k"));
+
+        db.sadd(b"s".to_vec(), b"m".to_vec())?;
+        assert_eq!(db.getex(b"s", None, true), Err(WrongType));
+        Ok(())
+    }
+}
@@ -0,0 +1,567 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, RandomState};
+
+// the smallest table that holds anything
+const MIN_BUCKETS: usize = 4;
+// a table shrinks once it has this many buckets per element, like the dict of redis
+const SHRINK_RATIO: usize = 8;
+// a scan step gives up after this many empty buckets per element asked for, like redis does
+const SCAN_EMPTY_VISITS: usize = 10;
+
+/*
+- a chained hash table like the dict of redis: an element always sits in the bucket picked by the
+  low bits of its hash, so SCAN can walk the buckets with a reverse binary cursor. Growing or
+  shrinking the table only splits or merges buckets the cursor has not passed yet, so an element
+  that stays in the table for a whole iteration is always returned
+- the hash of every element is kept next to it so resizing never hashes a key again
+*/
+#[derive(Clone)]
+pub struct Table<K, V> {
+    buckets: Vec<Vec<Slot<K, V>>>,
+    len: usize,
+    hasher: RandomState,
+}
+
+#[derive(Clone)]
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+// where an element sits: its bucket and its index in the bucket
+pub(super) type Position = (usize, usize);
+
+impl<K: Hash + Eq, V> Table<K, V> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    // tables sharing a hasher agree on the hash of every key
+    pub(super) fn with_hasher(hasher: RandomState) -> Self {
+        Table {
+            buckets: Vec::new(),
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(super) fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let position = self.find(self.hash(key), key)?;
+        Some(&self.slot(position).value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let position = self.find(self.hash(key), key)?;
+        Some(&mut self.slot_mut(position).value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(self.hash(key), key).is_some()
+    }
+
+    // returns the value the key held before
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let position = self.find(self.hash(key), key)?;
+        Some(self.remove_at(position).1)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let hash = self.hash(&key);
+        match self.find(hash, &key) {
+            Some(position) => Entry::Occupied(OccupiedEntry {
+                table: self,
+                position,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                table: self,
+                hash,
+                key,
+            }),
+        }
+    }
+
+    // keep the elements `keep` returns true for
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &mut V) -> bool) {
+        for bucket in &mut self.buckets {
+            bucket.retain_mut(|slot| keep(&slot.key, &mut slot.value));
+        }
+        self.len = self.buckets.iter().map(Vec::len).sum();
+        self.shrink_if_sparse();
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets = Vec::new();
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            bucket: [].iter(),
+            remaining: self.len,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    // visit the buckets from `cursor` on until the budget runs out, returns the cursor to continue
+    // from or 0 once every bucket was visited
+    pub(crate) fn scan(
+        &self,
+        mut cursor: u64,
+        budget: &mut ScanBudget,
+        mut visit: impl FnMut(&K, &V),
+    ) -> u64 {
+        if self.buckets.is_empty() {
+            return 0;
+        }
+        let mask = (self.buckets.len() - 1) as u64;
+        while !budget.spent() {
+            let bucket = &self.buckets[(cursor & mask) as usize];
+            match bucket.len() {
+                0 => budget.empty -= 1,
+                len => budget.elements = budget.elements.saturating_sub(len),
+            }
+            for slot in bucket {
+                visit(&slot.key, &slot.value);
+            }
+            // count in reverse binary: set the bits above the mask so the increment carries
+            // through them, it wraps to 0 after the last bucket
+            cursor = (cursor | !mask)
+                .reverse_bits()
+                .wrapping_add(1)
+                .reverse_bits();
+            if cursor == 0 {
+                break;
+            }
+        }
+        cursor
+    }
+
+    pub(super) fn find<Q>(&self, hash: u64, key: &Q) -> Option<Position>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket = self.bucket_of(hash);
+        let index = self.buckets[bucket]
+            .iter()
+            .position(|slot| slot.hash == hash && slot.key.borrow() == key)?;
+        Some((bucket, index))
+    }
+
+    pub(super) fn key_at(&self, position: Position) -> &K {
+        &self.slot(position).key
+    }
+
+    pub(super) fn value_at(&self, position: Position) -> &V {
+        &self.slot(position).value
+    }
+
+    pub(super) fn value_at_mut(&mut self, position: Position) -> &mut V {
+        &mut self.slot_mut(position).value
+    }
+
+    // add a key that is not in the table yet
+    pub(super) fn insert_new(&mut self, hash: u64, key: K, value: V) -> Position {
+        if self.len >= self.buckets.len() {
+            self.resize((self.buckets.len() * 2).max(MIN_BUCKETS));
+        }
+        let bucket = self.bucket_of(hash);
+        self.buckets[bucket].push(Slot { hash, key, value });
+        self.len += 1;
+        (bucket, self.buckets[bucket].len() - 1)
+    }
+
+    pub(super) fn remove_at(&mut self, (bucket, index): Position) -> (K, V) {
+        let slot = self.buckets[bucket].swap_remove(index);
+        self.len -= 1;
+        self.shrink_if_sparse();
+        (slot.key, slot.value)
+    }
+
+    fn slot(&self, (bucket, index): Position) -> &Slot<K, V> {
+        &self.buckets[bucket][index]
+    }
+
+    fn slot_mut(&mut self, (bucket, index): Position) -> &mut Slot<K, V> {
+        &mut self.buckets[bucket][index]
+    }
+
+    fn bucket_of(&self, hash: u64) -> usize {
+        hash as usize & (self.buckets.len() - 1)
+    }
+
+    fn shrink_if_sparse(&mut self) {
+        if self.len == 0 {
+            self.buckets = Vec::new();
+        } else if self.buckets.len() > MIN_BUCKETS && self.len * SHRINK_RATIO < self.buckets.len() {
+            self.resize(self.len.next_power_of_two().max(MIN_BUCKETS));
+        }
+    }
+
+    fn resize(&mut self, size: usize) {
+        let old = std::mem::replace(&mut self.buckets, Vec::with_capacity(size));
+        self.buckets.resize_with(size, Vec::new);
+        for slot in old.into_iter().flatten() {
+            let bucket = self.bucket_of(slot.hash);
+            self.buckets[bucket].push(slot);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Default for Table<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for Table<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|v| v == value))
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Table<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots = self.buckets.iter().flatten();
+        f.debug_map()
+            .entries(slots.map(|slot| (&slot.key, &slot.value)))
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for Table<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut table = Table::new();
+        table.extend(iter);
+        table
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for Table<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for Table<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            buckets: self.buckets.into_iter(),
+            bucket: Vec::new().into_iter(),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V> IntoIterator for &'a Table<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    buckets: std::slice::Iter<'a, Vec<Slot<K, V>>>,
+    bucket: std::slice::Iter<'a, Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.bucket.next() {
+                self.remaining -= 1;
+                return Some((&slot.key, &slot.value));
+            }
+            self.bucket = self.buckets.next()?.iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IntoIter<K, V> {
+    buckets: std::vec::IntoIter<Vec<Slot<K, V>>>,
+    bucket: std::vec::IntoIter<Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.bucket.next() {
+                self.remaining -= 1;
+                return Some((slot.key, slot.value));
+            }
+            self.bucket = self.buckets.next()?.into_iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    table: &'a mut Table<K, V>,
+    position: Position,
+}
+
+impl<K: Hash + Eq, V> OccupiedEntry<'_, K, V> {
+    pub fn key(&self) -> &K {
+        self.table.key_at(self.position)
+    }
+
+    pub fn get(&self) -> &V {
+        self.table.value_at(self.position)
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.table.value_at_mut(self.position)
+    }
+
+    // replace the value, returns the old one
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.table.remove_at(self.position).1
+    }
+}
+
+pub struct VacantEntry<'a, K, V> {
+    table: &'a mut Table<K, V>,
+    hash: u64,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let position = self.table.insert_new(self.hash, self.key, value);
+        self.table.value_at_mut(position)
+    }
+}
+
+// the work left in one scan step: `count` elements, or `count` times SCAN_EMPTY_VISITS empty
+// buckets so a sparse table cannot make a step walk all of it
+pub(crate) struct ScanBudget {
+    elements: usize,
+    empty: usize,
+}
+
+impl ScanBudget {
+    pub(crate) fn new(count: usize) -> Self {
+        let count = count.max(1);
+        ScanBudget {
+            elements: count,
+            empty: count.saturating_mul(SCAN_EMPTY_VISITS),
+        }
+    }
+
+    pub(crate) fn spent(&self) -> bool {
+        self.elements == 0 || self.empty == 0
+    }
+}
+
+// a set on top of the table, the members are the keys
+#[derive(Clone, Default, PartialEq)]
+pub struct TableSet<T: Hash + Eq>(Table<T, ()>);
+
+impl<T: Hash + Eq> TableSet<T> {
+    pub fn new() -> Self {
+        TableSet(Table::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // returns true if the member was not in the set yet
+    pub fn insert(&mut self, member: T) -> bool {
+        match self.0.entry(member) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(());
+                true
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, member: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.remove(member).is_some()
+    }
+
+    pub fn contains<Q>(&self, member: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.contains_key(member)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        self.0.retain(|member, _| keep(member));
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn iter(&self) -> SetIter<'_, T> {
+        SetIter(self.0.iter())
+    }
+
+    pub(crate) fn scan(
+        &self,
+        cursor: u64,
+        budget: &mut ScanBudget,
+        mut visit: impl FnMut(&T),
+    ) -> u64 {
+        self.0.scan(cursor, budget, |member, _| visit(member))
+    }
+}
+
+impl<T: Hash + Eq + fmt::Debug> fmt::Debug for TableSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for TableSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = TableSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Hash + Eq> Extend<T> for TableSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for member in iter {
+            self.insert(member);
+        }
+    }
+}
+
+impl<T: Hash + Eq> IntoIterator for TableSet<T> {
+    type Item = T;
+    type IntoIter = std::iter::Map<IntoIter<T, ()>, fn((T, ())) -> T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter().map(|(member, ())| member)
+    }
+}
+
+impl<'a, T: Hash + Eq> IntoIterator for &'a TableSet<T> {
+    type Item = &'a T;
+    type IntoIter = SetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct SetIter<'a, T>(Iter<'a, T, ()>);
+
+impl<'a, T> Iterator for SetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(member, _)| member)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for SetIter<'_, T> {}
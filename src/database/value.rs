@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::resp::{RespEncode, RespFrame};
+
+use super::table::{Table, TableSet};
+
+// keys, hash fields and set members are arbitrary bytes, like in redis
+pub type Key = Vec<u8>;
+
+// our own tables rather than std ones so SSCAN and HSCAN can walk their buckets with a cursor
+pub type Members = TableSet<Vec<u8>>;
+pub type Fields = Table<Vec<u8>, RespFrame>;
+
+// every key holds exactly one of these, so its type is decided by the keyspace alone
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(RespFrame),
+    Hash(HashValue),
+    Set(Members),
+}
+
+// the fields of a hash with the deadlines set by HEXPIRE and friends
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HashValue {
+    pub(crate) fields: Fields,
+    // field -> absolute deadline in unix milliseconds, only for fields that have one
+    pub(crate) expires: HashMap<Vec<u8>, i64>,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+pub struct WrongType;
+
+// failures of the commands that treat a string as a number
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericError {
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NotFinite,
+    #[error("ERR hash value is not an integer")]
+    HashNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashNotFloat,
+}
+
+impl Value {
+    // the name reported by TYPE
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+        }
+    }
+}
+
+// string commands and rdb only know byte strings, so scalar frames are taken in their textual form
+pub(crate) fn frame_to_bytes(frame: &RespFrame) -> Bytes {
+    match frame {
+        RespFrame::BulkString(s) => s.0.clone(),
+        RespFrame::SimpleString(s) => Bytes::copy_from_slice(s.as_bytes()),
+        RespFrame::Integer(i) => i.to_string().into(),
+        RespFrame::Double(f) => f.to_string().into(),
+        RespFrame::Boolean(b) => (*b as i64).to_string().into(),
+        other => other.clone().to_vec().into(),
+    }
+}
@@ -1,7 +1,12 @@
+pub use config::*;
 pub use database::*;
 pub use network::*;
+pub use resp::{RespDecoder, RespEncode, RespFrame};
 
 mod cmd;
+mod config;
 mod database;
 mod network;
 mod resp;
+#[cfg(test)]
+mod test_util;
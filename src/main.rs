@@ -1,20 +1,29 @@
 use tokio::net::TcpListener;
 use tracing::{info, warn};
 
-use simple_redis::{process_redis_conn, Database};
-
-mod resp;
+use simple_redis::{process_redis_conn, Config, Database};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
-    let addr = "0.0.0.0:6379";
-    let listener = TcpListener::bind(addr).await?;
+    let config = Config::from_args(std::env::args().skip(1))?;
+    let addr = config.addr();
+    let appendonly = config.appendonly;
+    let db = Database::with_config(config);
+    // the aof is the more complete record, so it wins over the snapshot like in redis
+    if appendonly {
+        db.load_aof()?;
+    } else {
+        db.load()?;
+    }
+    let listener = TcpListener::bind(&addr).await?;
     info!("Listening on: {}", addr);
-    let db = Database::new();
+    tokio::spawn(db.clone().run_active_expire());
     loop {
         let (stream, raddr) = listener.accept().await?;
         info!("Accepted connection from: {}", raddr);
+        // replies are already coalesced per pipeline, don't let nagle hold them back
+        stream.set_nodelay(true)?;
         let cloned_db = db.clone();
         tokio::spawn(async move {
             if let Err(e) = process_redis_conn(stream, cloned_db).await {
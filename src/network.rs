@@ -1,47 +1,157 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use anyhow::Result;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use futures::{FutureExt, SinkExt};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::cmd::{Command, CommandExecute};
+use crate::cmd::{lookup_command, propagate_reply, Command, CommandExecute, CommandFlag};
 use crate::database::Database;
-use crate::resp::RespDecode;
-use crate::resp::RespEncode;
-use crate::resp::RespError;
 use crate::resp::RespFrame;
+use crate::resp::{is_inline, RespDecoder, RespError, RespProtocol, TError};
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+// per-connection state, changed by HELLO and SELECT
+#[derive(Debug)]
+pub(crate) struct Session {
+    pub(crate) id: u64,
+    // index of the selected db
+    pub(crate) db: usize,
+    pub(crate) protocol: RespProtocol,
+    pub(crate) name: Option<Vec<u8>>,
+    pub(crate) authenticated: bool,
+}
+
+impl Session {
+    pub(crate) fn new(backend: &Database) -> Self {
+        Self {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            db: 0,
+            protocol: RespProtocol::default(),
+            name: None,
+            authenticated: backend.config.requirepass.is_none(),
+        }
+    }
+}
 
-pub async fn process_redis_conn(stream: TcpStream, database: Database) -> Result<()> {
-    let mut framed = Framed::new(stream, RespFrameCodec);
+pub async fn process_redis_conn<S>(stream: S, database: Database) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let codec = RespFrameCodec {
+        protocol: RespProtocol::default(),
+        decoder: RespDecoder::with_limits(database.config.resp_limits()),
+    };
+    let mut framed = Framed::new(stream, codec);
+    let mut session = Session::new(&database);
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let request = RedisRequest {
-                    frame,
-                    backend: database.clone(),
-                };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
-            }
-            Some(Err(e)) => return Err(e),
+        let frame = match framed.next().await {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => return reject_request(&mut framed, e).await,
             None => return Ok(()),
+        };
+        let mut next = Some(frame);
+        // execute every frame already buffered (a pipeline) and answer them with a single flush
+        while let Some(frame) = next {
+            info!("Received frame: {:?}", frame);
+            // resolved per request, so a SWAPDB is seen by the next command
+            let backend = database
+                .select(session.db)
+                .unwrap_or_else(|| database.clone());
+            let request = RedisRequest { frame, backend };
+            let response = request_handler(request, &mut session).await?;
+            info!("Sending response: {:?}", response.frame);
+            // the reply to HELLO already uses the protocol it negotiated
+            framed.codec_mut().protocol = session.protocol;
+            framed.feed(response.frame).await?;
+            // keep going while the next request is already buffered or readable without waiting
+            next = match framed.next().now_or_never().flatten() {
+                Some(Ok(frame)) => Some(frame),
+                Some(Err(e)) => return reject_request(&mut framed, e).await,
+                None => None,
+            };
         }
+        framed.flush().await?;
     }
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
+// like redis, a request that can't be parsed or breaks the protocol limits is answered with an
+// error and the connection is closed, since the rest of the stream can't be trusted
+async fn reject_request<S>(framed: &mut Framed<S, RespFrameCodec>, e: anyhow::Error) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Some(e) = e.downcast_ref::<RespError>() else {
+        return Err(e);
+    };
+    warn!("Protocol error: {}", e);
+    let frame = TError::new(format!("ERR Protocol error: {}", e)).into();
+    framed.send(frame).await?;
+    Ok(())
+}
+
+async fn request_handler(request: RedisRequest, session: &mut Session) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
+    if !session.authenticated && !allowed_without_auth(&frame) {
+        return Ok(RedisResponse {
+            frame: TError::new("NOAUTH Authentication required.").into(),
+        });
+    }
+    let request = match &frame {
+        RespFrame::Array(array) if backend.aof_enabled() => Some(array.clone()),
+        _ => None,
+    };
+    let mut cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            // a bad request only fails itself, the connection keeps being served
+            warn!("Invalid command: {}", e);
+            return Ok(RedisResponse { frame: e.into() });
+        }
+    };
+    let propagate = request.and_then(|request| cmd.propagate(request));
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
+    let execute = |session: &mut Session| match cmd {
+        Command::Hello(args) => args.negotiate(&backend, session),
+        Command::Select(args) => args.select(&backend, session),
+        cmd => cmd.execute(&backend),
+    };
+    let frame = match propagate {
+        Some(propagate) => backend.apply_logged(|| {
+            let frame = execute(session);
+            let logged = match frame {
+                RespFrame::Error(_) => None,
+                _ => propagate_reply(propagate, &frame),
+            };
+            (frame, logged)
+        })?,
+        None => execute(session),
+    };
     Ok(RedisResponse { frame })
 }
 
+// unknown commands are let through so they get their own error
+fn allowed_without_auth(frame: &RespFrame) -> bool {
+    let spec = match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(name)) => lookup_command(name),
+            _ => None,
+        },
+        _ => None,
+    };
+    spec.is_none_or(|spec| spec.has_flag(CommandFlag::NoAuth))
+}
+
 #[derive(Debug)]
-struct RespFrameCodec;
+struct RespFrameCodec {
+    // replies are downgraded for resp2 clients
+    protocol: RespProtocol,
+    // keeps partially received frames across reads
+    decoder: RespDecoder,
+}
 
 #[derive(Debug)]
 struct RedisRequest {
@@ -58,8 +168,7 @@ impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        let encoded = item.encode();
-        dst.extend_from_slice(&encoded);
+        item.encode_for(self.protocol, dst);
         Ok(())
     }
 }
@@ -69,10 +178,242 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotCompleteFrame) => Ok(None),
-            Err(e) => Err(e.into()),
+        while self.decoder.is_idle() && is_inline(src) {
+            match self.decoder.decode_inline(src)? {
+                // blank lines are ignored like in redis
+                Some(array) if array.is_empty() => continue,
+                Some(array) => return Ok(Some(array.into())),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.decoder.decode(src)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pipelined_responses_keep_order() -> Result<()> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, Database::new()));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        let mut request = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            let value = format!("v{}", i);
+            request.extend_from_slice(
+                format!(
+                    "*3\r\n$3\r\nset\r\n$1\r\nk\r\n${}\r\n{}\r\n",
+                    value.len(),
+                    value
+                )
+                .as_bytes(),
+            );
+            request.extend_from_slice(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n");
+            expected.extend_from_slice(b"+OK\r\n");
+            expected.extend_from_slice(format!("${}\r\n{}\r\n", value.len(), value).as_bytes());
+        }
+        // split the pipeline in the middle of a frame to exercise partial reads
+        let (first, second) = request.split_at(request.len() / 2 + 3);
+        writer.write_all(first).await?;
+        writer.write_all(second).await?;
+
+        let mut response = vec![0u8; expected.len()];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(
+            String::from_utf8_lossy(&response),
+            String::from_utf8_lossy(&expected)
+        );
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_command_keeps_connection() -> Result<()> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, Database::new()));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        writer.write_all(b"*1\r\n$3\r\nget\r\n").await?;
+        writer.write_all(b"*2\r\n$3\r\nget\r\n:1\r\n").await?;
+        writer
+            .write_all(b"*2\r\n$4\r\necho\r\n$2\r\nhi\r\n")
+            .await?;
+
+        let expected = b"-ERR wrong number of arguments for 'get' command\r\n";
+        let mut response = vec![0u8; expected.len()];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, expected);
+
+        let mut response = vec![0u8; 5];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"-ERR ");
+        let mut line = Vec::new();
+        while !line.ends_with(b"\r\n") {
+            line.push(reader.read_u8().await?);
         }
+
+        let mut response = vec![0u8; 8];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"$2\r\nhi\r\n");
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_switches_protocol() -> Result<()> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, Database::new()));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        let get = b"*2\r\n$3\r\nget\r\n$7\r\nmissing\r\n";
+        writer.write_all(get).await?;
+        let mut response = vec![0u8; 5];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"$-1\r\n");
+
+        writer
+            .write_all(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n")
+            .await?;
+        let mut response = vec![0u8; 4];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"%7\r\n");
+        let mut rest = vec![0u8; 1024];
+        let mut read = 0;
+        // drain the map up to its last entry: version
+        while !rest[..read].ends_with(b"7.4.0\r\n") {
+            read += reader.read(&mut rest[read..]).await?;
+        }
+
+        writer.write_all(get).await?;
+        let mut response = vec![0u8; 3];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"_\r\n");
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_select_is_per_connection() -> Result<()> {
+        let database = Database::new();
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, database.clone()));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        let mut request = b"*2\r\n$6\r\nselect\r\n$1\r\n3\r\n".to_vec();
+        request.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n");
+        request.extend_from_slice(b"*2\r\n$6\r\nselect\r\n$2\r\n16\r\n");
+        request.extend_from_slice(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n");
+        writer.write_all(&request).await?;
+
+        let expected = b"+OK\r\n+OK\r\n-ERR DB index is out of range\r\n$1\r\nv\r\n";
+        let mut response = vec![0u8; expected.len()];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, expected);
+        // other connections still start on db 0
+        assert!(!database.exists(b"k"));
+        assert!(database.select(3).is_some_and(|db| db.exists(b"k")));
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requirepass_needs_auth() -> Result<()> {
+        let database = Database::with_config(crate::config::Config {
+            requirepass: Some("secret".to_string()),
+            ..Default::default()
+        });
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, database));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        writer
+            .write_all(b"*2\r\n$4\r\necho\r\n$2\r\nhi\r\n")
+            .await?;
+        let expected = b"-NOAUTH Authentication required.\r\n";
+        let mut response = vec![0u8; expected.len()];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, expected);
+
+        writer
+            .write_all(
+                b"*5\r\n$5\r\nhello\r\n$1\r\n2\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n",
+            )
+            .await?;
+        let mut response = vec![0u8; 5];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"*14\r\n");
+        let mut rest = vec![0u8; 1024];
+        let mut read = 0;
+        while !rest[..read].ends_with(b"7.4.0\r\n") {
+            read += reader.read(&mut rest[read..]).await?;
+        }
+
+        writer
+            .write_all(b"*2\r\n$4\r\necho\r\n$2\r\nhi\r\n")
+            .await?;
+        let mut response = vec![0u8; 8];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, b"$2\r\nhi\r\n");
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inline_commands() -> Result<()> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, Database::new()));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        writer
+            .write_all(b"PING\r\n\r\nset k \"hello world\"\nget k\r\n")
+            .await?;
+        let expected = b"+PONG\r\n+OK\r\n$11\r\nhello world\r\n";
+        let mut response = vec![0u8; expected.len()];
+        reader.read_exact(&mut response).await?;
+        assert_eq!(response, expected);
+
+        writer.shutdown().await?;
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_limit_closes_connection() -> Result<()> {
+        let database = Database::with_config(crate::config::Config {
+            proto_max_bulk_len: 16,
+            ..Default::default()
+        });
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handle = tokio::spawn(process_redis_conn(server, database));
+        let (mut reader, mut writer) = tokio::io::split(client);
+
+        // the valid request before it is still answered, the one after it never runs
+        let echo: &[u8] = b"*2\r\n$4\r\necho\r\n$2\r\nhi\r\n";
+        let request = [echo, b"*2\r\n$4\r\necho\r\n$999999999\r\n", echo].concat();
+        writer.write_all(&request).await?;
+        let mut response = Vec::new();
+        reader.read_to_end(&mut response).await?;
+        assert_eq!(
+            String::from_utf8_lossy(&response),
+            "$2\r\nhi\r\n-ERR Protocol error: invalid bulk length\r\n"
+        );
+        handle.await??;
+        Ok(())
     }
 }
@@ -1,13 +1,8 @@
 use std::ops::Deref;
 
-use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 
-use crate::resp::RespDecode;
-use crate::resp::{
-    calc_total_length, extract_fixed_data, parse_length, RespEncode, RespError, RespFrame, BUF_CAP,
-    CRLF_LEN,
-};
+use crate::resp::{encode_header, RespEncode, RespFrame};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TArray(pub Vec<RespFrame>);
@@ -28,46 +23,11 @@ impl TArray {
 
 // - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for TArray {
-    fn encode(self) -> Vec<u8> {
-        if self.is_empty() {
-            return "*-1\r\n".to_string().into_bytes();
-        }
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '*', self.0.len());
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
-        }
-        buf
-    }
-}
-
-impl RespDecode for TArray {
-    const PREFIX: &'static str = "*";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        if extract_fixed_data(buf, "*-1\r\n", "TArray").is_ok() {
-            return Ok(TArray::new(Vec::new()));
-        }
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-
-        if buf.len() < total_len {
-            return Err(RespError::NotCompleteFrame);
-        }
-
-        buf.advance(end + CRLF_LEN);
-
-        let mut frames = Vec::with_capacity(len);
-        for _ in 0..len {
-            frames.push(RespFrame::decode(buf)?);
+            frame.encode(buf);
         }
-
-        Ok(TArray::new(frames))
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        calc_total_length(buf, end, len, Self::PREFIX)
     }
 }
 
@@ -79,7 +39,9 @@ impl From<Vec<RespFrame>> for TArray {
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::TBulkString;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespError, TBulkString, TNullArray, TNullBulkString};
 
     use super::*;
 
@@ -92,26 +54,41 @@ mod tests {
         ])
         .into();
         assert_eq!(
-            &frame.encode(),
+            &frame.to_vec(),
             b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
         );
     }
 
     #[test]
-    fn test_null_array_encode() {
+    fn test_array_encode_appends() {
+        // replies are serialized back to back into the same output buffer
+        let mut buf = BytesMut::from(&b"+OK\r\n"[..]);
+        TArray::new([RespFrame::Integer(1), TNullBulkString.into()]).encode(&mut buf);
+        RespFrame::from(TArray::new([b"a".into()])).encode(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n*2\r\n:1\r\n$-1\r\n*1\r\n$1\r\na\r\n");
+    }
+
+    #[test]
+    fn test_empty_array_encode() {
         let frame: RespFrame = TArray::new(Vec::new()).into();
-        assert_eq!(frame.encode(), b"*-1\r\n");
+        assert_eq!(frame.to_vec(), b"*0\r\n");
     }
 
     #[test]
-    fn test_null_array_decode() -> Result<()> {
+    fn test_empty_array_decode() -> Result<()> {
         let mut buf = BytesMut::new();
-        buf.extend_from_slice(b"*-1\r\n");
+        buf.extend_from_slice(b"*0\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
-        let null_array = TArray::new(Vec::new());
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        assert_eq!(frame, TArray::new(Vec::new()));
 
-        assert_eq!(frame, null_array);
+        // nulls inside an array keep their own type
+        buf.extend_from_slice(b"*2\r\n$-1\r\n*-1\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
+        assert_eq!(
+            frame,
+            TArray::new([TNullBulkString.into(), TNullArray.into()])
+        );
 
         Ok(())
     }
@@ -121,15 +98,19 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$3\r\nset\r\n$5\r\nhello\r\n");
 
-        let frame = TArray::decode(&mut buf)?;
+        let frame = decode_frame::<TArray>(&mut buf)?;
         assert_eq!(frame, TArray::new([b"set".into(), b"hello".into()]));
 
         buf.extend_from_slice(b"*2\r\n$3\r\nset\r\n");
-        let ret = TArray::decode(&mut buf);
+        let ret = decode_frame::<TArray>(&mut buf);
+        assert_eq!(ret.unwrap_err(), RespError::NotCompleteFrame);
+
+        buf.extend_from_slice(b"$5\r\nhel");
+        let ret = decode_frame::<TArray>(&mut buf);
         assert_eq!(ret.unwrap_err(), RespError::NotCompleteFrame);
 
-        buf.extend_from_slice(b"$5\r\nhello\r\n");
-        let frame = TArray::decode(&mut buf)?;
+        buf.extend_from_slice(b"lo\r\n");
+        let frame = decode_frame::<TArray>(&mut buf)?;
         assert_eq!(frame, TArray::new([b"set".into(), b"hello".into()]));
 
         Ok(())
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
+use bytes::BytesMut;
+
+use crate::resp::{encode_header, RespEncode, RespFrame, TSimpleString};
+
+// out-of-band metadata about the reply that follows it, laid out like a map
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct TAttribute(pub(crate) BTreeMap<String, RespFrame>);
+
+impl Deref for TAttribute {
+    type Target = BTreeMap<String, RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for TAttribute {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl TAttribute {
+    pub fn new() -> Self {
+        TAttribute(BTreeMap::new())
+    }
+}
+
+// - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
+impl RespEncode for TAttribute {
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '|', self.len());
+        for (key, value) in self.0 {
+            TSimpleString::new(key).encode(buf);
+            value.encode(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespError};
+
+    #[test]
+    fn test_attribute_encode() {
+        let mut attributes = TAttribute::new();
+        attributes.insert("ttl".to_string(), 3600.into());
+        let frame: RespFrame = attributes.into();
+        assert_eq!(frame.to_vec(), b"|1\r\n+ttl\r\n:3600\r\n");
+    }
+
+    #[test]
+    fn test_attribute_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"|1\r\n+key-popularity\r\n%1\r\n+a\r\n,0.1923\r\n");
+
+        let frame = decode_frame::<TAttribute>(&mut buf)?;
+        let Some(RespFrame::Map(popularity)) = frame.get("key-popularity") else {
+            panic!("expected a map value");
+        };
+        assert_eq!(popularity.get(b"a".as_slice()), Some(&0.1923.into()));
+
+        buf.extend_from_slice(b"|1\r\n+ttl\r\n");
+        assert_eq!(
+            decode_frame::<TAttribute>(&mut buf).unwrap_err(),
+            RespError::NotCompleteFrame
+        );
+
+        Ok(())
+    }
+}
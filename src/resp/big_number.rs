@@ -0,0 +1,72 @@
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+use crate::resp::{encode_line, RespEncode};
+
+// integers beyond the i64 range, kept in their decimal form
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TBigNumber(pub(crate) String);
+
+impl Deref for TBigNumber {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TBigNumber {
+    pub fn new(data: impl Into<String>) -> Self {
+        TBigNumber(data.into())
+    }
+}
+
+// - big number: "([+|-]<number>\r\n"
+impl RespEncode for TBigNumber {
+    fn encode(self, buf: &mut BytesMut) {
+        encode_line(buf, b'(', self.0.as_bytes());
+    }
+}
+
+pub(crate) fn is_big_number(digits: &[u8]) -> bool {
+    let unsigned = digits.strip_prefix(b"-").or(digits.strip_prefix(b"+"));
+    let unsigned = unsigned.unwrap_or(digits);
+    !unsigned.is_empty() && unsigned.iter().all(u8::is_ascii_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
+
+    use super::*;
+
+    #[test]
+    fn test_big_number_encode() {
+        let frame: RespFrame =
+            TBigNumber::new("3492890328409238509324850943850943825024385").into();
+        assert_eq!(
+            frame.to_vec(),
+            b"(3492890328409238509324850943850943825024385\r\n"
+        );
+    }
+
+    #[test]
+    fn test_big_number_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"(-3492890328409238509324850943850943825024385\r\n");
+
+        let frame = decode_frame::<TBigNumber>(&mut buf)?;
+        assert_eq!(
+            frame,
+            TBigNumber::new("-3492890328409238509324850943850943825024385")
+        );
+
+        buf.extend_from_slice(b"(12a\r\n");
+        assert!(decode_frame::<TBigNumber>(&mut buf).is_err());
+
+        Ok(())
+    }
+}
@@ -1,46 +1,29 @@
-use anyhow::Result;
 use bytes::BytesMut;
 
-use crate::resp::{extract_fixed_data, RespDecode, RespEncode, RespError};
+use crate::resp::{encode_line, RespEncode};
 
 // - boolean: "#<t|f>\r\n"
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { 't' } else { 'f' }).into_bytes()
-    }
-}
-
-impl RespDecode for bool {
-    const PREFIX: &'static str = "#";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        match extract_fixed_data(buf, "#t\r\n", "Bool") {
-            Ok(_) => Ok(true),
-            Err(_) => match extract_fixed_data(buf, "#f\r\n", "Bool") {
-                Ok(_) => Ok(false),
-                Err(f) => Err(f),
-            },
-        }
-    }
-
-    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
-        Ok(4)
+    fn encode(self, buf: &mut BytesMut) {
+        encode_line(buf, b'#', if self { b"t" } else { b"f" });
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::RespFrame;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
 
     use super::*;
 
     #[test]
     fn test_boolean_encode() {
         let frame: RespFrame = true.into();
-        assert_eq!(frame.encode(), b"#t\r\n");
+        assert_eq!(frame.to_vec(), b"#t\r\n");
 
         let frame: RespFrame = false.into();
-        assert_eq!(frame.encode(), b"#f\r\n");
+        assert_eq!(frame.to_vec(), b"#f\r\n");
     }
 
     #[test]
@@ -50,16 +33,16 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"#t\r\n");
 
-        let frame = bool::decode(&mut buf)?;
+        let frame = decode_frame::<bool>(&mut buf)?;
         assert!(frame);
 
         buf.extend_from_slice(b"#f\r\n");
 
-        let frame = bool::decode(&mut buf)?;
+        let frame = decode_frame::<bool>(&mut buf)?;
         assert!(!frame);
 
         buf.extend_from_slice(b"#f");
-        if !bool::decode(&mut buf).is_err() {
+        if !decode_frame::<bool>(&mut buf).is_err() {
             assert!(false);
         }
         Ok(())
@@ -0,0 +1,63 @@
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+use crate::resp::{encode_header, RespEncode, CRLF, CRLF_LEN};
+
+// an error whose message may be binary or contain newlines
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TBulkError(pub(crate) Vec<u8>);
+
+impl Deref for TBulkError {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TBulkError {
+    pub fn new(data: impl Into<Vec<u8>>) -> Self {
+        TBulkError(data.into())
+    }
+}
+
+// - bulk error: "!<length>\r\n<error>\r\n"
+impl RespEncode for TBulkError {
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '!', self.len());
+        buf.reserve(self.len() + CRLF_LEN);
+        buf.extend_from_slice(&self);
+        buf.extend_from_slice(CRLF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespError, RespFrame};
+
+    use super::*;
+
+    #[test]
+    fn test_bulk_error_encode() {
+        let frame: RespFrame = TBulkError::new(b"SYNTAX invalid\r\nsyntax".to_vec()).into();
+        assert_eq!(frame.to_vec(), b"!22\r\nSYNTAX invalid\r\nsyntax\r\n");
+    }
+
+    #[test]
+    fn test_bulk_error_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"!21\r\nSYNTAX invalid syntax\r\n");
+
+        let frame = decode_frame::<TBulkError>(&mut buf)?;
+        assert_eq!(frame, TBulkError::new(b"SYNTAX invalid syntax".to_vec()));
+
+        buf.extend_from_slice(b"!21\r\nSYNTAX");
+        let ret = decode_frame::<TBulkError>(&mut buf);
+        assert_eq!(ret.unwrap_err(), RespError::NotCompleteFrame);
+
+        Ok(())
+    }
+}
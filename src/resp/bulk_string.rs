@@ -1,30 +1,24 @@
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 
-use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use bytes::{Bytes, BytesMut};
 
-use crate::resp::{extract_fixed_data, parse_length, RespDecode, RespEncode, RespError, CRLF_LEN};
+use crate::resp::{encode_header, RespEncode, CRLF, CRLF_LEN};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub struct TBulkString(pub Vec<u8>);
+// backed by `Bytes` so the streaming decoder can hand out slices of the read buffer
+pub struct TBulkString(pub Bytes);
 
 impl Deref for TBulkString {
-    type Target = Vec<u8>;
+    type Target = Bytes;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for TBulkString {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
 impl TBulkString {
     pub fn new(data: impl Into<Vec<u8>>) -> Self {
-        TBulkString(data.into())
+        TBulkString(Bytes::from(data.into()))
     }
 }
 
@@ -36,83 +30,68 @@ impl AsRef<[u8]> for TBulkString {
 
 impl From<&str> for TBulkString {
     fn from(s: &str) -> Self {
-        TBulkString(s.as_bytes().to_vec())
+        TBulkString(Bytes::copy_from_slice(s.as_bytes()))
     }
 }
 
 impl From<String> for TBulkString {
     fn from(s: String) -> Self {
-        TBulkString(s.into_bytes())
+        TBulkString(Bytes::from(s))
+    }
+}
+
+impl From<Vec<u8>> for TBulkString {
+    fn from(s: Vec<u8>) -> Self {
+        TBulkString(Bytes::from(s))
+    }
+}
+
+impl From<Bytes> for TBulkString {
+    fn from(s: Bytes) -> Self {
+        TBulkString(s)
     }
 }
 
 impl From<&[u8]> for TBulkString {
     fn from(s: &[u8]) -> Self {
-        TBulkString(s.to_vec())
+        TBulkString(Bytes::copy_from_slice(s))
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for TBulkString {
     fn from(s: &[u8; N]) -> Self {
-        TBulkString(s.to_vec())
+        TBulkString(Bytes::copy_from_slice(s))
     }
 }
 
 // - bulk string: "$<length>\r\n<data>\r\n"
 impl RespEncode for TBulkString {
-    fn encode(self) -> Vec<u8> {
-        if self.is_empty() {
-            return b"$-1\r\n".to_vec();
-        }
-        let mut buf = Vec::with_capacity(self.len() + 16);
-        buf.extend_from_slice(format!("${}\r\n", self.len()).as_bytes());
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '$', self.len());
+        buf.reserve(self.len() + CRLF_LEN);
         buf.extend_from_slice(&self);
-        buf.extend_from_slice(b"\r\n");
-        buf
-    }
-}
-
-impl RespDecode for TBulkString {
-    const PREFIX: &'static str = "$";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        if extract_fixed_data(buf, "$-1\r\n", "TBulkString").is_ok() {
-            return Ok(TBulkString::new(Vec::new()));
-        }
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let remained = &buf[end + CRLF_LEN..];
-        if remained.len() < len + CRLF_LEN {
-            return Err(RespError::NotCompleteFrame);
-        }
-
-        buf.advance(end + CRLF_LEN);
-
-        let data = buf.split_to(len + CRLF_LEN);
-        Ok(TBulkString::new(data[..len].to_vec()))
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN + len + CRLF_LEN)
+        buf.extend_from_slice(CRLF);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::RespFrame;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespError, RespFrame};
 
     use super::*;
 
     #[test]
     fn test_bulk_string_encode() {
         let frame: RespFrame = TBulkString::new(b"hello".to_vec()).into();
-        assert_eq!(frame.encode(), b"$5\r\nhello\r\n");
+        assert_eq!(frame.to_vec(), b"$5\r\nhello\r\n");
     }
 
     #[test]
-    fn test_null_bulk_string_encode() {
+    fn test_empty_bulk_string_encode() {
         let frame: RespFrame = TBulkString::new(Vec::new()).into();
-        assert_eq!(frame.encode(), b"$-1\r\n");
+        assert_eq!(frame.to_vec(), b"$0\r\n\r\n");
     }
 
     #[test]
@@ -120,28 +99,30 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"$5\r\nhello\r\n");
 
-        let frame = TBulkString::decode(&mut buf)?;
+        let frame = decode_frame::<TBulkString>(&mut buf)?;
         assert_eq!(frame, TBulkString::new(b"hello"));
 
         buf.extend_from_slice(b"$5\r\nhello");
-        let ret = TBulkString::decode(&mut buf);
+        let ret = decode_frame::<TBulkString>(&mut buf);
         assert_eq!(ret.unwrap_err(), RespError::NotCompleteFrame);
 
         buf.extend_from_slice(b"\r\n");
-        let frame = TBulkString::decode(&mut buf)?;
+        let frame = decode_frame::<TBulkString>(&mut buf)?;
         assert_eq!(frame, TBulkString::new(b"hello"));
 
         Ok(())
     }
 
     #[test]
-    fn test_null_bulk_string_decode() -> Result<()> {
+    fn test_empty_bulk_string_decode() -> Result<()> {
         let mut buf = BytesMut::new();
-        buf.extend_from_slice(b"$-1\r\n");
+        buf.extend_from_slice(b"$0\r\n\r\n");
 
-        let frame = TBulkString::decode(&mut buf)?;
-        let null_bulk_string = TBulkString::new(Vec::new());
-        assert_eq!(frame, null_bulk_string);
+        let frame = decode_frame::<TBulkString>(&mut buf)?;
+        assert_eq!(frame, TBulkString::new(Vec::new()));
+
+        buf.extend_from_slice(b"$-1\r\n");
+        assert!(decode_frame::<TBulkString>(&mut buf).is_err());
 
         Ok(())
     }
@@ -0,0 +1,587 @@
+use bytes::{Buf, BytesMut};
+
+use crate::resp::inline::{split_args, TYPE_BYTES};
+use crate::resp::{
+    is_big_number, RespError, RespFrame, TArray, TAttribute, TBigNumber, TBulkError, TBulkString,
+    TError, TMap, TNull, TNullArray, TNullBulkString, TPush, TSet, TSimpleString, TVerbatimString,
+    CRLF, CRLF_LEN,
+};
+
+// an aggregate whose elements are still arriving
+#[derive(Debug)]
+struct Pending {
+    kind: u8,
+    // number of frames to collect, twice the entry count for maps and attributes
+    len: usize,
+    items: Vec<RespFrame>,
+}
+
+enum Step {
+    Frame(RespFrame),
+    // an aggregate header was pushed on the stack
+    Nested,
+    Incomplete,
+}
+
+// upper bounds for what a client may send, checked before anything is buffered or allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespLimits {
+    // payload size of a single bulk string
+    pub max_bulk_len: usize,
+    // element count of a single aggregate, entries for maps and attributes
+    pub max_multibulk_len: usize,
+    // aggregates nested in each other, 1 only allows flat arrays
+    pub max_nesting: usize,
+    // line length of an inline request and of the header or simple value line of a frame, the
+    // newline is looked for this far at most
+    pub max_inline_len: usize,
+}
+
+impl Default for RespLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_nesting: 32,
+            max_inline_len: 64 * 1024,
+        }
+    }
+}
+
+// single pass decoder: every element is parsed once and split off the buffer as soon as it is
+// complete, the aggregates around it wait on a stack until their last element arrives, so a
+// partial read never causes earlier bytes to be parsed again. bulk strings are `Bytes` slices
+// of the read buffer rather than copies.
+#[derive(Debug, Default)]
+pub struct RespDecoder {
+    stack: Vec<Pending>,
+    // bytes of the current line already searched for the CRLF
+    scanned: usize,
+    limits: RespLimits,
+}
+
+impl RespDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: RespLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    // true when no frame is partially decoded
+    pub fn is_idle(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            let mut frame = match self.decode_element(buf)? {
+                Step::Frame(frame) => frame,
+                Step::Nested => continue,
+                Step::Incomplete => return Ok(None),
+            };
+            // fold the completed frame into its parents, closing every aggregate it fills up
+            loop {
+                let Some(top) = self.stack.last_mut() else {
+                    return Ok(Some(frame));
+                };
+                top.items.push(frame);
+                if top.items.len() < top.len {
+                    break;
+                }
+                match self.stack.pop() {
+                    Some(pending) => frame = pending.into_frame()?,
+                    None => unreachable!("the stack top was just borrowed"),
+                }
+            }
+        }
+    }
+
+    // - inline command: "<arg-1> <arg-2> ... <arg-n>\r\n", arguments may be quoted like in
+    // redis-cli; a blank line gives an empty array. Only call it when `is_inline` holds for the
+    // buffer and the decoder is idle
+    pub fn decode_inline(&mut self, buf: &mut BytesMut) -> Result<Option<TArray>, RespError> {
+        let too_big = || RespError::LimitExceeded("too big inline request".to_string());
+        let start = self.scanned;
+        let Some(end) = buf[start..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|pos| start + pos)
+        else {
+            if buf.len() > self.limits.max_inline_len {
+                return Err(too_big());
+            }
+            self.scanned = buf.len();
+            return Ok(None);
+        };
+        if end > self.limits.max_inline_len {
+            return Err(too_big());
+        }
+        let line = buf.split_to(end + 1);
+        self.scanned = 0;
+        let line = &line[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let args = split_args(line)?;
+        if args.len() > self.limits.max_multibulk_len {
+            return Err(RespError::LimitExceeded(
+                "invalid multibulk length".to_string(),
+            ));
+        }
+        if args.iter().any(|arg| arg.len() > self.limits.max_bulk_len) {
+            return Err(RespError::LimitExceeded("invalid bulk length".to_string()));
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| TBulkString::new(arg).into())
+            .collect::<Vec<RespFrame>>();
+        Ok(Some(TArray::new(args)))
+    }
+
+    fn decode_element(&mut self, buf: &mut BytesMut) -> Result<Step, RespError> {
+        let Some(&kind) = buf.first() else {
+            return Ok(Step::Incomplete);
+        };
+        if !TYPE_BYTES.contains(&kind) {
+            return Err(RespError::InvalidFrameType(format!(
+                "unknown frame type: {:?}",
+                kind as char
+            )));
+        }
+        let Some(end) = self.find_crlf(buf) else {
+            if buf.len() > self.limits.max_inline_len {
+                return Err(too_big_line(kind));
+            }
+            return Ok(Step::Incomplete);
+        };
+        if end > self.limits.max_inline_len {
+            return Err(too_big_line(kind));
+        }
+        match kind {
+            b'$' | b'=' | b'!' => {
+                let len = parse_length(&buf[1..end])?;
+                if len == -1 && kind == b'$' {
+                    self.consume(buf, end + CRLF_LEN);
+                    return Ok(Step::Frame(TNullBulkString.into()));
+                }
+                let len = usize::try_from(len).map_err(|_| RespError::InvalidFrameLength(-1))?;
+                if len > self.limits.max_bulk_len {
+                    return Err(RespError::LimitExceeded("invalid bulk length".to_string()));
+                }
+                let total = end + CRLF_LEN + len + CRLF_LEN;
+                if buf.len() < total {
+                    // the header is complete, only wait for the payload
+                    self.scanned = end;
+                    return Ok(Step::Incomplete);
+                }
+                if &buf[total - CRLF_LEN..total] != CRLF {
+                    return Err(RespError::InvalidFrameData(
+                        "bulk payload is not terminated by CRLF".to_string(),
+                    ));
+                }
+                self.consume(buf, end + CRLF_LEN);
+                let data = buf.split_to(len).freeze();
+                buf.advance(CRLF_LEN);
+                let frame = match kind {
+                    b'$' => TBulkString::from(data).into(),
+                    b'!' => TBulkError::new(data.to_vec()).into(),
+                    _ => {
+                        if data.len() < 4 || data[3] != b':' {
+                            return Err(RespError::InvalidFrameData(
+                                "verbatim string without a format".to_string(),
+                            ));
+                        }
+                        let format = [data[0], data[1], data[2]];
+                        TVerbatimString::new(format, data[4..].to_vec()).into()
+                    }
+                };
+                Ok(Step::Frame(frame))
+            }
+            b'*' | b'~' | b'>' | b'%' | b'|' => {
+                let len = parse_length(&buf[1..end])?;
+                self.consume(buf, end + CRLF_LEN);
+                if len == -1 && kind == b'*' {
+                    return Ok(Step::Frame(TNullArray.into()));
+                }
+                let len = usize::try_from(len).map_err(|_| RespError::InvalidFrameLength(-1))?;
+                if len > self.limits.max_multibulk_len {
+                    return Err(RespError::LimitExceeded(
+                        "invalid multibulk length".to_string(),
+                    ));
+                }
+                if self.stack.len() >= self.limits.max_nesting {
+                    return Err(RespError::LimitExceeded(
+                        "too many nested aggregates".to_string(),
+                    ));
+                }
+                let len = match kind {
+                    b'%' | b'|' => len
+                        .checked_mul(2)
+                        .ok_or(RespError::InvalidFrameLength(-1))?,
+                    _ => len,
+                };
+                let pending = Pending {
+                    kind,
+                    len,
+                    // don't trust the announced length for the allocation
+                    items: Vec::with_capacity(len.min(1024)),
+                };
+                if len == 0 {
+                    return Ok(Step::Frame(pending.into_frame()?));
+                }
+                self.stack.push(pending);
+                Ok(Step::Nested)
+            }
+            _ => {
+                let line = buf.split_to(end + CRLF_LEN);
+                self.scanned = 0;
+                let data = &line[1..end];
+                let text = || String::from_utf8_lossy(data).to_string();
+                let frame = match kind {
+                    b'+' => TSimpleString::new(text()).into(),
+                    b'-' => TError::new(text()).into(),
+                    b':' => RespFrame::Integer(text().parse()?),
+                    b',' => RespFrame::Double(text().parse()?),
+                    b'#' => match data {
+                        b"t" => true.into(),
+                        b"f" => false.into(),
+                        _ => return Err(invalid_data("Bool", data)),
+                    },
+                    b'_' if data.is_empty() => TNull.into(),
+                    b'(' if is_big_number(data) => TBigNumber::new(text()).into(),
+                    b'_' => return Err(invalid_data("Null", data)),
+                    _ => return Err(invalid_data("BigNumber", data)),
+                };
+                Ok(Step::Frame(frame))
+            }
+        }
+    }
+
+    // position of the CRLF ending the line at the start of the buffer, resuming where the last
+    // partial read stopped
+    fn find_crlf(&mut self, buf: &[u8]) -> Option<usize> {
+        let start = self.scanned.max(1);
+        match buf
+            .get(start..)
+            .and_then(|rest| rest.windows(CRLF_LEN).position(|w| w == CRLF))
+        {
+            Some(pos) => Some(start + pos),
+            None => {
+                // the last byte may be the '\r' of a CRLF split across reads
+                self.scanned = buf.len().saturating_sub(1);
+                None
+            }
+        }
+    }
+
+    fn consume(&mut self, buf: &mut BytesMut, len: usize) {
+        buf.advance(len);
+        self.scanned = 0;
+    }
+}
+
+// a line is only buffered up to the inline limit while its CRLF is missing, with the messages
+// redis uses for the headers
+fn too_big_line(kind: u8) -> RespError {
+    let message = match kind {
+        b'*' | b'~' | b'>' | b'%' | b'|' => "too big mbulk count string",
+        b'$' | b'=' | b'!' => "too big bulk count string",
+        _ => "too big line",
+    };
+    RespError::LimitExceeded(message.to_string())
+}
+
+impl Pending {
+    fn into_frame(self) -> Result<RespFrame, RespError> {
+        let frame = match self.kind {
+            b'*' => TArray::new(self.items).into(),
+            b'~' => TSet::new(self.items).into(),
+            b'>' => TPush::new(self.items).into(),
+            kind => {
+                let mut entries = Vec::with_capacity(self.items.len() / 2);
+                let mut items = self.items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    let key = match key {
+                        RespFrame::SimpleString(s) => s.0.into_bytes(),
+                        RespFrame::BulkString(s) => s.0.into(),
+                        other => {
+                            return Err(RespError::InvalidFrameData(format!(
+                                "expect: map key, got: {:?}",
+                                other
+                            )))
+                        }
+                    };
+                    entries.push((key, value));
+                }
+                if kind == b'%' {
+                    let mut map = TMap::new();
+                    map.extend(entries);
+                    map.into()
+                } else {
+                    let mut attributes = TAttribute::new();
+                    for (key, value) in entries {
+                        attributes.insert(String::from_utf8_lossy(&key).into_owned(), value);
+                    }
+                    attributes.into()
+                }
+            }
+        };
+        Ok(frame)
+    }
+}
+
+fn parse_length(data: &[u8]) -> Result<i64, RespError> {
+    std::str::from_utf8(data)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|len| *len >= -1)
+        .ok_or(RespError::InvalidFrameLength(-1))
+}
+
+// one complete frame of type `T` off the front of the buffer, which is left untouched while the
+// frame is incomplete
+#[cfg(test)]
+pub(crate) fn decode_frame<T>(buf: &mut BytesMut) -> Result<T, RespError>
+where
+    RespFrame: TryInto<T>,
+    <RespFrame as TryInto<T>>::Error: std::fmt::Debug,
+{
+    let mut data = buf.clone();
+    let frame = RespDecoder::new()
+        .decode(&mut data)?
+        .ok_or(RespError::NotCompleteFrame)?;
+    *buf = data;
+    frame
+        .try_into()
+        .map_err(|e| RespError::InvalidFrameType(format!("{:?}", e)))
+}
+
+fn invalid_data(expect: &str, data: &[u8]) -> RespError {
+    RespError::InvalidFrameData(format!("expect: {}, got: {:?}", expect, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::{is_inline, RespEncode};
+
+    use super::*;
+
+    #[test]
+    fn test_decoder_frame_types() -> Result<()> {
+        let mut map = TMap::new();
+        map.insert(b"hello".to_vec(), b"world".into());
+        map.insert(b"foo".to_vec(), TArray::new(Vec::new()).into());
+        let mut attributes = TAttribute::new();
+        attributes.insert("ttl".to_string(), RespFrame::Integer(3600));
+        let cases: Vec<(&[u8], RespFrame)> = vec![
+            (b"+OK\r\n", TSimpleString::new("OK").into()),
+            (b"-ERR bad\r\n", TError::new("ERR bad").into()),
+            (b":-42\r\n", RespFrame::Integer(-42)),
+            (b"$5\r\nhello\r\n", b"hello".into()),
+            (b"$0\r\n\r\n", TBulkString::new(Vec::new()).into()),
+            (b"$-1\r\n", TNullBulkString.into()),
+            (b"*-1\r\n", TNullArray.into()),
+            (b"*0\r\n", TArray::new(Vec::new()).into()),
+            (b"_\r\n", TNull.into()),
+            (b"#t\r\n", true.into()),
+            (b",1.5\r\n", 1.5.into()),
+            (
+                b"(123456789012345678901234567890\r\n",
+                TBigNumber::new("123456789012345678901234567890").into(),
+            ),
+            (
+                b"=15\r\ntxt:Some string\r\n",
+                TVerbatimString::new(*b"txt", b"Some string".to_vec()).into(),
+            ),
+            (
+                b"!7\r\nERR bad\r\n",
+                TBulkError::new(b"ERR bad".to_vec()).into(),
+            ),
+            (
+                b"*3\r\n$3\r\nset\r\n*1\r\n:1\r\n~2\r\n+a\r\n+b\r\n",
+                TArray::new([
+                    b"set".into(),
+                    TArray::new([RespFrame::Integer(1)]).into(),
+                    TSet::new(vec![
+                        TSimpleString::new("a").into(),
+                        TSimpleString::new("b").into(),
+                    ])
+                    .into(),
+                ])
+                .into(),
+            ),
+            (b"%2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n*0\r\n", map.into()),
+            (b"|1\r\n+ttl\r\n:3600\r\n", attributes.into()),
+            (
+                b">2\r\n$7\r\nmessage\r\n$-1\r\n",
+                TPush::new([b"message".into(), TNullBulkString.into()]).into(),
+            ),
+        ];
+        for (data, expected) in cases {
+            let mut buf = BytesMut::from(data);
+            let frame = RespDecoder::new().decode(&mut buf)?;
+            assert_eq!(frame, Some(expected), "{}", String::from_utf8_lossy(data));
+            assert!(buf.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_partial_reads() -> Result<()> {
+        let frame: RespFrame = TArray::new([
+            b"mset".into(),
+            b"key".into(),
+            TBulkString::new(vec![b'x'; 300]).into(),
+            TArray::new([RespFrame::Integer(7), TNullBulkString.into()]).into(),
+        ])
+        .into();
+        let data = frame.clone().to_vec();
+
+        // feed one byte at a time, the decoder must resume where it stopped
+        let mut decoder = RespDecoder::new();
+        let mut buf = BytesMut::new();
+        let mut decoded = None;
+        for (i, b) in data.iter().enumerate() {
+            buf.extend_from_slice(&[*b]);
+            decoded = decoder.decode(&mut buf)?;
+            if i + 1 < data.len() {
+                assert_eq!(decoded, None);
+            }
+        }
+        assert_eq!(decoded, Some(frame));
+        assert!(decoder.is_idle());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_pipeline_and_errors() -> Result<()> {
+        let mut decoder = RespDecoder::new();
+        let mut buf = BytesMut::from(&b"$3\r\nget\r\n:1\r\n*1\r\n$3\r\nab"[..]);
+        assert_eq!(decoder.decode(&mut buf)?, Some(b"get".into()));
+        assert_eq!(decoder.decode(&mut buf)?, Some(RespFrame::Integer(1)));
+        assert_eq!(decoder.decode(&mut buf)?, None);
+        assert!(!decoder.is_idle());
+        buf.extend_from_slice(b"c\r\n");
+        assert_eq!(
+            decoder.decode(&mut buf)?,
+            Some(TArray::new([b"abc".into()]).into())
+        );
+
+        let mut buf = BytesMut::from(&b"$3\r\nabcd\r\n"[..]);
+        assert!(RespDecoder::new().decode(&mut buf).is_err());
+        let mut buf = BytesMut::from(&b"*-2\r\n"[..]);
+        assert!(RespDecoder::new().decode(&mut buf).is_err());
+        let mut buf = BytesMut::from(&b"?\r\n"[..]);
+        assert!(RespDecoder::new().decode(&mut buf).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_limits() -> Result<()> {
+        let limits = RespLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            max_nesting: 2,
+            max_inline_len: 16,
+        };
+        let limited =
+            |data: &[u8]| RespDecoder::with_limits(limits).decode(&mut BytesMut::from(data));
+        let exceeded = |message: &str| Err(RespError::LimitExceeded(message.to_string()));
+
+        // rejected from the header alone, without waiting for the announced payload
+        assert_eq!(limited(b"$5\r\n"), exceeded("invalid bulk length"));
+        assert_eq!(
+            limited(b"=999999999999\r\n"),
+            exceeded("invalid bulk length")
+        );
+        assert_eq!(
+            limited(b"*999999999999\r\n"),
+            exceeded("invalid multibulk length")
+        );
+        assert_eq!(limited(b"%3\r\n"), exceeded("invalid multibulk length"));
+        assert_eq!(
+            limited(b"*1\r\n*1\r\n*0\r\n"),
+            exceeded("too many nested aggregates")
+        );
+
+        // a header or simple line that never ends is cut off instead of buffered without bound
+        assert_eq!(limited(&[b'*'; 17]), exceeded("too big mbulk count string"));
+        assert_eq!(limited(&[b'$'; 17]), exceeded("too big bulk count string"));
+        assert_eq!(limited(&[b'+'; 17]), exceeded("too big line"));
+        assert_eq!(limited(b":1111111111111111\r\n"), exceeded("too big line"));
+        assert_eq!(limited(&[b'+'; 16])?, None);
+        assert_eq!(
+            limited(b"+aaaaaaaaaaaaaa\r\n")?,
+            Some(TSimpleString::new("aaaaaaaaaaaaaa").into())
+        );
+
+        assert_eq!(limited(b"$4\r\nabcd\r\n")?, Some(b"abcd".into()));
+        let frame = limited(b"*2\r\n*1\r\n:1\r\n$-1\r\n")?;
+        let expected = TArray::new([
+            TArray::new([RespFrame::Integer(1)]).into(),
+            TNullBulkString.into(),
+        ]);
+        assert_eq!(frame, Some(expected.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_inline() -> Result<()> {
+        let mut decoder = RespDecoder::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"PING\r\nget k\n*1\r\n");
+        assert!(is_inline(&buf));
+
+        let frame = decoder.decode_inline(&mut buf)?;
+        assert_eq!(frame, Some(TArray::new([b"PING".into()])));
+        let frame = decoder.decode_inline(&mut buf)?;
+        assert_eq!(frame, Some(TArray::new([b"get".into(), b"k".into()])));
+        assert!(!is_inline(&buf));
+
+        // a line split across reads is only scanned once, then completed
+        let mut buf = BytesMut::from(&b"echo hi"[..]);
+        assert_eq!(decoder.decode_inline(&mut buf)?, None);
+        assert_eq!(buf.len(), 7);
+        assert_eq!(decoder.scanned, 7);
+        buf.extend_from_slice(b" there\r\n");
+        let frame = decoder.decode_inline(&mut buf)?;
+        let expected = TArray::new([b"echo".into(), b"hi".into(), b"there".into()]);
+        assert_eq!(frame, Some(expected));
+        assert_eq!(decoder.scanned, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_inline_limits() -> Result<()> {
+        let limits = RespLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            max_nesting: 1,
+            max_inline_len: 16,
+        };
+        let limited =
+            |data: &[u8]| RespDecoder::with_limits(limits).decode_inline(&mut BytesMut::from(data));
+        let exceeded = |message: &str| Err(RespError::LimitExceeded(message.to_string()));
+
+        // a client that never sends the newline is cut off instead of buffered without bound
+        assert_eq!(limited(&[b'a'; 17]), exceeded("too big inline request"));
+        assert_eq!(
+            limited(b"get aaaaaaaaaaaaaaaaa\n"),
+            exceeded("too big inline request")
+        );
+        assert_eq!(limited(&[b'a'; 16])?, None);
+        assert_eq!(limited(b"a b c\n"), exceeded("invalid multibulk length"));
+        assert_eq!(limited(b"get abcde\n"), exceeded("invalid bulk length"));
+        assert_eq!(
+            limited(b"get abcd\n")?,
+            Some(TArray::new([b"get".into(), b"abcd".into()]))
+        );
+        Ok(())
+    }
+}
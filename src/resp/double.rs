@@ -1,36 +1,16 @@
-use anyhow::Result;
 use bytes::BytesMut;
 
-use crate::resp::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
+use crate::resp::{encode_fmt, RespEncode};
 
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
-            format!(",{:+e}\r\n", self)
+    fn encode(self, buf: &mut BytesMut) {
+        if self.abs() > 1e+8 || self.abs() < 1e-8 {
+            encode_fmt(buf, format_args!(",{:+e}\r\n", self));
         } else {
             let sign = if self < 0.0 { "" } else { "+" };
-            format!(",{}{}\r\n", sign, self)
-        };
-        buf.extend_from_slice(ret.as_bytes());
-        buf
-    }
-}
-
-impl RespDecode for f64 {
-    const PREFIX: &'static str = ",";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(s.parse()?)
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN)
+            encode_fmt(buf, format_args!(",{}{}\r\n", sign, self));
+        }
     }
 }
 
@@ -38,21 +18,21 @@ impl RespDecode for f64 {
 mod tests {
     use bytes::BytesMut;
 
-    use crate::resp::{RespDecode, RespEncode, RespFrame};
+    use crate::resp::{decode_frame, RespEncode, RespFrame};
 
     #[test]
     fn test_double_encode() {
         let frame: RespFrame = 123.456.into();
-        assert_eq!(frame.encode(), b",+123.456\r\n");
+        assert_eq!(frame.to_vec(), b",+123.456\r\n");
 
         let frame: RespFrame = (-123.456).into();
-        assert_eq!(frame.encode(), b",-123.456\r\n");
+        assert_eq!(frame.to_vec(), b",-123.456\r\n");
 
         let frame: RespFrame = 1.23456e+8.into();
-        assert_eq!(frame.encode(), b",+1.23456e8\r\n");
+        assert_eq!(frame.to_vec(), b",+1.23456e8\r\n");
 
         let frame: RespFrame = (-1.23456e-9).into();
-        assert_eq!(&frame.encode(), b",-1.23456e-9\r\n");
+        assert_eq!(&frame.to_vec(), b",-1.23456e-9\r\n");
     }
 
     #[test]
@@ -60,11 +40,11 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b",123.45\r\n");
 
-        let frame = f64::decode(&mut buf)?;
+        let frame = decode_frame::<f64>(&mut buf)?;
         assert_eq!(frame, 123.45);
 
         buf.extend_from_slice(b",+1.23456e-9\r\n");
-        let frame = f64::decode(&mut buf)?;
+        let frame = decode_frame::<f64>(&mut buf)?;
         assert_eq!(frame, 1.23456e-9);
 
         Ok(())
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
+
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
 
 use crate::resp::{
-    RespDecode, RespError, TArray, TBulkString, TError, TMap, TNull, TSet, TSimpleString,
+    encode_header, RespEncode, TArray, TAttribute, TBigNumber, TBulkError, TBulkString, TError,
+    TMap, TNull, TNullArray, TNullBulkString, TPush, TSet, TSimpleString, TVerbatimString,
 };
 
 #[enum_dispatch(RespEncode)]
@@ -13,87 +16,92 @@ pub enum RespFrame {
     Integer(i64),
     BulkString(TBulkString),
     Array(TArray),
+    NullBulkString(TNullBulkString),
+    NullArray(TNullArray),
     Null(TNull),
     Boolean(bool),
     Double(f64),
     Map(TMap),
     Set(TSet),
+    BigNumber(TBigNumber),
+    VerbatimString(TVerbatimString),
+    BulkError(TBulkError),
+    Attribute(TAttribute),
+    Push(TPush),
 }
 
-impl RespDecode for RespFrame {
-    const PREFIX: &'static str = "";
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let mut iter = buf.iter().peekable();
-        match iter.peek() {
-            Some(b'+') => {
-                let frame = TSimpleString::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'-') => {
-                let frame = TError::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b':') => {
-                let frame = i64::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'$') => {
-                // try null bulk string first
-                match TBulkString::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(e) => Err(e),
-                }
-            }
-            Some(b'*') => {
-                // try null array first
-                match TArray::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(e) => Err(e),
-                }
-            }
-            Some(b'_') => {
-                let frame = TNull::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'#') => {
-                let frame = bool::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b',') => {
-                let frame = f64::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'%') => {
-                let frame = TMap::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'~') => {
-                let frame = TSet::decode(buf)?;
-                Ok(frame.into())
-            }
-            None => Err(RespError::NotCompleteFrame),
-            _ => Err(RespError::InvalidFrameType(format!(
-                "expect_length: unknown frame type: {:?}",
-                buf
-            ))),
+// the protocol a connection negotiated with HELLO, connections start on resp2 like in redis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RespProtocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl RespProtocol {
+    pub fn version(&self) -> i64 {
+        match self {
+            RespProtocol::Resp2 => 2,
+            RespProtocol::Resp3 => 3,
         }
     }
+}
 
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let mut iter = buf.iter().peekable();
-        match iter.peek() {
-            Some(b'*') => TArray::expect_length(buf),
-            Some(b'~') => TSet::expect_length(buf),
-            Some(b'%') => TMap::expect_length(buf),
-            Some(b'$') => TBulkString::expect_length(buf),
-            Some(b':') => i64::expect_length(buf),
-            Some(b'+') => TSimpleString::expect_length(buf),
-            Some(b'-') => TError::expect_length(buf),
-            Some(b'#') => bool::expect_length(buf),
-            Some(b',') => f64::expect_length(buf),
-            Some(b'_') => TNull::expect_length(buf),
-            _ => Err(RespError::NotCompleteFrame),
+impl RespFrame {
+    // writes the frame for the protocol of the connection, on resp2 every resp3 reply is written
+    // as its closest resp2 equivalent, nested ones included
+    pub fn encode_for(self, protocol: RespProtocol, buf: &mut BytesMut) {
+        if protocol == RespProtocol::Resp3 {
+            return self.encode(buf);
         }
+        match self {
+            RespFrame::Null(_) => TNullBulkString.encode(buf),
+            RespFrame::Boolean(b) => (b as i64).encode(buf),
+            RespFrame::Double(f) => TBulkString::from(format_double(f)).encode(buf),
+            RespFrame::Map(map) => encode_resp2_pairs(map.0, buf),
+            // like redis, only the reply an attribute annotates reaches a resp2 client
+            RespFrame::Attribute(_) => {}
+            RespFrame::Array(array) => encode_resp2_items(array.0, buf),
+            RespFrame::Set(set) => encode_resp2_items(set.0, buf),
+            // pub/sub messages were plain arrays before resp3
+            RespFrame::Push(push) => encode_resp2_items(push.0, buf),
+            RespFrame::BigNumber(n) => TBulkString::from(n.0).encode(buf),
+            RespFrame::VerbatimString(s) => TBulkString::new(s.data).encode(buf),
+            RespFrame::BulkError(e) => {
+                TError::new(String::from_utf8_lossy(&e).replace("\r\n", " ")).encode(buf)
+            }
+            other => other.encode(buf),
+        }
+    }
+}
+
+// maps become flat arrays of keys and values
+fn encode_resp2_pairs(pairs: BTreeMap<Vec<u8>, RespFrame>, buf: &mut BytesMut) {
+    encode_header(buf, '*', pairs.len() * 2);
+    for (key, value) in pairs {
+        TBulkString::from(key).encode(buf);
+        value.encode_for(RespProtocol::Resp2, buf);
+    }
+}
+
+fn encode_resp2_items(items: Vec<RespFrame>, buf: &mut BytesMut) {
+    // a dropped attribute is not counted as an element
+    let attributes = items
+        .iter()
+        .filter(|item| matches!(item, RespFrame::Attribute(_)))
+        .count();
+    encode_header(buf, '*', items.len() - attributes);
+    for item in items {
+        item.encode_for(RespProtocol::Resp2, buf);
+    }
+}
+
+fn format_double(f: f64) -> String {
+    match f {
+        f if f.is_nan() => "nan".to_string(),
+        f if f == f64::INFINITY => "inf".to_string(),
+        f if f == f64::NEG_INFINITY => "-inf".to_string(),
+        f => f.to_string(),
     }
 }
 
@@ -105,12 +113,64 @@ impl From<&str> for RespFrame {
 
 impl From<&[u8]> for RespFrame {
     fn from(s: &[u8]) -> Self {
-        TBulkString(s.to_vec()).into()
+        TBulkString::from(s).into()
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for RespFrame {
     fn from(s: &[u8; N]) -> Self {
-        TBulkString(s.to_vec()).into()
+        TBulkString::from(s).into()
+    }
+}
+
+impl From<Vec<RespFrame>> for RespFrame {
+    fn from(frames: Vec<RespFrame>) -> Self {
+        TArray::new(frames).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resp2(frame: RespFrame) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        frame.encode_for(RespProtocol::Resp2, &mut buf);
+        buf.to_vec()
+    }
+
+    #[test]
+    fn test_encode_for_resp2() {
+        let mut map = TMap::new();
+        map.insert(b"proto".to_vec(), RespFrame::Integer(2));
+        map.insert(b"flag".to_vec(), true.into());
+        map.insert(b"score".to_vec(), 1.5.into());
+        map.insert(b"missing".to_vec(), TNull.into());
+        let frame: RespFrame = map.into();
+        assert_eq!(
+            resp2(frame),
+            b"*8\r\n$4\r\nflag\r\n:1\r\n$7\r\nmissing\r\n$-1\r\n$5\r\nproto\r\n:2\r\n$5\r\nscore\r\n$3\r\n1.5\r\n"
+        );
+
+        let frame: RespFrame = TSet::new(vec![f64::INFINITY.into(), false.into()]).into();
+        assert_eq!(resp2(frame), b"*2\r\n$3\r\ninf\r\n:0\r\n");
+
+        let frame: RespFrame = TPush::new([
+            TBigNumber::new("12345678901234567890").into(),
+            TVerbatimString::new(*b"txt", b"hi".to_vec()).into(),
+            TBulkError::new(b"ERR a\r\nb".to_vec()).into(),
+        ])
+        .into();
+        assert_eq!(
+            resp2(frame),
+            b"*3\r\n$20\r\n12345678901234567890\r\n$2\r\nhi\r\n-ERR a b\r\n"
+        );
+
+        // attributes are dropped, the replies they annotate are sent as they are
+        let mut attributes = TAttribute::new();
+        attributes.insert("ttl".to_string(), 3600.into());
+        assert_eq!(resp2(attributes.clone().into()), b"");
+        let frame: RespFrame = TArray::new([attributes.into(), RespFrame::Integer(1)]).into();
+        assert_eq!(resp2(frame), b"*1\r\n:1\r\n");
     }
 }
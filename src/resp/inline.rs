@@ -0,0 +1,124 @@
+use crate::resp::RespError;
+
+// the first byte of every resp2 and resp3 frame
+pub(super) const TYPE_BYTES: &[u8] = b"+-:$*_#,(!=%~|>";
+
+// requests typed by hand (telnet, nc) or sent by health checks don't start with a type byte
+pub fn is_inline(buf: &[u8]) -> bool {
+    buf.first().is_some_and(|b| !TYPE_BYTES.contains(b))
+}
+
+// same rules as redis' sdssplitargs: double quotes understand escapes, single quotes only \'
+pub(super) fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let unbalanced = || RespError::InvalidFrameData("unbalanced quotes in request".to_string());
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+        let mut arg = Vec::new();
+        let mut in_double = false;
+        let mut in_single = false;
+        loop {
+            let Some(&c) = line.get(i) else {
+                if in_double || in_single {
+                    return Err(unbalanced());
+                }
+                break;
+            };
+            if in_double {
+                match c {
+                    b'\\' if line.get(i + 1) == Some(&b'x') && hex_pair(line, i + 2).is_some() => {
+                        arg.push(hex_pair(line, i + 2).unwrap_or_default());
+                        i += 3;
+                    }
+                    b'\\' if i + 1 < line.len() => {
+                        i += 1;
+                        arg.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    b'"' => {
+                        // the closing quote must end the argument
+                        if line.get(i + 1).is_some_and(|b| !b.is_ascii_whitespace()) {
+                            return Err(unbalanced());
+                        }
+                        i += 1;
+                        break;
+                    }
+                    _ => arg.push(c),
+                }
+            } else if in_single {
+                match c {
+                    b'\\' if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    b'\'' => {
+                        if line.get(i + 1).is_some_and(|b| !b.is_ascii_whitespace()) {
+                            return Err(unbalanced());
+                        }
+                        i += 1;
+                        break;
+                    }
+                    _ => arg.push(c),
+                }
+            } else {
+                match c {
+                    c if c.is_ascii_whitespace() => break,
+                    b'"' => in_double = true,
+                    b'\'' => in_single = true,
+                    _ => arg.push(c),
+                }
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+fn hex_pair(line: &[u8], at: usize) -> Option<u8> {
+    let digits = line.get(at..at + 2)?;
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    fn args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+        split_args(line)
+    }
+
+    #[test]
+    fn test_split_args() -> Result<()> {
+        assert_eq!(
+            args(b"  set  k v ")?,
+            vec![b"set".to_vec(), b"k".to_vec(), b"v".to_vec()]
+        );
+        assert_eq!(
+            args(br#"set "hello world" 'it\'s'"#)?,
+            vec![b"set".to_vec(), b"hello world".to_vec(), b"it's".to_vec()]
+        );
+        assert_eq!(args(br#""a\r\n\x41\"""#)?, vec![b"a\r\nA\"".to_vec()]);
+        assert_eq!(args(br#"'a\nb'"#)?, vec![b"a\\nb".to_vec()]);
+        assert_eq!(args(br#""""#)?, vec![Vec::<u8>::new()]);
+        assert!(args(b"")?.is_empty());
+
+        assert!(args(br#"set "unclosed"#).is_err());
+        assert!(args(br#"set "a"b"#).is_err());
+        assert!(args(br#"set 'a'b"#).is_err());
+        Ok(())
+    }
+}
@@ -1,48 +1,29 @@
-use anyhow::Result;
 use bytes::BytesMut;
-use tracing::info;
 
-use crate::resp::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
+use crate::resp::{encode_fmt, RespEncode};
 
 // - integer: ":[<+|->]<value>\r\n"
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        let e = format!(":{}\r\n", self).into_bytes();
-        info!("Integer encoded: {:?}", String::from_utf8_lossy(&e));
-        e
-    }
-}
-
-impl RespDecode for i64 {
-    const PREFIX: &'static str = ":";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        // split the buffer
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(s.parse()?)
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN)
+    fn encode(self, buf: &mut BytesMut) {
+        encode_fmt(buf, format_args!(":{}\r\n", self));
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::RespFrame;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
 
     use super::*;
 
     #[test]
     fn test_integer_encode() {
         let frame: RespFrame = 123.into();
-        assert_eq!(frame.encode(), b":123\r\n");
+        assert_eq!(frame.to_vec(), b":123\r\n");
 
         let frame: RespFrame = (-123).into();
-        assert_eq!(frame.encode(), b":-123\r\n");
+        assert_eq!(frame.to_vec(), b":-123\r\n");
     }
 
     #[test]
@@ -50,12 +31,12 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b":123\r\n");
 
-        let frame = i64::decode(&mut buf)?;
+        let frame = decode_frame::<i64>(&mut buf)?;
         assert_eq!(frame, 123);
 
         buf.extend_from_slice(b":-123\r\n");
 
-        let frame = i64::decode(&mut buf)?;
+        let frame = decode_frame::<i64>(&mut buf)?;
         assert_eq!(frame, -123);
 
         Ok(())
@@ -1,28 +1,41 @@
-use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
 use thiserror::Error;
 
 pub use array::*;
+pub use attribute::*;
+pub use big_number::*;
+pub use bulk_error::*;
 pub use bulk_string::*;
+pub use decoder::*;
 pub use frame::*;
+pub use inline::*;
 pub use map::*;
 pub use null::*;
+pub use push::*;
 pub use set::*;
 pub use simple_error::*;
 pub use simple_string::*;
+pub use verbatim_string::*;
 
 mod array;
+mod attribute;
+mod big_number;
 mod boolean;
+mod bulk_error;
 mod bulk_string;
+mod decoder;
 mod double;
 mod frame;
+mod inline;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
+mod verbatim_string;
 
 /*
 - 如何 serialize/deserialize Frame
@@ -41,13 +54,15 @@ mod simple_string;
     - big number: "([+|-]<number>\r\n"
     - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
     - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
+    - verbatim string: "=<length>\r\n<encoding>:<data>\r\n"
+    - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
+    - push: "><number-of-elements>\r\n<element-1>...<element-n>"
     - ...
 - enum RespFrame {}
-- trait RespEncode / RespDecode (enum dispatch)
+- trait RespEncode (enum dispatch), RespDecoder parses frames as they arrive
 - bytes trait
 */
 
-const BUF_CAP: usize = 4096;
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
 
@@ -60,6 +75,9 @@ pub enum RespError {
     InvalidFrameLength(isize),
     #[error("Invalid frame data: {0}")]
     InvalidFrameData(String),
+    // the client sent more than the configured limits allow
+    #[error("{0}")]
+    LimitExceeded(String),
     #[error("Frame is not complete")]
     NotCompleteFrame,
     #[error("Parse float error: {0}")]
@@ -73,108 +91,33 @@ pub trait RespEncode {
     //self 获取所有权, 如果你需要在方法内部完全消耗该对象
     //&self 借用所有权, 不需要修改调用者并且只需要读访问的方法
     //&mut self 可变借用所有权, 在方法内部修改对象，但仍希望在方法调用后继续使用该对象
-    fn encode(self) -> Vec<u8>;
-}
-
-#[allow(dead_code)]
-pub trait RespDecode: Sized {
-    const PREFIX: &'static str;
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError>;
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError>;
-}
-
-fn extract_fixed_data(
-    buf: &mut BytesMut,
-    expect: &str,
-    expect_type: &str,
-) -> Result<(), RespError> {
-    if buf.len() < expect.len() {
-        return Err(RespError::InvalidFrameData(format!(
-            "expect: {}, got: {}",
-            expect.len(),
-            buf.len()
-        )));
-    }
-
-    if !buf.starts_with(expect.as_bytes()) {
-        return Err(RespError::InvalidFrameData(format!(
-            "expect: {}, got: {:?}",
-            expect_type, buf
-        )));
-    }
-
-    buf.advance(expect.len());
-    Ok(())
-}
-
-fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
-    if buf.len() < 3 {
-        return Err(RespError::NotCompleteFrame);
-    }
-
-    if !buf.starts_with(prefix.as_bytes()) {
-        return Err(RespError::InvalidFrameType(format!(
-            "expect: SimpleString({}), got: {:?}",
-            prefix, buf
-        )));
+    fn encode(self, buf: &mut BytesMut);
+
+    // the frame on its own, for callers without an output buffer
+    fn to_vec(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut buf = BytesMut::new();
+        self.encode(&mut buf);
+        buf.to_vec()
     }
-
-    let end = find_crlf(buf, 1).ok_or(RespError::NotCompleteFrame)?;
-
-    Ok(end)
 }
 
-// find nth CRLF in the buffer
-fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
-    let mut count = 0;
-    for i in 1..buf.len() - 1 {
-        if buf[i] == b'\r' && buf[i + 1] == b'\n' {
-            count += 1;
-            if count == nth {
-                return Some(i);
-            }
-        }
-    }
-
-    None
+// `<prefix><len>\r\n`, the header of bulk and aggregate frames
+fn encode_header(buf: &mut BytesMut, prefix: char, len: usize) {
+    encode_fmt(buf, format_args!("{}{}\r\n", prefix, len));
 }
 
-fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
-    let end = extract_simple_frame_data(buf, prefix)?;
-    let s = String::from_utf8_lossy(&buf[prefix.len()..end]);
-    let len = s
-        .parse::<usize>()
-        .map_err(|_| RespError::InvalidFrameLength(-1))?;
-    Ok((end, len))
+// `<prefix><line>\r\n`, for frames that fit on a single line
+fn encode_line(buf: &mut BytesMut, prefix: u8, line: &[u8]) {
+    buf.reserve(line.len() + 1 + CRLF_LEN);
+    buf.extend_from_slice(&[prefix]);
+    buf.extend_from_slice(line);
+    buf.extend_from_slice(CRLF);
 }
 
-fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
-    let mut total = end + CRLF_LEN;
-    let mut data = &buf[total..];
-    match prefix {
-        "*" | "~" => {
-            // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
-            for _ in 0..len {
-                let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
-                total += len;
-            }
-            Ok(total)
-        }
-        "%" => {
-            // find nth CRLF in the buffer. For map, we need to find 2 CRLF for each key-value pair
-            for _ in 0..len {
-                let len = TSimpleString::expect_length(data)?;
-
-                data = &data[len..];
-                total += len;
-
-                let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
-                total += len;
-            }
-            Ok(total)
-        }
-        _ => Ok(len + CRLF_LEN),
-    }
+// formats straight into the buffer, writing to a BytesMut can't fail
+fn encode_fmt(buf: &mut BytesMut, args: std::fmt::Arguments) {
+    let _ = std::fmt::Write::write_fmt(buf, args);
 }
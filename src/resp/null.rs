@@ -1,43 +1,49 @@
-use anyhow::Result;
 use bytes::BytesMut;
 
-use crate::resp::{extract_fixed_data, RespDecode, RespEncode, RespError};
+use crate::resp::RespEncode;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TNull;
 
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TNullBulkString;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TNullArray;
+
 // - null: "_\r\n"
 impl RespEncode for TNull {
-    fn encode(self) -> Vec<u8> {
-        "_\r\n".to_string().into_bytes()
+    fn encode(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
     }
 }
 
-impl RespDecode for TNull {
-    const PREFIX: &'static str = "_";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        match extract_fixed_data(buf, "_\r\n", "TNull") {
-            Ok(_) => Ok(TNull),
-            Err(e) => Err(e),
-        }
+// - null bulk string: "$-1\r\n"
+impl RespEncode for TNullBulkString {
+    fn encode(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"$-1\r\n");
     }
+}
 
-    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
-        Ok(3)
+// - null array: "*-1\r\n"
+impl RespEncode for TNullArray {
+    fn encode(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"*-1\r\n");
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::RespFrame;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
 
     use super::*;
 
     #[test]
     fn test_null_encode() {
         let frame: RespFrame = TNull.into();
-        assert_eq!(frame.encode(), b"_\r\n");
+        assert_eq!(frame.to_vec(), b"_\r\n");
     }
 
     #[test]
@@ -45,9 +51,43 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"_\r\n");
 
-        let frame = TNull::decode(&mut buf)?;
+        let frame = decode_frame::<TNull>(&mut buf)?;
         assert_eq!(frame, TNull);
 
         Ok(())
     }
+
+    #[test]
+    fn test_null_bulk_string_encode() {
+        let frame: RespFrame = TNullBulkString.into();
+        assert_eq!(frame.to_vec(), b"$-1\r\n");
+    }
+
+    #[test]
+    fn test_null_bulk_string_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"$-1\r\n");
+
+        let frame = decode_frame::<RespFrame>(&mut buf)?;
+        assert_eq!(frame, TNullBulkString.into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_array_encode() {
+        let frame: RespFrame = TNullArray.into();
+        assert_eq!(frame.to_vec(), b"*-1\r\n");
+    }
+
+    #[test]
+    fn test_null_array_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*-1\r\n");
+
+        let frame = decode_frame::<RespFrame>(&mut buf)?;
+        assert_eq!(frame, TNullArray.into());
+
+        Ok(())
+    }
 }
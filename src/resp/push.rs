@@ -0,0 +1,72 @@
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+use crate::resp::{encode_header, RespEncode, RespFrame};
+
+// out-of-band data such as pub/sub messages and tracking invalidations
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TPush(pub(crate) Vec<RespFrame>);
+
+impl Deref for TPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TPush {
+    pub fn new(data: impl Into<Vec<RespFrame>>) -> Self {
+        TPush(data.into())
+    }
+}
+
+// - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+impl RespEncode for TPush {
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '>', self.0.len());
+        for frame in self.0 {
+            frame.encode(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespError};
+
+    #[test]
+    fn test_push_encode() {
+        let frame: RespFrame =
+            TPush::new([b"message".into(), b"news".into(), b"hello".into()]).into();
+        assert_eq!(
+            frame.to_vec(),
+            b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nkey\r\n");
+
+        let frame = decode_frame::<RespFrame>(&mut buf)?;
+        let expected = TPush::new([
+            b"invalidate".into(),
+            crate::resp::TArray::new([b"key".into()]).into(),
+        ]);
+        assert_eq!(frame, expected.into());
+
+        buf.extend_from_slice(b">2\r\n$10\r\ninvalidate\r\n");
+        assert_eq!(
+            decode_frame::<RespFrame>(&mut buf).unwrap_err(),
+            RespError::NotCompleteFrame
+        );
+
+        Ok(())
+    }
+}
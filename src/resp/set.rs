@@ -1,11 +1,8 @@
 use std::ops::Deref;
 
-use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 
-use crate::resp::{
-    calc_total_length, parse_length, RespDecode, RespEncode, RespError, RespFrame, CRLF_LEN,
-};
+use crate::resp::{encode_header, RespEncode, RespFrame};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TSet(pub(crate) Vec<RespFrame>);
@@ -20,41 +17,11 @@ impl Deref for TSet {
 
 // - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for TSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
+    fn encode(self, buf: &mut BytesMut) {
+        encode_header(buf, '~', self.len());
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+            frame.encode(buf);
         }
-        buf
-    }
-}
-
-impl RespDecode for TSet {
-    const PREFIX: &'static str = "~";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-
-        if buf.len() < total_len {
-            return Err(RespError::NotCompleteFrame);
-        }
-
-        buf.advance(end + CRLF_LEN);
-
-        let mut frames = Vec::new();
-        for _ in 0..len {
-            frames.push(RespFrame::decode(buf)?);
-        }
-
-        Ok(TSet::new(frames))
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        calc_total_length(buf, end, len, Self::PREFIX)
     }
 }
 
@@ -66,7 +33,9 @@ impl TSet {
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::{TArray, TBulkString};
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, TArray, TBulkString};
 
     use super::*;
 
@@ -78,7 +47,7 @@ mod tests {
         ])
         .into();
         assert_eq!(
-            frame.encode(),
+            frame.to_vec(),
             b"~2\r\n*2\r\n:1234\r\n#t\r\n$5\r\nworld\r\n"
         );
     }
@@ -87,7 +56,7 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"~2\r\n$3\r\nset\r\n$5\r\nhello\r\n");
 
-        let frame = TSet::decode(&mut buf)?;
+        let frame = decode_frame::<TSet>(&mut buf)?;
         assert_eq!(
             frame,
             TSet::new(vec![
@@ -1,9 +1,8 @@
 use std::ops::Deref;
 
-use anyhow::Result;
 use bytes::BytesMut;
 
-use crate::resp::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
+use crate::resp::{encode_line, RespEncode};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TError(pub(crate) String);
@@ -18,25 +17,8 @@ impl Deref for TError {
 
 // - error: "-Error message\r\n"
 impl RespEncode for TError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
-    }
-}
-
-impl RespDecode for TError {
-    const PREFIX: &'static str = "-";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        // split the buffer
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(TError::new(s.to_string()))
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN)
+    fn encode(self, buf: &mut BytesMut) {
+        encode_line(buf, b'-', self.0.as_bytes());
     }
 }
 
@@ -48,7 +30,9 @@ impl TError {
 
 #[cfg(test)]
 mod tests {
-    use crate::resp::RespFrame;
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
 
     use super::*;
 
@@ -56,7 +40,7 @@ mod tests {
     fn test_error_encode() {
         let frame: RespFrame = TError::new("Error message".to_string()).into();
 
-        assert_eq!(frame.encode(), b"-Error message\r\n");
+        assert_eq!(frame.to_vec(), b"-Error message\r\n");
     }
 
     #[test]
@@ -64,7 +48,7 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"-Error message\r\n");
 
-        let frame = TError::decode(&mut buf)?;
+        let frame = decode_frame::<TError>(&mut buf)?;
         assert_eq!(frame, TError::new("Error message".to_string()));
 
         Ok(())
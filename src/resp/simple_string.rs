@@ -1,34 +1,16 @@
 use std::ops::Deref;
 
-use anyhow::Result;
 use bytes::BytesMut;
 
-use crate::resp::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
+use crate::resp::{encode_line, RespEncode};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TSimpleString(pub(crate) String);
 
 //simple string: "+OK\r\n"
 impl RespEncode for TSimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
-    }
-}
-
-impl RespDecode for TSimpleString {
-    const PREFIX: &'static str = "+";
-
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        // split the buffer
-        let data = buf.split_to(end + CRLF_LEN);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(TSimpleString::new(s.to_string()))
-    }
-
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN)
+    fn encode(self, buf: &mut BytesMut) {
+        encode_line(buf, b'+', self.0.as_bytes());
     }
 }
 
@@ -60,9 +42,10 @@ impl TSimpleString {
 
 #[cfg(test)]
 mod tests {
+    use anyhow::Result;
     use bytes::BufMut;
 
-    use crate::resp::RespFrame;
+    use crate::resp::{decode_frame, RespError, RespFrame};
 
     use super::*;
 
@@ -70,7 +53,7 @@ mod tests {
     fn test_simple_string_encode() {
         let frame: RespFrame = TSimpleString::new("OK".to_string()).into();
 
-        assert_eq!(frame.encode(), b"+OK\r\n");
+        assert_eq!(frame.to_vec(), b"+OK\r\n");
     }
 
     #[test]
@@ -78,16 +61,16 @@ mod tests {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"+OK\r\n");
 
-        let frame = TSimpleString::decode(&mut buf)?;
+        let frame = decode_frame::<TSimpleString>(&mut buf)?;
         assert_eq!(frame, TSimpleString::new("OK".to_string()));
 
         buf.extend_from_slice(b"+hello\r");
 
-        let ret = TSimpleString::decode(&mut buf);
+        let ret = decode_frame::<TSimpleString>(&mut buf);
         assert_eq!(ret.unwrap_err(), RespError::NotCompleteFrame);
 
         buf.put_u8(b'\n');
-        let frame = TSimpleString::decode(&mut buf)?;
+        let frame = decode_frame::<TSimpleString>(&mut buf)?;
         assert_eq!(frame, TSimpleString::new("hello".to_string()));
 
         Ok(())
@@ -0,0 +1,70 @@
+use bytes::BytesMut;
+
+use crate::resp::{encode_header, RespEncode, CRLF, CRLF_LEN};
+
+// a string with a three letter format hint, e.g. txt or mkd
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TVerbatimString {
+    pub(crate) format: [u8; 3],
+    pub(crate) data: Vec<u8>,
+}
+
+impl TVerbatimString {
+    pub fn new(format: [u8; 3], data: impl Into<Vec<u8>>) -> Self {
+        TVerbatimString {
+            format,
+            data: data.into(),
+        }
+    }
+
+    pub fn format(&self) -> &[u8] {
+        &self.format
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+// - verbatim string: "=<length>\r\n<encoding>:<data>\r\n", the length covers the encoding and colon
+impl RespEncode for TVerbatimString {
+    fn encode(self, buf: &mut BytesMut) {
+        let len = self.format.len() + 1 + self.data.len();
+        encode_header(buf, '=', len);
+        buf.reserve(len + CRLF_LEN);
+        buf.extend_from_slice(&self.format);
+        buf.extend_from_slice(b":");
+        buf.extend_from_slice(&self.data);
+        buf.extend_from_slice(CRLF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::resp::{decode_frame, RespFrame};
+
+    use super::*;
+
+    #[test]
+    fn test_verbatim_string_encode() {
+        let frame: RespFrame = TVerbatimString::new(*b"txt", b"Some string".to_vec()).into();
+        assert_eq!(frame.to_vec(), b"=15\r\ntxt:Some string\r\n");
+    }
+
+    #[test]
+    fn test_verbatim_string_decode() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"=15\r\ntxt:Some string\r\n");
+
+        let frame = decode_frame::<TVerbatimString>(&mut buf)?;
+        assert_eq!(frame.format(), b"txt");
+        assert_eq!(frame.data(), b"Some string");
+
+        buf.extend_from_slice(b"=5\r\ntxt-a\r\n");
+        assert!(decode_frame::<TVerbatimString>(&mut buf).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+// helpers shared by the unit tests of the command and database modules
+use anyhow::Result;
+use bytes::BytesMut;
+
+use crate::cmd::{Command, CommandExecute};
+use crate::config::Config;
+use crate::database::Database;
+use crate::resp::{decode_frame, RespFrame, TArray, TBulkString};
+
+// decodes a raw request and runs it
+pub(crate) fn run(backend: &Database, request: &[u8]) -> Result<RespFrame> {
+    let cmd: Command = decode_frame::<TArray>(&mut BytesMut::from(request))?.try_into()?;
+    Ok(cmd.execute(backend))
+}
+
+// runs a request given as its arguments, parse errors are answered like the connection does
+pub(crate) fn run_args(backend: &Database, args: &[&str]) -> RespFrame {
+    let request = args.iter().map(|arg| TBulkString::from(*arg).into());
+    match Command::try_from(TArray::new(request.collect::<Vec<_>>())) {
+        Ok(cmd) => cmd.execute(backend),
+        Err(e) => e.into(),
+    }
+}
+
+// dump and append only files in the temp dir, named after the test and the process
+pub(crate) fn temp_config(name: &str) -> Config {
+    Config {
+        dir: std::env::temp_dir(),
+        dbfilename: format!("simple-redis-{}-{}.rdb", std::process::id(), name),
+        appendfilename: format!("simple-redis-{}-{}.aof", std::process::id(), name),
+        ..Default::default()
+    }
+}